@@ -0,0 +1,63 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: rnotes [OPTIONS] [FILE]
+
+Open the notes manager, optionally starting on FILE.
+
+Options:
+  -r, --root <DIR>  Use DIR as the notes directory for this session
+      --read-only   Browse without editing, creating, renaming, deleting or pushing
+  -h, --help        Show this help and exit
+
+Environment:
+  RNOTES_CONFIG     Config file to use instead of the default location
+  RNOTES_ROOT       Notes directory for this session (overridden by --root)
+  NO_COLOR          Draw without colors when set to a non-empty value";
+
+/// Options given on the command line
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub root: Option<PathBuf>,
+    pub file: Option<PathBuf>,
+    pub read_only: bool,
+}
+
+impl CliArgs {
+    /// Parse the process arguments, returning `None` when only help was requested
+    pub fn parse() -> Result<Option<Self>> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Option<Self>> {
+        let mut parsed = CliArgs::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--read-only" => parsed.read_only = true,
+                "-r" | "--root" => {
+                    let dir = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("{} needs a directory\n\n{}", arg, USAGE))?;
+                    parsed.root = Some(PathBuf::from(dir));
+                }
+                _ if arg.starts_with("--root=") => {
+                    parsed.root = Some(PathBuf::from(&arg["--root=".len()..]));
+                }
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(anyhow::anyhow!("Unknown option '{}'\n\n{}", arg, USAGE));
+                }
+                _ => {
+                    if parsed.file.is_some() {
+                        return Err(anyhow::anyhow!("Only one file can be opened\n\n{}", USAGE));
+                    }
+                    parsed.file = Some(PathBuf::from(arg));
+                }
+            }
+        }
+
+        Ok(Some(parsed))
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use arboard::Clipboard;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// What to do with copied text when there is no system clipboard (e.g. over SSH)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardFallback {
+    /// Ask the terminal to set its clipboard with an OSC 52 escape sequence
+    #[default]
+    Osc52,
+    /// Write the text to a new file only the user can read in the temp directory
+    TempFile,
+    /// Report the failure
+    None,
+}
+
+/// Where copied text ended up
+pub enum CopyOutcome {
+    Clipboard,
+    Terminal,
+    File(PathBuf),
+}
+
+impl CopyOutcome {
+    /// Status bar message for copying `what`
+    pub fn message(&self, what: &str) -> String {
+        match self {
+            CopyOutcome::Clipboard => format!("✓ Copied {}", what),
+            CopyOutcome::Terminal => format!("✓ Copied {} through the terminal", what),
+            CopyOutcome::File(path) => format!("ℹ No clipboard available, saved {} to {}", what, path.display()),
+        }
+    }
+}
+
+/// The system clipboard, opened once at startup, with a fallback for headless sessions
+pub struct SystemClipboard {
+    // Kept open so the copied text stays available on X11 while RNotes runs
+    clipboard: Option<Clipboard>,
+    fallback: ClipboardFallback,
+}
+
+impl SystemClipboard {
+    pub fn new(fallback: ClipboardFallback) -> Self {
+        Self {
+            clipboard: Clipboard::new().ok(),
+            fallback,
+        }
+    }
+
+    /// Text on the system clipboard, if there is a clipboard and it holds text
+    pub fn paste(&mut self) -> Option<String> {
+        self.clipboard.as_mut()?.get_text().ok()
+    }
+
+    /// Copy `text`; `secret` text, such as a decrypted note, is never written to a file
+    pub fn copy(&mut self, text: &str, secret: bool) -> Result<CopyOutcome> {
+        let error = match &mut self.clipboard {
+            Some(clipboard) => match clipboard.set_text(text.to_string()) {
+                Ok(()) => return Ok(CopyOutcome::Clipboard),
+                Err(e) => anyhow::Error::new(e),
+            },
+            None => anyhow::anyhow!("No clipboard available"),
+        };
+
+        match self.fallback {
+            ClipboardFallback::Osc52 => {
+                let mut stdout = io::stdout();
+                write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+                    .and_then(|_| stdout.flush())
+                    .context("Failed to write to the terminal")?;
+                Ok(CopyOutcome::Terminal)
+            }
+            ClipboardFallback::TempFile if secret => {
+                Err(error.context("Not saving decrypted text to a file"))
+            }
+            ClipboardFallback::TempFile => {
+                let (path, mut file) = create_private_temp_file().context("Failed to create clipboard file")?;
+                file.write_all(text.as_bytes()).context("Failed to write clipboard file")?;
+                Ok(CopyOutcome::File(path))
+            }
+            ClipboardFallback::None => Err(error),
+        }
+    }
+}
+
+/// A new file in the temp directory that only the current user can read
+///
+/// The name is unique and the file must not exist yet, so another user can't
+/// have planted a file or symlink there to read the copied text.
+fn create_private_temp_file() -> io::Result<(PathBuf, File)> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut attempt = 0u32;
+    loop {
+        let name = format!("rnotes-clipboard-{}-{:x}.txt", process::id(), nanos.wrapping_add(attempt));
+        let path = env::temp_dir().join(name);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_files_are_new_and_private() {
+        let (first, _) = create_private_temp_file().unwrap();
+        let (second, _) = create_private_temp_file().unwrap();
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn secrets_are_not_written_to_a_file() {
+        let mut clipboard = SystemClipboard { clipboard: None, fallback: ClipboardFallback::TempFile };
+        assert!(clipboard.copy("passphrase-protected text", true).is_err());
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+    }
+}
@@ -0,0 +1,139 @@
+use crate::help;
+
+/// A command the palette accepts, run by pressing its key in the file tree
+pub struct PaletteCommand {
+    pub name: &'static str,
+    pub key: char,
+    pub description: &'static str,
+    /// The help section the command is listed under
+    pub section: &'static str,
+}
+
+const fn tree(name: &'static str, key: char, description: &'static str) -> PaletteCommand {
+    PaletteCommand { name, key, description, section: help::FILE_TREE }
+}
+
+const fn git(name: &'static str, key: char, description: &'static str) -> PaletteCommand {
+    PaletteCommand { name, key, description, section: help::GIT }
+}
+
+/// Every palette command, in the order they are listed here and in the help
+pub const COMMANDS: &[PaletteCommand] = &[
+    tree("edit", 'i', "Edit the selected file"),
+    tree("new", 'n', "Create a new note"),
+    tree("paste-note", 'P', "Create a note from the clipboard text"),
+    tree("folder", 'd', "Create a new folder"),
+    tree("rename", 'r', "Rename the selected file or folder"),
+    tree("delete", 'x', "Delete the selected file or folder"),
+    tree("duplicate", 'C', "Duplicate the selected note"),
+    tree("move-up", '[', "Move the selected note up by renumbering order prefixes"),
+    tree("move-down", ']', "Move the selected note down by renumbering order prefixes"),
+    tree("journal", 'T', "Open today's journal note"),
+    tree("export", 'e', "Export the note to HTML"),
+    tree("export-pdf", 'E', "Export the note to PDF"),
+    tree("export-vault", 'X', "Export the whole vault to one document"),
+    tree("search", '/', "Search the text of every note; words after it are searched for"),
+    tree("tags", 't', "Filter notes by tag"),
+    tree("backlinks", 'b', "List notes linking to this one"),
+    tree("broken-links", 'B', "List links to notes that do not exist"),
+    tree("split", 'v', "Show the source beside the preview"),
+    tree("narrower", '<', "Narrow the file tree"),
+    tree("wider", '>', "Widen the file tree"),
+    tree("relative-paths", 'R', "Show paths relative to the notes root"),
+    tree("note-counts", '#', "Show / hide the number of notes in each folder"),
+    tree("line-numbers", 'L', "Show / hide source line numbers beside the note"),
+    tree("copy-path", 'y', "Copy the absolute path"),
+    tree("copy-relative-path", 'Y', "Copy the path relative to the notes root"),
+    tree("file-manager", 'o', "Open the selected folder in the file manager"),
+    tree("open-root", 'O', "Open the notes root in the file manager"),
+    tree("vault", 'V', "Switch to another vault"),
+    tree("encrypt", 'K', "Encrypt the selected note"),
+    tree("gitignore", 'I', "Edit the .gitignore"),
+    tree("config", 'c', "Open configuration"),
+    tree("help", '?', "Show all key bindings"),
+    tree("quit", 'q', "Quit"),
+    git("push", 'g', "Commit all changes, or only the staged ones, and push"),
+    git("pull", 'p', "Pull from the remote"),
+    git("stage", 'S', "Stage files and commit only those"),
+    git("discard", 'u', "Discard uncommitted changes to the selected note"),
+    git("conflicts", 'M', "Resolve merge conflicts"),
+    git("git-error", '!', "Show why the last git operation failed"),
+];
+
+/// Commands whose name starts with `prefix`
+pub fn matching(prefix: &str) -> Vec<&'static PaletteCommand> {
+    COMMANDS.iter().filter(|command| command.name.starts_with(prefix)).collect()
+}
+
+/// The longest name every command starting with `prefix` shares, if any command does
+pub fn complete(prefix: &str) -> Option<&'static str> {
+    let matches = matching(prefix);
+    let first = matches.first()?.name;
+    let shared = matches.iter().fold(first.len(), |shared, command| {
+        first
+            .bytes()
+            .zip(command.name.bytes())
+            .take(shared)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    Some(&first[..shared])
+}
+
+/// Split typed text into a command name and the text after it
+pub fn parse(input: &str) -> (&str, Option<&str>) {
+    let input = input.trim();
+    match input.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim()).filter(|argument| !argument.is_empty())),
+        None => (input, None),
+    }
+}
+
+/// What `key` already does in the file tree, if anything
+pub fn bound_to(key: char) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .find(|command| command.key == key)
+        .map(|command| command.description)
+        .or_else(|| help::file_tree_binding(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_command_has_its_own_name_and_key() {
+        for (i, command) in COMMANDS.iter().enumerate() {
+            for other in &COMMANDS[i + 1..] {
+                assert_ne!(command.name, other.name);
+                assert_ne!(command.key, other.key, "{} and {}", command.name, other.name);
+            }
+        }
+    }
+
+    #[test]
+    fn every_command_is_in_the_help() {
+        let help: Vec<String> = help::help_lines(':')
+            .iter()
+            .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        for command in COMMANDS {
+            assert!(
+                help.iter().any(|line| line.trim_start().starts_with(command.key) && line.ends_with(command.description)),
+                "{} is missing from the help",
+                command.name
+            );
+        }
+    }
+
+    #[test]
+    fn navigation_and_command_keys_are_bound() {
+        assert!(bound_to('n').is_some());
+        assert!(bound_to('!').is_some());
+        assert!(bound_to('j').is_some());
+        assert!(bound_to(' ').is_some());
+        assert_eq!(bound_to(':'), None);
+        assert_eq!(bound_to(';'), None);
+    }
+}
@@ -1,15 +1,263 @@
 use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    ffi::OsString,
+    fs,
+    path::PathBuf,
+};
+
+use crate::clipboard::ClipboardFallback;
+use crate::markdown::MarkdownFlavor;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub root_directory: PathBuf,
+    // Notes directories to switch between; `root_directory` is the active one
+    #[serde(default)]
+    pub vaults: Vec<Vault>,
     pub editor: String,
     pub git_enabled: bool,
     pub git_repository: Option<String>,
     pub git_username: Option<String>,
     pub git_email: Option<String>,
+    #[serde(default)]
+    pub git_remote_name: Option<String>,
+    #[serde(default)]
+    pub git_branch: Option<String>,
+    #[serde(default)]
+    pub git_ssh_key: Option<PathBuf>,
+    #[serde(default)]
+    pub git_ssh_passphrase: Option<String>,
+    #[serde(default)]
+    pub max_words: Option<usize>,
+    #[serde(default)]
+    pub export_directory: Option<PathBuf>,
+    #[serde(default = "default_pdf_converter")]
+    pub pdf_converter: String,
+    #[serde(default)]
+    pub templates_directory: Option<PathBuf>,
+    #[serde(default = "default_journal_path_pattern")]
+    pub journal_path_pattern: String,
+    #[serde(default)]
+    pub journal_template: Option<PathBuf>,
+    #[serde(default)]
+    pub date_format: Option<String>,
+    // Message for commits made by RNotes, see `COMMIT_MESSAGE_PLACEHOLDERS`
+    #[serde(default)]
+    pub commit_message_template: Option<String>,
+    #[serde(default)]
+    pub monochrome: bool,
+    #[serde(default)]
+    pub inline_images: bool,
+    #[serde(default = "default_tab_width")]
+    pub tab_width: usize,
+    #[serde(default)]
+    pub markdown_flavor: MarkdownFlavor,
+    #[serde(default)]
+    pub show_line_numbers: bool,
+    #[serde(default = "default_tree_width_percent")]
+    pub tree_width_percent: u16,
+    // Key that opens the command palette from the file tree
+    #[serde(default = "default_command_palette_key")]
+    pub command_palette_key: char,
+    #[serde(default)]
+    pub natural_sort: bool,
+    #[serde(default)]
+    pub show_note_counts: bool,
+    // Sort by numeric prefixes like `01-` and hide them from the file tree
+    #[serde(default)]
+    pub order_prefixes: bool,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    // Globs for notes and folders to hide from the file tree, relative to the root
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub autocommit_interval_secs: Option<u64>,
+    #[serde(default)]
+    pub autocommit_push: bool,
+    #[serde(default)]
+    pub pull_before_push: bool,
+    #[serde(default = "default_pull_on_startup")]
+    pub pull_on_startup: bool,
+    #[serde(default)]
+    pub encryption_enabled: bool,
+    #[serde(default)]
+    pub clipboard_fallback: ClipboardFallback,
+    // Notes larger than this are rendered a chunk at a time as they are scrolled
+    #[serde(default = "default_large_note_threshold_kb")]
+    pub large_note_threshold_kb: u64,
+    #[serde(default)]
+    pub remember_recent_notes: bool,
+    // Recently opened notes of each notes directory, relative to it, most recent first
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    vault_recent_notes: BTreeMap<PathBuf, Vec<PathBuf>>,
+    // The single list older versions kept, moved into `vault_recent_notes` on loading
+    #[serde(default, rename = "recent_notes", skip_serializing)]
+    legacy_recent_notes: Vec<PathBuf>,
+    #[serde(default = "default_create_welcome_file")]
+    pub create_welcome_file: bool,
+    // Notes directories the user deleted welcome.md from, so it is not created there again
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    welcome_file_deleted_in: BTreeSet<PathBuf>,
+    // Set by older versions for whichever directory was open, moved into `welcome_file_deleted_in` on loading
+    #[serde(default, rename = "welcome_file_deleted", skip_serializing)]
+    legacy_welcome_file_deleted: bool,
+    // Session-only notes directory and the configured one it replaced
+    #[serde(skip)]
+    root_override: Option<(PathBuf, PathBuf)>,
+    // Set when a note outside every vault was opened, so its folder is left alone
+    #[serde(skip)]
+    git_suspended: bool,
+    // Why the config file could not be read, to tell the user once the UI is up
+    #[serde(skip)]
+    load_error: Option<String>,
+}
+
+/// A named notes directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vault {
+    pub name: String,
+    pub path: PathBuf,
+    // Remote this vault syncs with; Git is off in a vault without one
+    #[serde(default)]
+    pub git_repository: Option<String>,
+    #[serde(default)]
+    pub git_branch: Option<String>,
+}
+
+/// `path` typed by the user, with a leading `~` standing for the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
+    match path.strip_prefix('~') {
+        Some("") => home(),
+        Some(rest) if rest.starts_with('/') => home().join(&rest[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Range the file tree width may be adjusted within, in percent of the screen
+pub const TREE_WIDTH_RANGE: (u16, u16) = (15, 60);
+
+/// The editor set in `$VISUAL` or `$EDITOR`, falling back to vim
+fn default_editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .map(|editor| editor.trim().to_string())
+        .find(|editor| !editor.is_empty())
+        .unwrap_or_else(|| "vim".to_string())
+}
+
+/// Split `command` into words at unquoted whitespace, as `sh` would
+///
+/// Single quotes keep everything up to the next one literally, double quotes
+/// allow `\"` and `\\` inside, and a backslash outside quotes escapes the next
+/// character. Variables and globs are not expanded. `None` if a quote is not
+/// closed.
+fn split_shell_words(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+fn default_pdf_converter() -> String {
+    "pandoc".to_string()
+}
+
+fn default_journal_path_pattern() -> String {
+    "journal/{{year}}/{{date}}.md".to_string()
+}
+
+fn default_tab_width() -> usize {
+    4
+}
+
+fn default_tree_width_percent() -> u16 {
+    30
+}
+
+fn default_command_palette_key() -> char {
+    ':'
+}
+
+fn default_large_note_threshold_kb() -> u64 {
+    1024
+}
+
+fn default_pull_on_startup() -> bool {
+    true
+}
+
+fn default_create_welcome_file() -> bool {
+    true
+}
+
+/// Whether chrono understands every specifier in `format`
+pub fn is_valid_date_format(format: &str) -> bool {
+    !format.trim().is_empty()
+        && StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+/// Placeholders a commit message template may use
+pub const COMMIT_MESSAGE_PLACEHOLDERS: &[&str] = &["{{date}}", "{{count}}", "{{files}}"];
+
+/// Whether `template` is non-empty and every `{{...}}` in it is a known placeholder
+pub fn is_valid_commit_message_template(template: &str) -> bool {
+    if template.trim().is_empty() {
+        return false;
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end + 2,
+            None => return false,
+        };
+        if !COMMIT_MESSAGE_PLACEHOLDERS.contains(&&rest[start..end]) {
+            return false;
+        }
+        rest = &rest[end..];
+    }
+    true
 }
 
 impl Default for Config {
@@ -19,22 +267,89 @@ impl Default for Config {
         
         Self {
             root_directory,
-            editor: "vim".to_string(),
+            vaults: Vec::new(),
+            editor: default_editor(),
             git_enabled: false,
             git_repository: None,
             git_username: None,
             git_email: None,
+            git_remote_name: None,
+            git_branch: None,
+            git_ssh_key: None,
+            git_ssh_passphrase: None,
+            max_words: None,
+            export_directory: None,
+            pdf_converter: default_pdf_converter(),
+            templates_directory: None,
+            journal_path_pattern: default_journal_path_pattern(),
+            journal_template: None,
+            date_format: None,
+            commit_message_template: None,
+            monochrome: false,
+            inline_images: false,
+            tab_width: default_tab_width(),
+            markdown_flavor: MarkdownFlavor::default(),
+            show_line_numbers: false,
+            tree_width_percent: default_tree_width_percent(),
+            command_palette_key: default_command_palette_key(),
+            natural_sort: false,
+            show_note_counts: false,
+            order_prefixes: false,
+            follow_symlinks: false,
+            ignore_patterns: Vec::new(),
+            read_only: false,
+            autocommit_interval_secs: None,
+            autocommit_push: false,
+            pull_before_push: false,
+            pull_on_startup: default_pull_on_startup(),
+            encryption_enabled: false,
+            clipboard_fallback: ClipboardFallback::default(),
+            large_note_threshold_kb: default_large_note_threshold_kb(),
+            remember_recent_notes: false,
+            vault_recent_notes: BTreeMap::new(),
+            legacy_recent_notes: Vec::new(),
+            create_welcome_file: default_create_welcome_file(),
+            welcome_file_deleted_in: BTreeSet::new(),
+            legacy_welcome_file_deleted: false,
+            root_override: None,
+            git_suspended: false,
+            load_error: None,
         }
     }
 }
 
 impl Config {
+    /// Load the config, creating it with defaults on first run
+    ///
+    /// The file is `$RNOTES_CONFIG` if set, otherwise `rnotes/config.json` in the
+    /// platform config directory. The notes directory is `$RNOTES_ROOT` if set,
+    /// then the config file's `root_directory`, then `~/rnotes`.
     pub fn load_or_create() -> Result<Self> {
         let config_path = Self::config_file_path()?;
         
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)?;
-            let config: Config = serde_json::from_str(&content)?;
+            let mut config: Config = match serde_json::from_str(&content) {
+                Ok(config) => config,
+                Err(e) => {
+                    // A typo in a hand-edited file should not lock the user out;
+                    // keep it next to the new one so it can be fixed and restored
+                    let mut backup = config_path.clone().into_os_string();
+                    backup.push(".bak");
+                    let backup = PathBuf::from(backup);
+                    fs::rename(&config_path, &backup)?;
+
+                    let mut config = Self::create_default()?;
+                    config.load_error = Some(format!(
+                        "Config file is invalid ({}), moved it to {} and started with defaults",
+                        e,
+                        backup.display()
+                    ));
+                    return Ok(config);
+                }
+            };
+            config.adopt_legacy_settings();
+            config.apply_env_root();
             
             // Ensure the root directory exists
             if !config.root_directory.exists() {
@@ -43,33 +358,350 @@ impl Config {
             
             Ok(config)
         } else {
-            let config = Config::default();
-            
-            // Create the root directory
-            if !config.root_directory.exists() {
-                fs::create_dir_all(&config.root_directory)?;
-            }
-            
-            // Create config directory if it doesn't exist
-            if let Some(parent) = config_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            
-            config.save()?;
-            Ok(config)
+            Self::create_default()
+        }
+    }
+
+    /// Give the recent notes and deleted welcome note older versions kept for one notes directory to that directory
+    fn adopt_legacy_settings(&mut self) {
+        if !self.legacy_recent_notes.is_empty() {
+            let notes = std::mem::take(&mut self.legacy_recent_notes);
+            self.vault_recent_notes.entry(self.root_directory.clone()).or_insert(notes);
+        }
+        if std::mem::take(&mut self.legacy_welcome_file_deleted) {
+            self.welcome_file_deleted_in.insert(self.root_directory.clone());
+        }
+    }
+
+    /// Write a default config file and create its notes directory
+    fn create_default() -> Result<Self> {
+        let config_path = Self::config_file_path()?;
+        let mut config = Config::default();
+        config.apply_env_root();
+        
+        // Create the root directory
+        if !config.root_directory.exists() {
+            fs::create_dir_all(&config.root_directory)?;
+        }
+        
+        // Create config directory if it doesn't exist
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        config.save()?;
+        Ok(config)
+    }
+
+    /// Why the config file had to be replaced with defaults, if it was
+    pub fn take_load_error(&mut self) -> Option<String> {
+        self.load_error.take()
+    }
+    
+    fn apply_env_root(&mut self) {
+        self.apply_root_variable(env::var_os("RNOTES_ROOT"));
+    }
+
+    /// Use the value of `RNOTES_ROOT` as the notes directory, unless it is unset or empty
+    fn apply_root_variable(&mut self, root: Option<OsString>) {
+        if let Some(root) = root.filter(|v| !v.is_empty()) {
+            self.override_root(PathBuf::from(root));
+        }
+    }
+
+    /// Use `root` as the notes directory for this session without saving it
+    pub fn override_root(&mut self, root: PathBuf) {
+        let root = if root.is_absolute() {
+            root
+        } else {
+            env::current_dir().map(|dir| dir.join(&root)).unwrap_or(root)
+        };
+        let configured = match self.root_override.take() {
+            Some((_, configured)) => configured,
+            None => self.root_directory.clone(),
+        };
+        self.root_directory = root.clone();
+        self.root_override = Some((root, configured));
+    }
+
+    /// Turn Git off for this session without saving it, or back on with `false`
+    pub fn suspend_git(&mut self, suspended: bool) {
+        self.git_suspended = suspended;
+    }
+
+    /// The vault whose directory is the active notes directory, if any
+    pub fn active_vault(&self) -> Option<&Vault> {
+        self.vaults.iter().find(|vault| vault.path == self.root_directory)
+    }
+
+    /// The settings Git should use for the active notes directory
+    ///
+    /// A vault uses its own remote and branch instead of the global ones, so
+    /// switching vaults never pushes one vault's notes to another's remote.
+    pub fn git_settings(&self) -> Config {
+        let mut config = self.clone();
+        config.git_enabled = self.git_enabled && !self.git_suspended;
+        if let Some(vault) = self.active_vault() {
+            config.git_enabled &= vault.git_repository.is_some();
+            config.git_repository = vault.git_repository.clone();
+            config.git_branch = vault.git_branch.clone();
+        }
+        config
+    }
+
+    /// A copy with the settings in `value`, a `Config` serialized and then edited
+    pub fn with_settings(&self, value: serde_json::Value) -> Result<Self> {
+        let mut config: Config = serde_json::from_value(value)?;
+        config.root_override = self.root_override.clone();
+        config.git_suspended = self.git_suspended;
+        config.vault_recent_notes = self.vault_recent_notes.clone();
+        config.welcome_file_deleted_in = self.welcome_file_deleted_in.clone();
+        Ok(config)
+    }
+
+    /// Recently opened notes of the current notes directory, relative to it, most recent first
+    pub fn recent_notes(&self) -> &[PathBuf] {
+        self.vault_recent_notes.get(&self.root_directory).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Remember `notes` as the recently opened notes of the current notes directory
+    pub fn set_recent_notes(&mut self, notes: Vec<PathBuf>) {
+        if notes.is_empty() {
+            self.vault_recent_notes.remove(&self.root_directory);
+        } else {
+            self.vault_recent_notes.insert(self.root_directory.clone(), notes);
+        }
+    }
+
+    /// Whether the user deleted welcome.md from the current notes directory
+    pub fn welcome_file_deleted(&self) -> bool {
+        self.welcome_file_deleted_in.contains(&self.root_directory)
+    }
+
+    /// Keep welcome.md from being created in the current notes directory again
+    pub fn mark_welcome_file_deleted(&mut self) {
+        self.welcome_file_deleted_in.insert(self.root_directory.clone());
+    }
+
+    /// Templates directory, with relative paths resolved against the notes root
+    pub fn templates_path(&self) -> Option<PathBuf> {
+        self.templates_directory.as_ref().map(|dir| self.root_directory.join(dir))
+    }
+    
+    /// The editor program and its arguments, split like a shell would
+    ///
+    /// `None` if the setting has an unclosed quote.
+    pub fn editor_command(&self) -> Option<Vec<String>> {
+        split_shell_words(&self.editor)
+    }
+
+    /// The configured strftime date format, or `None` if unset or invalid
+    pub fn date_format(&self) -> Option<&str> {
+        self.date_format.as_deref().filter(|format| is_valid_date_format(format))
+    }
+
+    /// The key that opens the command palette, or `:` if the configured one already does something else
+    pub fn command_palette_key(&self) -> char {
+        match crate::commands::bound_to(self.command_palette_key) {
+            Some(_) => default_command_palette_key(),
+            None => self.command_palette_key,
         }
     }
+
+    /// The configured commit message template, or `None` if unset or invalid
+    pub fn commit_message_template(&self) -> Option<&str> {
+        self.commit_message_template.as_deref().filter(|template| is_valid_commit_message_template(template))
+    }
+
+    /// Whether to draw without colors, set by the config or a non-empty `NO_COLOR`
+    pub fn monochrome(&self) -> bool {
+        self.monochrome || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+    }
+
+    /// File tree width in percent, clamped to `TREE_WIDTH_RANGE`
+    pub fn tree_width(&self) -> u16 {
+        self.tree_width_percent.clamp(TREE_WIDTH_RANGE.0, TREE_WIDTH_RANGE.1)
+    }
     
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_file_path()?;
-        let content = serde_json::to_string_pretty(self)?;
+        let content = serde_json::to_string_pretty(&self.saved())?;
         fs::write(config_path, content)?;
         Ok(())
     }
+
+    /// The config as written to the file, keeping a session-only notes directory out of it unless the user changed it
+    fn saved(&self) -> Config {
+        let mut saved = self.clone();
+        if let Some((root, configured)) = &self.root_override {
+            if saved.root_directory == *root {
+                saved.root_directory = configured.clone();
+            }
+        }
+        saved
+    }
     
     fn config_file_path() -> Result<PathBuf> {
+        Self::config_file_path_from(env::var_os("RNOTES_CONFIG"))
+    }
+
+    /// The config file, `custom` (the value of `RNOTES_CONFIG`) unless it is unset or empty
+    fn config_file_path_from(custom: Option<OsString>) -> Result<PathBuf> {
+        if let Some(path) = custom.filter(|v| !v.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Unable to find config directory"))?;
         Ok(config_dir.join("rnotes").join("config.json"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(path: &str, remote: Option<&str>) -> Vault {
+        Vault {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            git_repository: remote.map(str::to_string),
+            git_branch: None,
+        }
+    }
+
+    fn config_with_vaults() -> Config {
+        Config {
+            root_directory: PathBuf::from("/notes/work"),
+            git_enabled: true,
+            git_repository: Some("git@example.com:global.git".to_string()),
+            vaults: vec![
+                vault("/notes/work", Some("git@example.com:work.git")),
+                vault("/notes/personal", None),
+            ],
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn git_settings_use_the_active_vaults_remote() {
+        let git = config_with_vaults().git_settings();
+        assert!(git.git_enabled);
+        assert_eq!(git.git_repository.as_deref(), Some("git@example.com:work.git"));
+    }
+
+    #[test]
+    fn git_is_off_in_a_vault_without_a_remote() {
+        let mut config = config_with_vaults();
+        config.root_directory = PathBuf::from("/notes/personal");
+        let git = config.git_settings();
+        assert!(!git.git_enabled);
+        assert_eq!(git.git_repository, None);
+    }
+
+    #[test]
+    fn git_settings_are_global_outside_the_vault_list() {
+        let mut config = config_with_vaults();
+        config.root_directory = PathBuf::from("/notes/other");
+        let git = config.git_settings();
+        assert!(git.git_enabled);
+        assert_eq!(git.git_repository.as_deref(), Some("git@example.com:global.git"));
+    }
+
+    #[test]
+    fn a_leading_tilde_is_the_home_directory() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~/notes/export.md"), home.join("notes/export.md"));
+        assert_eq!(expand_home("/tmp/~/x"), PathBuf::from("/tmp/~/x"));
+        assert_eq!(expand_home("~user/x"), PathBuf::from("~user/x"));
+    }
+
+    #[test]
+    fn editor_commands_are_split_like_a_shell() {
+        let words = |command: &str| split_shell_words(command).map(|words| words.join("|"));
+        assert_eq!(words("code --wait").as_deref(), Some("code|--wait"));
+        assert_eq!(words("  vim  ").as_deref(), Some("vim"));
+        assert_eq!(words("'/Applications/Sublime Text/subl' -w").as_deref(), Some("/Applications/Sublime Text/subl|-w"));
+        assert_eq!(words(r#""C:\Program Files\ed.exe" --x="a b""#).as_deref(), Some(r"C:\Program Files\ed.exe|--x=a b"));
+        assert_eq!(words(r"my\ editor ''").as_deref(), Some("my editor|"));
+        assert_eq!(words(r#"emacs -nw "\"quoted\"""#).as_deref(), Some(r#"emacs|-nw|"quoted""#));
+        assert_eq!(words("vim 'unclosed"), None);
+        assert_eq!(words("vim \"unclosed"), None);
+    }
+
+    #[test]
+    fn rnotes_config_overrides_the_config_file() {
+        let custom = Config::config_file_path_from(Some("/tmp/work.json".into())).unwrap();
+        assert_eq!(custom, PathBuf::from("/tmp/work.json"));
+        // An empty variable counts as unset
+        for unset in [None, Some(OsString::new())] {
+            assert!(Config::config_file_path_from(unset).unwrap().ends_with("rnotes/config.json"));
+        }
+    }
+
+    #[test]
+    fn rnotes_root_overrides_the_config_file_for_the_session() {
+        let mut config = Config { root_directory: PathBuf::from("/notes/configured"), ..Config::default() };
+
+        config.apply_root_variable(Some(OsString::new()));
+        assert_eq!(config.root_directory, PathBuf::from("/notes/configured"));
+
+        config.apply_root_variable(Some("/notes/from-env".into()));
+        assert_eq!(config.root_directory, PathBuf::from("/notes/from-env"));
+        assert_eq!(config.saved().root_directory, PathBuf::from("/notes/configured"));
+
+        // A root the user picks afterwards is saved
+        config.root_directory = PathBuf::from("/notes/picked");
+        assert_eq!(config.saved().root_directory, PathBuf::from("/notes/picked"));
+
+        config.apply_root_variable(Some("relative".into()));
+        assert_eq!(config.root_directory, env::current_dir().unwrap().join("relative"));
+    }
+
+    #[test]
+    fn suspended_git_stays_off() {
+        let mut config = config_with_vaults();
+        config.suspend_git(true);
+        assert!(!config.git_settings().git_enabled);
+        config.suspend_git(false);
+        assert!(config.git_settings().git_enabled);
+    }
+
+    #[test]
+    fn recent_notes_are_kept_per_notes_directory() {
+        // Written by a version that kept one list for whichever directory was open
+        let mut old = serde_json::to_value(Config::default()).unwrap();
+        old["root_directory"] = serde_json::json!("/notes/work");
+        old["recent_notes"] = serde_json::json!(["a.md", "b.md"]);
+        let mut config: Config = serde_json::from_value(old).unwrap();
+        config.adopt_legacy_settings();
+        assert_eq!(config.recent_notes(), [PathBuf::from("a.md"), PathBuf::from("b.md")]);
+
+        config.root_directory = PathBuf::from("/notes/home");
+        assert!(config.recent_notes().is_empty());
+        config.set_recent_notes(vec![PathBuf::from("c.md")]);
+
+        let saved: Config = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved.recent_notes(), [PathBuf::from("c.md")]);
+        let work = Config { root_directory: PathBuf::from("/notes/work"), ..saved };
+        assert_eq!(work.recent_notes(), [PathBuf::from("a.md"), PathBuf::from("b.md")]);
+    }
+
+    #[test]
+    fn a_deleted_welcome_note_is_remembered_per_notes_directory() {
+        let mut old = serde_json::to_value(Config::default()).unwrap();
+        old["root_directory"] = serde_json::json!("/notes/work");
+        old["welcome_file_deleted"] = serde_json::json!(true);
+        let mut config: Config = serde_json::from_value(old).unwrap();
+        config.adopt_legacy_settings();
+        assert!(config.welcome_file_deleted());
+
+        config.root_directory = PathBuf::from("/notes/home");
+        assert!(!config.welcome_file_deleted());
+        config.mark_welcome_file_deleted();
+        let saved: Config = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert!(saved.welcome_file_deleted());
+        assert!(Config { root_directory: PathBuf::from("/notes/work"), ..saved }.welcome_file_deleted());
+    }
+}
@@ -0,0 +1,88 @@
+use age::secrecy::SecretString;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    io::{Read, Write},
+    iter,
+    path::{Path, PathBuf},
+};
+
+use crate::file_tree;
+
+/// Extension added to a note's own when it is encrypted, e.g. `todo.md.age`
+pub const ENCRYPTED_EXTENSION: &str = "age";
+
+/// Whether `path` is a passphrase-encrypted markdown note
+pub fn is_encrypted_note(path: &Path) -> bool {
+    let encrypted = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(ENCRYPTED_EXTENSION));
+    encrypted && path.file_stem().is_some_and(|stem| file_tree::is_markdown_file(Path::new(stem)))
+}
+
+/// Path of the encrypted counterpart of a plain markdown note
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(ENCRYPTED_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Decrypt a note in memory
+pub fn decrypt_note(path: &Path, passphrase: &str) -> Result<String> {
+    let encrypted = fs::read(path).context("Failed to read encrypted note")?;
+    let decryptor = age::Decryptor::new_buffered(&encrypted[..]).context("Not an age encrypted file")?;
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_owned()));
+
+    let mut reader = match decryptor.decrypt(iter::once(&identity as &dyn age::Identity)) {
+        Ok(reader) => reader,
+        Err(age::DecryptError::DecryptionFailed | age::DecryptError::NoMatchingKeys) => {
+            return Err(anyhow::anyhow!("Wrong passphrase"));
+        }
+        Err(e) => return Err(e).context("Failed to decrypt note"),
+    };
+
+    let mut plaintext = String::new();
+    reader.read_to_string(&mut plaintext).context("Decrypted note is not valid UTF-8")?;
+    Ok(plaintext)
+}
+
+/// Encrypt `plaintext` and write it to `path`; only ciphertext ever touches the disk
+pub fn encrypt_note(path: &Path, plaintext: &str, passphrase: &str) -> Result<()> {
+    let encryptor = age::Encryptor::with_user_passphrase(SecretString::from(passphrase.to_owned()));
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut encrypted).context("Failed to start encryption")?;
+    writer.write_all(plaintext.as_bytes()).context("Failed to encrypt note")?;
+    writer.finish().context("Failed to finish encryption")?;
+
+    // Write next to the target and rename so a crash never leaves a truncated note
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    fs::write(&temp_path, &encrypted).context("Failed to write encrypted note")?;
+    fs::rename(&temp_path, path).context("Failed to replace encrypted note")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_notes_keep_their_markdown_extension() {
+        for name in ["todo.md", "todo.markdown", "todo.mdown", "todo.mkd"] {
+            let encrypted = encrypted_path(Path::new(name));
+            assert_eq!(encrypted, PathBuf::from(format!("{}.age", name)));
+            assert!(is_encrypted_note(&encrypted), "{} not recognized", encrypted.display());
+        }
+    }
+
+    #[test]
+    fn only_age_encrypted_markdown_is_an_encrypted_note() {
+        assert!(is_encrypted_note(Path::new("notes/Plan.MD.AGE")));
+        assert!(!is_encrypted_note(Path::new("todo.md")));
+        assert!(!is_encrypted_note(Path::new("photo.png.age")));
+        assert!(!is_encrypted_note(Path::new("key.age")));
+    }
+}
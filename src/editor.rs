@@ -0,0 +1,289 @@
+/// A plain text buffer with a cursor, for quick edits without an external editor
+pub struct TextEditor {
+    lines: Vec<String>,
+    // Cursor position; the column counts characters, not bytes
+    row: usize,
+    col: usize,
+    // First line and column shown, kept so the cursor stays visible
+    scroll_row: usize,
+    scroll_col: usize,
+    trailing_newline: bool,
+    modified: bool,
+}
+
+impl TextEditor {
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self {
+            lines,
+            row: 0,
+            col: 0,
+            scroll_row: 0,
+            scroll_col: 0,
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+            modified: false,
+        }
+    }
+
+    /// The edited text, ending in a newline if the original did
+    pub fn text(&self) -> String {
+        let mut text = self.lines.join("\n");
+        if self.trailing_newline {
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+    }
+
+    /// Cursor line and column, both 0-based
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Put the cursor on `row`, keeping the column where the line allows
+    pub fn go_to_line(&mut self, row: usize) {
+        self.row = row.min(self.lines.len() - 1);
+        self.clamp_col();
+    }
+
+    /// Scroll so the cursor is inside a `width` by `height` view, returning the top-left corner
+    pub fn scroll_to_cursor(&mut self, width: usize, height: usize) -> (usize, usize) {
+        if self.row < self.scroll_row {
+            self.scroll_row = self.row;
+        } else if height > 0 && self.row >= self.scroll_row + height {
+            self.scroll_row = self.row + 1 - height;
+        }
+        if self.col < self.scroll_col {
+            self.scroll_col = self.col;
+        } else if width > 0 && self.col >= self.scroll_col + width {
+            self.scroll_col = self.col + 1 - width;
+        }
+        (self.scroll_row, self.scroll_col)
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index();
+        self.lines[self.row].insert(index, c);
+        self.col += 1;
+        self.modified = true;
+    }
+
+    pub fn insert_newline(&mut self) {
+        let index = self.byte_index();
+        let rest = self.lines[self.row].split_off(index);
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.col = 0;
+        self.modified = true;
+    }
+
+    /// Delete the character before the cursor, joining lines at the start of one
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.lines[self.row].chars().count();
+            self.lines[self.row].push_str(&line);
+        } else {
+            return;
+        }
+        self.modified = true;
+    }
+
+    /// Delete the character under the cursor, joining lines at the end of one
+    pub fn delete(&mut self) {
+        if self.col < self.line_len() {
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        } else {
+            return;
+        }
+        self.modified = true;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn move_up(&mut self, rows: usize) {
+        self.row = self.row.saturating_sub(rows);
+        self.clamp_col();
+    }
+
+    pub fn move_down(&mut self, rows: usize) {
+        self.row = (self.row + rows).min(self.lines.len() - 1);
+        self.clamp_col();
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.line_len();
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn clamp_col(&mut self) {
+        self.col = self.col.min(self.line_len());
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices().nth(self.col).map_or(line.len(), |(index, _)| index)
+    }
+}
+
+/// A single line of text with a cursor, for prompts like renaming
+#[derive(Debug, Default, Clone)]
+pub struct LineInput {
+    text: String,
+    // Cursor position in characters, not bytes
+    cursor: usize,
+}
+
+impl LineInput {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Replace the text, putting the cursor at its end
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.chars().count();
+        self.text = text;
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    /// Cursor position in characters
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The text with `mark` inserted where the cursor is
+    pub fn with_cursor(&self, mark: char) -> String {
+        let mut text = self.text.clone();
+        text.insert(self.byte_index(), mark);
+        text
+    }
+
+    /// Every character shown as `mask`, with `mark` at the cursor, for secrets
+    pub fn masked_with_cursor(&self, mask: char, mark: char) -> String {
+        let length = self.text.chars().count();
+        let mut text: String = std::iter::repeat_n(mask, length).collect();
+        text.insert(self.cursor.min(length) * mask.len_utf8(), mark);
+        text
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index();
+        self.text.insert(index, c);
+        self.cursor += 1;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        let index = self.byte_index();
+        self.text.insert_str(index, text);
+        self.cursor += text.chars().count();
+    }
+
+    /// Delete the character before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let index = self.byte_index();
+            self.text.remove(index);
+        }
+    }
+
+    /// Delete the character under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let index = self.byte_index();
+            self.text.remove(index);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    fn byte_index(&self) -> usize {
+        self.text.char_indices().nth(self.cursor).map_or(self.text.len(), |(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_masked_with_the_cursor_in_place() {
+        let mut input = LineInput::default();
+        input.set("pässword".to_string());
+        input.move_left();
+        input.move_left();
+        assert_eq!(input.masked_with_cursor('*', '|'), "******|**");
+        assert_eq!(input.with_cursor('|'), "pässwo|rd");
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use pulldown_cmark::{html, Parser};
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use crate::markdown::MarkdownFlavor;
+
+const HTML_STYLE: &str = "body { max-width: 48em; margin: 2em auto; padding: 0 1em; font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; line-height: 1.6; color: #24292f; }
+h1, h2 { border-bottom: 1px solid #d0d7de; padding-bottom: .3em; }
+code { background: #f6f8fa; padding: .2em .4em; border-radius: 4px; font-size: 90%; }
+pre { background: #f6f8fa; padding: 1em; border-radius: 6px; overflow: auto; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; color: #57606a; border-left: .25em solid #d0d7de; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: .4em .8em; }
+img { max-width: 100%; }";
+
+// Start each note of a vault export on a new page when printed
+const VAULT_HTML_STYLE: &str = "section.note { page-break-before: always; }
+h1.note-path { color: #57606a; font-size: 1.2em; }";
+
+/// Work out where an exported copy of `source` should be written
+fn export_path(source: &Path, export_dir: Option<&Path>, extension: &str) -> Result<PathBuf> {
+    let stem = source
+        .file_stem()
+        .ok_or_else(|| anyhow::anyhow!("Invalid note file name"))?;
+
+    let target_dir = match export_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir).context("Failed to create export directory")?;
+            dir.to_path_buf()
+        }
+        None => source.parent().unwrap_or(Path::new(".")).to_path_buf(),
+    };
+
+    let mut file_name = stem.to_os_string();
+    file_name.push(".");
+    file_name.push(extension);
+    Ok(target_dir.join(file_name))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Convert a note to a standalone HTML document and write it to disk
+pub fn export_html(source: &Path, markdown: &str, export_dir: Option<&Path>, flavor: MarkdownFlavor) -> Result<PathBuf> {
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, flavor.options()));
+
+    let title = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let document = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&title),
+        HTML_STYLE,
+        body
+    );
+
+    let output_path = export_path(source, export_dir, "html")?;
+    fs::write(&output_path, document).context("Failed to write HTML export")?;
+
+    Ok(output_path)
+}
+
+/// Convert a note to PDF by running an external converter (`<converter> <source> -o <output>`)
+pub fn export_pdf(source: &Path, export_dir: Option<&Path>, converter: &str) -> Result<PathBuf> {
+    let output_path = export_path(source, export_dir, "pdf")?;
+
+    let output = match Command::new(converter)
+        .arg(source)
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(anyhow::anyhow!(
+                "'{}' not found. Install it or set pdf_converter in the config",
+                converter
+            ));
+        }
+        Err(e) => return Err(e).context(format!("Failed to run '{}'", converter)),
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(anyhow::anyhow!("'{}' exited with {}: {}", converter, output.status, reason));
+    }
+
+    Ok(output_path)
+}
+
+/// Check that `output` may receive an export of the vault at `root`
+///
+/// It must be an absolute path outside the vault, so the export never ends up
+/// in the notes it was made from, and not a folder.
+pub fn check_vault_export_target(root: &Path, output: &Path) -> Result<()> {
+    if !output.is_absolute() {
+        anyhow::bail!("{} is not an absolute path", output.display());
+    }
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    if resolve(output).starts_with(&root) {
+        anyhow::bail!("{} is inside the vault", output.display());
+    }
+    if output.is_dir() {
+        anyhow::bail!("{} is a folder", output.display());
+    }
+    Ok(())
+}
+
+/// `path` with `..` removed and its longest existing ancestor resolved through symlinks
+fn resolve(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    for ancestor in normalized.ancestors() {
+        if let Ok(resolved) = ancestor.canonicalize() {
+            return resolved.join(normalized.strip_prefix(ancestor).unwrap_or(Path::new("")));
+        }
+    }
+    normalized
+}
+
+/// Write every note in `notes` into one document at `output`, with a table of contents
+///
+/// The document is HTML if `output` ends in `.html` or `.htm` and markdown
+/// otherwise. An existing `output` is only replaced if `overwrite` is set.
+/// Notes are read and written one at a time so a large vault is never held in
+/// memory. Returns the number of notes written.
+pub fn export_vault(root: &Path, notes: &[PathBuf], output: &Path, flavor: MarkdownFlavor, overwrite: bool) -> Result<usize> {
+    check_vault_export_target(root, output)?;
+    let as_html = output
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+
+    let names: Vec<String> = notes
+        .iter()
+        .map(|note| note.strip_prefix(root).unwrap_or(note).to_string_lossy().to_string())
+        .collect();
+    let title = root
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Notes".to_string());
+
+    if let Some(parent) = output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).context("Failed to create export directory")?;
+    }
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(overwrite)
+        .truncate(overwrite)
+        .create_new(!overwrite)
+        .open(output)
+        .context("Failed to create the export file")?;
+    let mut writer = BufWriter::new(file);
+
+    if as_html {
+        write!(
+            writer,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n{}\n</style>\n</head>\n<body>\n<nav>\n<h1>{}</h1>\n<h2>Contents</h2>\n<ol>\n",
+            escape_html(&title),
+            HTML_STYLE,
+            VAULT_HTML_STYLE,
+            escape_html(&title)
+        )?;
+        for (i, name) in names.iter().enumerate() {
+            writeln!(writer, "<li><a href=\"#note-{}\">{}</a></li>", i + 1, escape_html(name))?;
+        }
+        writeln!(writer, "</ol>\n</nav>")?;
+    } else {
+        writeln!(writer, "# {}\n\n## Contents\n", title)?;
+        for (i, name) in names.iter().enumerate() {
+            writeln!(writer, "{}. [{}](#note-{})", i + 1, name, i + 1)?;
+        }
+    }
+
+    for (i, (note, name)) in notes.iter().zip(&names).enumerate() {
+        let markdown = fs::read_to_string(note).with_context(|| format!("Failed to read {}", name))?;
+        if as_html {
+            let mut body = String::new();
+            html::push_html(&mut body, Parser::new_ext(&markdown, flavor.options()));
+            write!(
+                writer,
+                "<section class=\"note\">\n<h1 class=\"note-path\" id=\"note-{}\">{}</h1>\n{}</section>\n",
+                i + 1,
+                escape_html(name),
+                body
+            )?;
+        } else {
+            write!(writer, "\n---\n\n<a id=\"note-{}\"></a>\n\n# {}\n\n{}", i + 1, name, markdown)?;
+            if !markdown.ends_with('\n') {
+                writeln!(writer)?;
+            }
+        }
+    }
+
+    if as_html {
+        writeln!(writer, "</body>\n</html>")?;
+    }
+    writer.flush().context("Failed to write the export file")?;
+
+    Ok(notes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("notes")).unwrap();
+        fs::write(dir.path().join("notes/a.md"), "# A").unwrap();
+        dir
+    }
+
+    #[test]
+    fn export_targets_must_be_absolute_and_outside_the_vault() {
+        let dir = vault();
+        let root = dir.path().join("notes");
+        assert!(check_vault_export_target(&root, &dir.path().join("export.md")).is_ok());
+        assert!(check_vault_export_target(&root, Path::new("export.md")).is_err());
+        assert!(check_vault_export_target(&root, &root.join("export.md")).is_err());
+        assert!(check_vault_export_target(&root, &root.join("new/folder/export.md")).is_err());
+        assert!(check_vault_export_target(&root, &dir.path().join("other/../notes/export.md")).is_err());
+        assert!(check_vault_export_target(&root, dir.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn export_targets_through_a_symlink_into_the_vault_are_refused() {
+        let dir = vault();
+        let root = dir.path().join("notes");
+        std::os::unix::fs::symlink(&root, dir.path().join("link")).unwrap();
+        assert!(check_vault_export_target(&root, &dir.path().join("link/export.md")).is_err());
+    }
+
+    #[test]
+    fn existing_exports_are_only_replaced_when_asked() {
+        let dir = vault();
+        let root = dir.path().join("notes");
+        let notes = vec![root.join("a.md")];
+        let output = dir.path().join("export.md");
+        fs::write(&output, "old").unwrap();
+
+        assert!(export_vault(&root, &notes, &output, MarkdownFlavor::default(), false).is_err());
+        assert_eq!(fs::read_to_string(&output).unwrap(), "old");
+
+        assert_eq!(export_vault(&root, &notes, &output, MarkdownFlavor::default(), true).unwrap(), 1);
+        assert!(fs::read_to_string(&output).unwrap().contains("# A"));
+    }
+}
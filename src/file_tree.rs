@@ -1,6 +1,15 @@
 use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use ratatui::widgets::ListState;
-use std::{fs, path::PathBuf};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::Config;
+use crate::crypto;
 
 #[derive(Debug, Clone)]
 pub struct TreeItem {
@@ -8,94 +17,185 @@ pub struct TreeItem {
     pub display_name: String,
     pub is_expanded: bool,
     pub is_dir: bool,
+    // Shown after the name: the symlink marker and the note count
+    pub suffix: String,
 }
 
 pub struct FileTree {
     items: Vec<TreeItem>,
     state: ListState,
     root_dir: PathBuf,
+    // When set, the tree shows only these files as a flat list
+    filter: Option<Vec<PathBuf>>,
+    // Label entries by their path relative to root instead of indenting them
+    relative_paths: bool,
+    // List `.md.age` notes; only when encryption is enabled
+    show_encrypted: bool,
+    // Sort names case-insensitively with numbers in numeric order
+    natural_sort: bool,
+    // List symlinked directories and expand them like regular ones
+    follow_symlinks: bool,
+    // Entries hidden from the tree, matched against paths relative to root
+    ignore_patterns: GlobSet,
+    // Configured ignore patterns that are not valid globs
+    invalid_ignore_patterns: Vec<String>,
+    // Show how many notes each folder holds, counting subfolders
+    show_note_counts: bool,
+    // Notes under each folder, counted once per rebuild when they are shown
+    note_counts: HashMap<PathBuf, usize>,
+    // Sort by numeric prefixes like `01-` and leave them out of the names
+    order_prefixes: bool,
 }
 
+/// Extensions of the files shown and rendered as markdown notes
+pub const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "mkd"];
+
+/// Deepest folder nesting the tree descends into
+const MAX_TREE_DEPTH: usize = 64;
+
+/// Appended to the names of symbolic links
+const SYMLINK_MARKER: &str = " ↪";
+
 impl FileTree {
-    pub fn new(root_dir: &PathBuf) -> Result<Self> {
+    /// The tree of the config's notes directory, listed the way the config sets
+    pub fn new(config: &Config) -> Result<Self> {
         let mut tree = FileTree {
             items: Vec::new(),
             state: ListState::default(),
-            root_dir: root_dir.clone(),
+            root_dir: config.root_directory.clone(),
+            filter: None,
+            relative_paths: false,
+            show_encrypted: false,
+            natural_sort: false,
+            follow_symlinks: false,
+            ignore_patterns: GlobSet::empty(),
+            invalid_ignore_patterns: Vec::new(),
+            show_note_counts: false,
+            note_counts: HashMap::new(),
+            order_prefixes: false,
         };
-        
-        tree.build_tree()?;
-        
-        if !tree.items.is_empty() {
-            tree.state.select(Some(0));
-        }
-        
+        tree.apply_config(config)?;
         Ok(tree)
     }
-    
+
     fn build_tree(&mut self) -> Result<()> {
+        self.populate_items(&mut Vec::new())
+    }
+
+    fn populate_items(&mut self, expanded_dirs: &mut Vec<PathBuf>) -> Result<()> {
         self.items.clear();
         let root_dir = self.root_dir.clone();
+
+        if let Some(filter) = &self.filter {
+            // Flat list of matching files, labelled by their path relative to root
+            let mut files: Vec<PathBuf> = filter.iter().filter(|p| p.is_file()).cloned().collect();
+            if self.natural_sort {
+                files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+            } else {
+                files.sort();
+            }
+            for path in files {
+                let relative = path.strip_prefix(&root_dir).unwrap_or(&path);
+                self.items.push(TreeItem {
+                    display_name: format!("  {}", relative.to_string_lossy()),
+                    path,
+                    is_expanded: false,
+                    is_dir: false,
+                    suffix: String::new(),
+                });
+            }
+            return Ok(());
+        }
+
         if root_dir.exists() && root_dir.is_dir() {
-            self.add_directory_contents(&root_dir, 0, &mut Vec::new())?;
+            // Hide files the vault's .gitignore excludes
+            let repo = git2::Repository::open(&root_dir).ok();
+            let mut ancestors: HashSet<PathBuf> = fs::canonicalize(&root_dir).into_iter().collect();
+            let mut note_counts = HashMap::new();
+            if self.show_note_counts {
+                self.count_notes(&root_dir, 0, &mut ancestors, repo.as_ref(), &mut note_counts);
+            }
+            self.note_counts = note_counts;
+            self.add_directory_contents(&root_dir, 0, expanded_dirs, &mut ancestors, repo.as_ref())?;
         }
         Ok(())
     }
-    
-    fn add_directory_contents(&mut self, dir: &PathBuf, depth: usize, expanded_dirs: &mut Vec<PathBuf>) -> Result<()> {
-        let mut entries: Vec<_> = fs::read_dir(dir)?
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                let path = entry.path();
-                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                
-                // Filter out .git directory and other hidden directories/files starting with .
-                if file_name.starts_with('.') {
-                    return false;
-                }
-                
-                // Show directories, markdown files, and image files
-                if path.is_dir() {
-                    return true;
-                }
-                
-                if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                    let ext_lower = ext.to_lowercase();
-                    return ext_lower == "md" || 
-                           ext_lower == "png" || 
-                           ext_lower == "jpg" || 
-                           ext_lower == "jpeg" || 
-                           ext_lower == "gif" || 
-                           ext_lower == "bmp" || 
-                           ext_lower == "webp" || 
-                           ext_lower == "svg";
-                }
-                
-                false
-            })
-            .collect();
 
-        // Sort entries: directories first, then files, both alphabetically
-        entries.sort_by(|a, b| {
-            let a_path = a.path();
-            let b_path = b.path();
-            
-            match (a_path.is_dir(), b_path.is_dir()) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a_path.file_name().cmp(&b_path.file_name()),
-            }
-        });
+    /// Restrict the tree to the given files, or restore the full tree with `None`
+    pub fn set_filter(&mut self, filter: Option<Vec<PathBuf>>) -> Result<()> {
+        self.filter = filter;
+        self.build_tree()?;
+        self.state.select(if self.items.is_empty() { None } else { Some(0) });
+        Ok(())
+    }
+
+    /// Take the listing settings from `config` and rebuild the tree once, keeping expanded folders and the selection
+    pub fn apply_config(&mut self, config: &Config) -> Result<()> {
+        (self.ignore_patterns, self.invalid_ignore_patterns) = ignore_set(&config.ignore_patterns);
+        self.show_encrypted = config.encryption_enabled;
+        self.natural_sort = config.natural_sort;
+        self.follow_symlinks = config.follow_symlinks;
+        self.show_note_counts = config.show_note_counts;
+        self.order_prefixes = config.order_prefixes;
+
+        let expanded_dirs = self.get_expansion_state();
+        let selected_path = self.get_selected_path().cloned();
+        self.refresh_with_state(expanded_dirs, selected_path)
+    }
+
+    /// The configured ignore patterns that are not valid globs, and so hide nothing
+    pub fn invalid_ignore_patterns(&self) -> &[String] {
+        &self.invalid_ignore_patterns
+    }
+
+    /// Show or hide the number of notes after each folder name
+    pub fn set_show_note_counts(&mut self, show: bool) -> Result<()> {
+        if self.show_note_counts != show {
+            self.show_note_counts = show;
+            let expanded_dirs = self.get_expansion_state();
+            let selected_path = self.get_selected_path().cloned();
+            self.refresh_with_state(expanded_dirs, selected_path)?;
+        }
+        Ok(())
+    }
+
+    pub fn shows_note_counts(&self) -> bool {
+        self.show_note_counts
+    }
+
+    /// Whether `path` matches an ignore pattern; folders also match patterns like `archive/**`
+    fn is_ignored(&self, path: &Path) -> bool {
+        is_ignored_by(&self.ignore_patterns, &self.root_dir, path)
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.filter.is_some()
+    }
+    
+    /// Add the entries of `dir`, descending into expanded folders
+    ///
+    /// `ancestors` holds the canonical paths of the folders above `dir`, so a
+    /// symlink pointing back up the tree is listed but never expanded.
+    fn add_directory_contents(&mut self, dir: &Path, depth: usize, expanded_dirs: &mut Vec<PathBuf>, ancestors: &mut HashSet<PathBuf>, repo: Option<&git2::Repository>) -> Result<()> {
+        let mut entries = self.visible_entries(dir, repo)?;
+        self.sort_entries(&mut entries);
 
         for entry in entries {
             let path = entry.path();
-            let name = path.file_name()
+            let mut name = path.file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("?")
                 .to_string();
+            if self.order_prefixes {
+                if let Some((_, rest)) = order_prefix(&name) {
+                    name = rest.to_string();
+                }
+            }
 
             let is_dir = path.is_dir();
-            let is_expanded = expanded_dirs.contains(&path);
+            let canonical = if is_dir { fs::canonicalize(&path).ok() } else { None };
+            let is_cycle = canonical.as_ref().is_some_and(|c| ancestors.contains(c));
+            let is_expanded = is_dir && !is_cycle && depth < MAX_TREE_DEPTH && expanded_dirs.contains(&path);
             
             // Create the display name with proper indentation
             let indent = "  ".repeat(depth);
@@ -105,26 +205,177 @@ impl FileTree {
                 "  "
             };
             
-            let display_name = format!("{}{}{}", indent, prefix, name);
+            let mut suffix = if is_symlink(&path) { SYMLINK_MARKER.to_string() } else { String::new() };
+            if let Some(count) = self.note_counts.get(&path).filter(|_| !is_cycle) {
+                suffix.push_str(&format!(" ({})", count));
+            }
+            let display_name = format!("{}{}{}{}", indent, prefix, name, suffix);
 
             self.items.push(TreeItem {
                 path: path.clone(),
                 display_name,
                 is_expanded,
                 is_dir,
+                suffix,
             });
 
             // If it's a directory and expanded, recursively add its contents
-            if is_dir && is_expanded {
-                self.add_directory_contents(&path, depth + 1, expanded_dirs)?;
+            if is_expanded {
+                let canonical = canonical.unwrap_or_else(|| path.clone());
+                ancestors.insert(canonical.clone());
+                let result = self.add_directory_contents(&path, depth + 1, expanded_dirs, ancestors, repo);
+                ancestors.remove(&canonical);
+                result?;
             }
         }
         
         Ok(())
     }
+
+    /// Sort entries: directories first, then files, both alphabetically
+    /// (after the ones with an order prefix, if those are enabled)
+    fn sort_entries(&self, entries: &mut [fs::DirEntry]) {
+        let natural_sort = self.natural_sort;
+        let order_prefixes = self.order_prefixes;
+        entries.sort_by(|a, b| {
+            let a_path = a.path();
+            let b_path = b.path();
+            let a_order = order_prefixes.then(|| order_prefix(&a.file_name().to_string_lossy()).map(|(n, _)| n)).flatten();
+            let b_order = order_prefixes.then(|| order_prefix(&b.file_name().to_string_lossy()).map(|(n, _)| n)).flatten();
+            
+            match (a_path.is_dir(), b_path.is_dir()) {
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ if a_order.is_some() || b_order.is_some() => match (a_order, b_order) {
+                    (Some(a_order), Some(b_order)) => a_order.cmp(&b_order).then_with(|| a_path.file_name().cmp(&b_path.file_name())),
+                    (Some(_), None) => Ordering::Less,
+                    _ => Ordering::Greater,
+                },
+                _ if natural_sort => natural_cmp(
+                    &a.file_name().to_string_lossy(),
+                    &b.file_name().to_string_lossy(),
+                ),
+                _ => a_path.file_name().cmp(&b_path.file_name()),
+            }
+        });
+    }
+
+    /// Entries of `dir` the tree lists: no hidden or ignored files, only notes, images and folders
+    fn visible_entries(&self, dir: &Path, repo: Option<&git2::Repository>) -> Result<Vec<fs::DirEntry>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let path = entry.path();
+                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                
+                // Filter out .git directory and other hidden directories/files starting with .
+                if file_name.starts_with('.') {
+                    return false;
+                }
+
+                if let Some(repo) = repo {
+                    if repo.is_path_ignored(&path).unwrap_or(false) {
+                        return false;
+                    }
+                }
+
+                if self.is_ignored(&path) {
+                    return false;
+                }
+                
+                if path.is_dir() && is_symlink(&path) && !self.follow_symlinks {
+                    return false;
+                }
+
+                // Show directories, markdown files, and image files
+                path.is_dir() || Self::is_visible_file(&path) || (self.show_encrypted && crypto::is_encrypted_note(&path))
+            })
+            .collect())
+    }
+
+    /// Number of notes the tree would show under `dir`, in all its subfolders
+    ///
+    /// Walks the folders once, deepest first, recording each one's count in `counts`.
+    fn count_notes(&self, dir: &Path, depth: usize, ancestors: &mut HashSet<PathBuf>, repo: Option<&git2::Repository>, counts: &mut HashMap<PathBuf, usize>) -> usize {
+        if depth > MAX_TREE_DEPTH {
+            return 0;
+        }
+        let mut count = 0;
+        for entry in self.visible_entries(dir, repo).unwrap_or_default() {
+            let path = entry.path();
+            if path.is_dir() {
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if ancestors.insert(canonical.clone()) {
+                    let notes = self.count_notes(&path, depth + 1, ancestors, repo, counts);
+                    ancestors.remove(&canonical);
+                    counts.insert(path, notes);
+                    count += notes;
+                }
+            } else if !Self::is_image_file(&path) {
+                count += 1;
+            }
+        }
+        count
+    }
     
+    /// Every note in the order the tree lists them with all folders expanded
+    ///
+    /// Images and encrypted notes are left out.
+    pub fn notes_in_tree_order(&self) -> Vec<PathBuf> {
+        let mut notes = Vec::new();
+        let repo = git2::Repository::open(&self.root_dir).ok();
+        let mut ancestors: HashSet<PathBuf> = fs::canonicalize(&self.root_dir).into_iter().collect();
+        self.collect_notes(&self.root_dir, 0, &mut ancestors, repo.as_ref(), &mut notes);
+        notes
+    }
+
+    fn collect_notes(&self, dir: &Path, depth: usize, ancestors: &mut HashSet<PathBuf>, repo: Option<&git2::Repository>, notes: &mut Vec<PathBuf>) {
+        if depth > MAX_TREE_DEPTH {
+            return;
+        }
+        let mut entries = self.visible_entries(dir, repo).unwrap_or_default();
+        self.sort_entries(&mut entries);
+        for entry in entries {
+            let path = entry.path();
+            if path.is_dir() {
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if ancestors.insert(canonical.clone()) {
+                    self.collect_notes(&path, depth + 1, ancestors, repo, notes);
+                    ancestors.remove(&canonical);
+                }
+            } else if !Self::is_image_file(&path) && !crypto::is_encrypted_note(&path) {
+                notes.push(path);
+            }
+        }
+    }
+
     pub fn get_items(&self) -> Vec<String> {
-        self.items.iter().map(|item| item.display_name.clone()).collect()
+        if !self.relative_paths || self.filter.is_some() {
+            return self.items.iter().map(|item| item.display_name.clone()).collect();
+        }
+
+        self.items
+            .iter()
+            .map(|item| {
+                let prefix = if item.is_dir {
+                    if item.is_expanded { "▼ " } else { "▶ " }
+                } else {
+                    "  "
+                };
+                let relative = item.path.strip_prefix(&self.root_dir).unwrap_or(&item.path);
+                format!("{}{}{}", prefix, relative.to_string_lossy(), item.suffix)
+            })
+            .collect()
+    }
+
+    /// Paths of the entries, in the same order as `get_items`
+    pub fn get_item_paths(&self) -> Vec<&Path> {
+        self.items.iter().map(|item| item.path.as_path()).collect()
+    }
+
+    /// Switch between indented names and root-relative paths
+    pub fn toggle_relative_paths(&mut self) {
+        self.relative_paths = !self.relative_paths;
     }
     
     pub fn get_state_mut(&mut self) -> &mut ListState {
@@ -167,6 +418,24 @@ impl FileTree {
         self.state.select(Some(i));
     }
     
+    /// Move the selection down by `rows`, stopping at the last entry
+    pub fn page_down(&mut self, rows: usize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| i + rows.max(1));
+        self.state.select(Some(i.min(self.items.len() - 1)));
+    }
+
+    /// Move the selection up by `rows`, stopping at the first entry
+    pub fn page_up(&mut self, rows: usize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| i.saturating_sub(rows.max(1)));
+        self.state.select(Some(i));
+    }
+    
     pub fn get_selected_file(&self) -> Option<&PathBuf> {
         if let Some(i) = self.state.selected() {
             self.items.get(i).filter(|item| !item.is_dir).map(|item| &item.path)
@@ -206,9 +475,7 @@ impl FileTree {
                     
                     // Rebuild the tree with new expansion state
                     let selected_path = self.items[i].path.clone();
-                    let root_dir = self.root_dir.clone();
-                    self.items.clear();
-                    self.add_directory_contents(&root_dir, 0, &mut expanded_dirs)?;
+                    self.populate_items(&mut expanded_dirs)?;
                     
                     // Try to maintain selection on the same item
                     if let Some(new_index) = self.items.iter().position(|item| item.path == selected_path) {
@@ -229,20 +496,20 @@ impl FileTree {
     }
     
     pub fn refresh_with_state(&mut self, expanded_dirs: Vec<PathBuf>, selected_path: Option<PathBuf>) -> Result<()> {
-        self.items.clear();
-        let root_dir = self.root_dir.clone();
         let mut expanded_dirs = expanded_dirs;
-        self.add_directory_contents(&root_dir, 0, &mut expanded_dirs)?;
+        let previous_paths: Vec<PathBuf> = self.items.iter().map(|item| item.path.clone()).collect();
+        let previous_index = self.state.selected();
+        self.populate_items(&mut expanded_dirs)?;
         
         // Try to maintain selection
         if let Some(target_path) = selected_path {
             if let Some(new_index) = self.items.iter().position(|item| item.path == target_path) {
                 self.state.select(Some(new_index));
-            } else {
-                // If the exact path is not found, try to select something nearby
-                if !self.items.is_empty() {
-                    self.state.select(Some(0));
-                }
+            } else if !self.items.is_empty() {
+                // The path is gone (e.g. deleted by a pull), so select something nearby
+                let new_index = self.nearest_index(&target_path, &previous_paths)
+                    .unwrap_or_else(|| previous_index.unwrap_or(0).min(self.items.len() - 1));
+                self.state.select(Some(new_index));
             }
         } else if !self.items.is_empty() {
             self.state.select(Some(0));
@@ -250,7 +517,57 @@ impl FileTree {
         
         Ok(())
     }
+
+    /// Index of the sibling that was next to `path` before the refresh, or of its closest remaining ancestor
+    fn nearest_index(&self, path: &Path, previous_paths: &[PathBuf]) -> Option<usize> {
+        let position = |candidate: &Path| self.items.iter().position(|item| item.path == candidate);
+
+        if let Some(old_index) = previous_paths.iter().position(|previous| previous == path) {
+            let is_sibling = |candidate: &&PathBuf| candidate.parent() == path.parent();
+            let after = previous_paths[old_index + 1..].iter().filter(is_sibling);
+            let before = previous_paths[..old_index].iter().rev().filter(is_sibling);
+            if let Some(index) = after.chain(before).find_map(|sibling| position(sibling)) {
+                return Some(index);
+            }
+        }
+
+        path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| ancestor.starts_with(&self.root_dir))
+            .find_map(position)
+    }
     
+    /// Expand the ancestors of `path` and select it
+    pub fn reveal_path(&mut self, path: &Path) -> Result<()> {
+        let mut expanded_dirs = self.get_expansion_state();
+        for ancestor in path.ancestors().skip(1) {
+            if ancestor == self.root_dir || !ancestor.starts_with(&self.root_dir) {
+                break;
+            }
+            let ancestor = ancestor.to_path_buf();
+            if !expanded_dirs.contains(&ancestor) {
+                expanded_dirs.push(ancestor);
+            }
+        }
+        self.refresh_with_state(expanded_dirs, Some(path.to_path_buf()))
+    }
+
+    /// Whether a file's extension is one the tree shows (markdown and images)
+    pub fn is_visible_file(path: &Path) -> bool {
+        if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
+            let ext_lower = ext.to_lowercase();
+            return is_markdown_file(path) || 
+                   ext_lower == "png" || 
+                   ext_lower == "jpg" || 
+                   ext_lower == "jpeg" || 
+                   ext_lower == "gif" || 
+                   ext_lower == "bmp" || 
+                   ext_lower == "webp" || 
+                   ext_lower == "svg";
+        }
+        false
+    }
+
     pub fn is_image_file(path: &PathBuf) -> bool {
         if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
             let ext_lower = ext.to_lowercase();
@@ -264,4 +581,246 @@ impl FileTree {
         }
         false
     }
+
+    /// Recursively collect every markdown note in the notes directory, skipping hidden and ignored entries
+    ///
+    /// Symlinked folders are followed if `follow_symlinks` is set, but each
+    /// folder is read only once so cyclic links cannot loop.
+    pub fn collect_markdown_files(config: &Config) -> Vec<PathBuf> {
+        let root = &config.root_directory;
+        let (ignore_patterns, _) = ignore_set(&config.ignore_patterns);
+        let mut files = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![root.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            if !visited.insert(fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone())) {
+                continue;
+            }
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let hidden = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with('.'))
+                    .unwrap_or(true);
+                if hidden || is_ignored_by(&ignore_patterns, root, &path) {
+                    continue;
+                }
+                if path.is_dir() {
+                    if config.follow_symlinks || !is_symlink(&path) {
+                        pending.push(path);
+                    }
+                } else if is_markdown_file(&path) {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        files
+    }
+}
+
+/// A glob set of the valid `patterns`, and the patterns that are not valid globs
+fn ignore_set(patterns: &[String]) -> (GlobSet, Vec<String>) {
+    let mut builder = GlobSetBuilder::new();
+    let mut invalid = Vec::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(_) => invalid.push(pattern.clone()),
+        }
+    }
+    // Every glob in the builder is valid, so building it cannot fail
+    (builder.build().unwrap_or_else(|_| GlobSet::empty()), invalid)
+}
+
+/// Whether `path` matches one of `patterns`, relative to `root`; folders also match patterns like `archive/**`
+fn is_ignored_by(patterns: &GlobSet, root: &Path, path: &Path) -> bool {
+    if patterns.is_empty() {
+        return false;
+    }
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    patterns.is_match(relative) || (path.is_dir() && patterns.is_match(format!("{}/", relative.to_string_lossy())))
+}
+
+/// Whether `path` has one of the `MARKDOWN_EXTENSIONS`, in any case
+pub fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| MARKDOWN_EXTENSIONS.iter().any(|markdown| ext.eq_ignore_ascii_case(markdown)))
+}
+
+/// Whether `path` itself is a symbolic link, without following it
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// The number and the rest of a name with an order prefix like `01-intro.md` or `2_plan`
+pub fn order_prefix(name: &str) -> Option<(u64, &str)> {
+    let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = name[digits..].strip_prefix(['-', '_', ' '])?;
+    if rest.is_empty() || rest.starts_with('.') {
+        return None;
+    }
+    Some((name[..digits].parse().ok()?, rest))
+}
+
+/// Compare names case-insensitively, with runs of digits compared by their value
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let x_digits = take_number(&mut a_chars);
+                let y_digits = take_number(&mut b_chars);
+                let x_value = x_digits.trim_start_matches('0');
+                let y_value = y_digits.trim_start_matches('0');
+                let ordering = x_value.len().cmp(&y_value.len()).then_with(|| x_value.cmp(y_value));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+    // Names that differ only in case or leading zeros still get a stable order
+    a.cmp(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_files_are_recognized_by_extension() {
+        for name in ["a.md", "a.markdown", "a.mdown", "a.mkd", "A.MD", "a.Markdown", "dir/a.MkD"] {
+            assert!(is_markdown_file(Path::new(name)), "{} should be markdown", name);
+        }
+        for name in ["foo.mdx", "a.txt", "md", "a.md.age", "a.mdown.bak"] {
+            assert!(!is_markdown_file(Path::new(name)), "{} should not be markdown", name);
+        }
+    }
+
+    #[test]
+    fn natural_order_ignores_case_and_compares_numbers_by_value() {
+        let mut names = vec!["note10.md", "Zebra.md", "note2.md", "apple.md", "Note1.md", "note02.md", "note2.md"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["apple.md", "Note1.md", "note02.md", "note2.md", "note2.md", "note10.md", "Zebra.md"]);
+        assert_eq!(natural_cmp("Apple.md", "apple.md"), Ordering::Less);
+    }
+
+    #[test]
+    fn the_tree_is_sorted_naturally_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        for note in ["Zebra.md", "apple.md", "note10.md", "note2.md"] {
+            fs::write(dir.path().join(note), "").unwrap();
+        }
+        let mut config = Config::default();
+        config.root_directory = dir.path().to_path_buf();
+        let names = |tree: &FileTree| -> Vec<String> {
+            tree.get_item_paths()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+
+        let mut tree = FileTree::new(&config).unwrap();
+        assert_eq!(names(&tree), ["Zebra.md", "apple.md", "note10.md", "note2.md"]);
+
+        config.natural_sort = true;
+        tree.apply_config(&config).unwrap();
+        assert_eq!(names(&tree), ["apple.md", "note2.md", "note10.md", "Zebra.md"]);
+    }
+
+    #[test]
+    fn a_removed_selection_moves_to_a_sibling_or_the_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("drafts")).unwrap();
+        for note in ["drafts/idea.md", "a.md", "b.md", "c.md"] {
+            fs::write(dir.path().join(note), "").unwrap();
+        }
+        let mut config = Config::default();
+        config.root_directory = dir.path().to_path_buf();
+        let mut tree = FileTree::new(&config).unwrap();
+        let refresh_after_removing = |tree: &mut FileTree, note: &str| {
+            let path = dir.path().join(note);
+            tree.reveal_path(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            let expanded = tree.get_expansion_state();
+            tree.refresh_with_state(expanded, Some(path)).unwrap();
+            tree.get_selected_path().cloned()
+        };
+
+        // The next sibling, or the previous one when the last is removed
+        assert_eq!(refresh_after_removing(&mut tree, "b.md"), Some(dir.path().join("c.md")));
+        assert_eq!(refresh_after_removing(&mut tree, "c.md"), Some(dir.path().join("a.md")));
+        // The folder, once it has no notes left
+        assert_eq!(refresh_after_removing(&mut tree, "drafts/idea.md"), Some(dir.path().join("drafts")));
+    }
+
+    #[test]
+    fn config_changes_keep_expanded_folders_and_report_bad_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("drafts")).unwrap();
+        fs::write(dir.path().join("drafts/idea.md"), "").unwrap();
+        fs::write(dir.path().join("todo.md"), "").unwrap();
+        let mut config = Config::default();
+        config.root_directory = dir.path().to_path_buf();
+
+        let mut tree = FileTree::new(&config).unwrap();
+        assert!(tree.invalid_ignore_patterns().is_empty());
+        tree.toggle_selected().unwrap();
+        assert_eq!(tree.get_item_paths().len(), 3);
+
+        config.ignore_patterns = vec!["todo.md".to_string(), "[".to_string()];
+        tree.apply_config(&config).unwrap();
+        assert_eq!(tree.invalid_ignore_patterns(), ["["]);
+        assert_eq!(tree.get_item_paths(), [dir.path().join("drafts"), dir.path().join("drafts/idea.md")]);
+    }
+
+    #[test]
+    fn folders_count_the_notes_in_all_their_subfolders() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        for note in ["a/one.md", "a/b/two.md", "a/b/c/three.md", "a/b/c/four.md", "a/b/c/picture.png"] {
+            fs::write(dir.path().join(note), "").unwrap();
+        }
+        let mut config = Config::default();
+        config.root_directory = dir.path().to_path_buf();
+        config.show_note_counts = true;
+
+        let mut tree = FileTree::new(&config).unwrap();
+        tree.reveal_path(&dir.path().join("a/b/c/three.md")).unwrap();
+        let folders: Vec<_> = tree.items.iter().filter(|item| item.is_dir).map(|item| item.suffix.as_str()).collect();
+        assert_eq!(folders, [" (4)", " (3)", " (2)"]);
+    }
 }
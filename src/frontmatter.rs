@@ -0,0 +1,75 @@
+use serde_yaml::Value;
+
+/// Metadata from the `---` YAML block at the top of a note
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frontmatter {
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Frontmatter {
+    /// Parse the YAML between the fences, or `None` if it is not a valid mapping
+    pub fn parse(yaml: &str) -> Option<Self> {
+        let mapping = match serde_yaml::from_str::<Value>(yaml).ok()? {
+            Value::Mapping(mapping) => mapping,
+            // An empty block is valid frontmatter without fields
+            Value::Null => return Some(Self::default()),
+            _ => return None,
+        };
+
+        let field = |name: &str| mapping.get(name).and_then(scalar_to_string);
+        let tags: Vec<String> = match mapping.get("tags").or_else(|| mapping.get("tag")) {
+            Some(Value::Sequence(items)) => items.iter().filter_map(scalar_to_string).collect(),
+            // Allow `tags: a, b` and `tags: a b`
+            Some(value) => scalar_to_string(value)
+                .map(|tags| {
+                    tags.split([',', ' '])
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        Some(Self {
+            title: field("title"),
+            date: field("date"),
+            tags: tags
+                .iter()
+                .map(|tag| tag.trim_start_matches('#').to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.date.is_none() && self.tags.is_empty()
+    }
+}
+
+/// Split a note into its frontmatter YAML and the body after the closing fence
+pub fn split(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---")?;
+    let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let fence = line.trim_end();
+        if fence == "---" || fence == "..." {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
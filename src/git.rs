@@ -1,11 +1,52 @@
 use anyhow::{Result, Context};
 use git2::{Repository, Signature};
-use std::path::PathBuf;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{mpsc::Sender, Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
 use crate::config::Config;
 
+/// Contents of the `.gitignore` written into new note repositories
+pub const DEFAULT_GITIGNORE: &str = "# RNotes Git ignore\n*.tmp\n*.bak\n*~\n.DS_Store\nThumbs.db\n";
+
+/// How long a status scan is reused when nothing in RNotes changed the notes
+///
+/// The UI asks for the status on every frame. Saves and git operations drop the
+/// scan through `invalidate_status_cache`; this only catches edits made elsewhere.
+const STATUS_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Uncommitted changes by absolute path
+pub type FileStatuses = Arc<HashMap<PathBuf, FileStatus>>;
+
+/// Per-file statuses and the distance from the upstream branch, read together
+#[derive(Clone)]
+struct StatusScan {
+    files: FileStatuses,
+    // Changed files and every folder above them, for marking the tree
+    changed: Arc<HashSet<PathBuf>>,
+    ahead_behind: (usize, usize),
+}
+
+// A failed scan is kept too, so a broken repository isn't reopened on every frame
+type StatusCache = Option<(Instant, std::result::Result<StatusScan, String>)>;
+
+// `None` until the history is first read, then `Some(None)` for a branch without commits
+type HistoryCache = Option<Option<History>>;
+
+#[derive(Clone)]
 pub struct GitManager {
     repo_path: PathBuf,
     config: Config,
+    // Shared with clones running background operations
+    status_cache: Arc<Mutex<StatusCache>>,
+    history_cache: Arc<Mutex<HistoryCache>>,
+    // Where fetches report how far they got, if anyone is listening
+    progress: Option<Sender<FetchProgress>>,
 }
 
 impl GitManager {
@@ -13,9 +54,17 @@ impl GitManager {
         Self {
             repo_path: config.root_directory.clone(),
             config,
+            status_cache: Arc::new(Mutex::new(None)),
+            history_cache: Arc::new(Mutex::new(None)),
+            progress: None,
         }
     }
 
+    /// Send the progress of fetches made by this manager over `sender`
+    pub fn report_progress(&mut self, sender: Sender<FetchProgress>) {
+        self.progress = Some(sender);
+    }
+
     /// Initialize a new Git repository in the notes directory
     pub fn init_repository(&self) -> Result<()> {
         if !self.config.git_enabled {
@@ -34,22 +83,36 @@ impl GitManager {
         // Create initial .gitignore if it doesn't exist
         let gitignore_path = self.repo_path.join(".gitignore");
         if !gitignore_path.exists() {
-            let gitignore_content = "# RNotes Git ignore\n*.tmp\n*.bak\n*~\n.DS_Store\nThumbs.db\n";
-            std::fs::write(&gitignore_path, gitignore_content)
+            std::fs::write(&gitignore_path, DEFAULT_GITIGNORE)
                 .context("Failed to create .gitignore")?;
         }
 
         // Set up remote if configured
         if let Some(remote_url) = &self.config.git_repository {
-            repo.remote("origin", remote_url)
-                .context("Failed to add remote origin")?;
+            let remote_name = self.config.git_remote_name.as_deref().unwrap_or("origin");
+            repo.remote(remote_name, remote_url)
+                .with_context(|| format!("Failed to add remote {}", remote_name))?;
         }
 
         Ok(())
     }
 
-    /// Add all changes and commit with a generic message
-    pub fn commit_and_push(&self) -> Result<()> {
+    /// Add all changes, or only the staged ones if any were staged, commit and push
+    pub fn commit_and_push(&self) -> Result<CommitOutcome> {
+        self.commit_all("Manual commit from RNotes", true, true)
+    }
+
+    /// Commit only what was staged with `stage`, then push
+    pub fn commit_staged(&self) -> Result<CommitOutcome> {
+        self.commit_all("Manual commit from RNotes", true, false)
+    }
+
+    /// Commit on the autocommit timer like `commit_and_push`, pushing only if asked to
+    pub fn autocommit(&self, push: bool) -> Result<CommitOutcome> {
+        self.commit_all("Automatic commit from RNotes", push, true)
+    }
+
+    fn commit_all(&self, message: &str, push: bool, stage_all: bool) -> Result<CommitOutcome> {
         if !self.config.git_enabled {
             return Err(anyhow::anyhow!("Git integration is not enabled"));
         }
@@ -57,15 +120,63 @@ impl GitManager {
         let repo = Repository::open(&self.repo_path)
             .context("Failed to open Git repository")?;
 
+        let push = push && self.config.git_repository.is_some();
+        // Catch up with the remote before committing so the push can fast-forward;
+        // if that fails only the push is skipped, the changes are still committed
+        let synced = if push {
+            self.fetch(&repo).and_then(|()| {
+                let behind_only = matches!(self.ahead_behind(&repo), Some((0, behind)) if behind > 0);
+                match self.upstream_target(&repo) {
+                    Some(upstream) if behind_only && self.config.pull_before_push => self.fast_forward(&repo, upstream),
+                    _ => Ok(()),
+                }
+            })
+        } else {
+            Ok(())
+        };
+
+        // Committing a half-resolved merge would record the conflict markers
+        let unresolved = self.conflicted_files()?
+            .iter()
+            .filter(|path| fs::read_to_string(path).map(|text| has_conflict_markers(&text)).unwrap_or(false))
+            .count();
+        if unresolved > 0 {
+            return Err(anyhow::anyhow!(
+                "{} file{} still contain{} conflict markers. Resolve them first (M)",
+                unresolved,
+                if unresolved == 1 { "" } else { "s" },
+                if unresolved == 1 { "s" } else { "" }
+            ));
+        }
+        let merge_head = match repo.state() {
+            git2::RepositoryState::Merge => Some(
+                repo.find_reference("MERGE_HEAD")
+                    .and_then(|reference| reference.peel_to_commit())
+                    .context("Failed to find the commit being merged")?,
+            ),
+            _ => None,
+        };
+
         let mut index = repo.index()
             .context("Failed to get repository index")?;
 
-        // Add all files
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
-            .context("Failed to add files to index")?;
+        // Files staged with `stage` are committed on their own, without the other changes;
+        // a conflicted index can't be compared and is settled by staging everything
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let staged = !index.has_conflicts() && match &head_tree {
+            Some(head_tree) => index.write_tree().context("Failed to write tree")? != head_tree.id(),
+            None => !index.is_empty(),
+        };
+        if stage_all && !staged {
+            // Add new and modified files, then stage deletions, which add_all skips
+            index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+                .context("Failed to add files to index")?;
+            index.update_all(["*"].iter(), None)
+                .context("Failed to stage deleted files")?;
 
-        index.write()
-            .context("Failed to write index")?;
+            index.write()
+                .context("Failed to write index")?;
+        }
 
         // Check if there are any changes to commit
         let tree_id = index.write_tree()
@@ -82,27 +193,23 @@ impl GitManager {
             Err(_) => None, // First commit
         };
 
-        // Check if there are actually changes to commit
-        let has_changes = if let Some(parent) = &parent_commit {
-            let parent_tree = parent.tree().context("Failed to get parent tree")?;
-            let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)
-                .context("Failed to create diff")?;
-            diff.deltas().len() > 0
-        } else {
-            // First commit, check if there are any files
-            tree.len() > 0
+        // Check if there are actually changes to commit; on the first commit every file is new
+        let parent_tree = match &parent_commit {
+            Some(parent) => Some(parent.tree().context("Failed to get parent tree")?),
+            None => None,
         };
+        let changed_files = changed_files(&repo, parent_tree.as_ref(), &tree)?;
+        let has_changes = !changed_files.is_empty();
 
-        if has_changes {
+        if has_changes || merge_head.is_some() {
             // Create signature
             let signature = self.create_signature()?;
 
-            // Create commit message with timestamp
-            let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
-            let commit_message = format!("Manual commit from RNotes - {}", timestamp);
+            let commit_message = self.commit_message(message, &changed_files);
 
             // Create the commit
-            let parents: Vec<&git2::Commit> = parent_commit.as_ref().map_or(vec![], |c| vec![c]);
+            let mut parents: Vec<&git2::Commit> = parent_commit.as_ref().map_or(vec![], |c| vec![c]);
+            parents.extend(merge_head.as_ref());
             
             repo.commit(
                 Some("HEAD"),
@@ -112,22 +219,25 @@ impl GitManager {
                 &tree,
                 &parents,
             ).context("Failed to create commit")?;
+            if merge_head.is_some() {
+                repo.cleanup_state().context("Failed to finish the merge")?;
+            }
 
-            println!("✓ Changes committed successfully");
-
-            // Push changes if remote is configured
-            if self.config.git_repository.is_some() {
-                println!("→ Pushing to remote repository...");
-                self.push_changes()?;
-                println!("✓ Successfully pushed to remote repository");
-            } else {
-                println!("⚠ No remote repository configured");
+            // Push changes if remote is configured, unless the remote would reject it
+            if !push {
+                return Ok(CommitOutcome::Committed);
+            }
+            synced.context("Committed locally, but could not catch up with the remote, so nothing was pushed")?;
+            match self.ahead_behind(&repo) {
+                Some((_, behind)) if behind > 0 => Ok(CommitOutcome::BehindRemote(behind)),
+                _ => {
+                    self.push_changes().context("Committed locally, but the push failed")?;
+                    Ok(CommitOutcome::Pushed)
+                }
             }
         } else {
-            println!("ℹ No changes to commit");
+            Ok(CommitOutcome::NoChanges)
         }
-
-        Ok(())
     }
 
     /// Push changes to remote repository
@@ -139,48 +249,16 @@ impl GitManager {
         let repo = Repository::open(&self.repo_path)
             .context("Failed to open Git repository")?;
 
-        // Try to get the remote - first "origin", then "rnotes", then the first available remote
-        let mut remote = repo.find_remote("origin")
-            .or_else(|_| repo.find_remote("rnotes"))
-            .or_else(|_| {
-                // Get the first available remote
-                let remotes = repo.remotes()?;
-                if let Some(remote_name) = remotes.get(0) {
-                    repo.find_remote(remote_name)
-                } else {
-                    Err(git2::Error::from_str("No remote repositories found"))
-                }
-            })
-            .context("Failed to find any remote repository")?;
-
-        // Set up callbacks for GitHub CLI authentication
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|url, username_from_url, allowed_types| {
-            // Try different credential types in order of preference
-            if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
-                // Use credential helper (works with gh auth)
-                if let Ok(config) = git2::Config::open_default() {
-                    if let Ok(cred) = git2::Cred::credential_helper(&config, url, username_from_url) {
-                        return Ok(cred);
-                    }
-                }
-            }
-            
-            if allowed_types.contains(git2::CredentialType::DEFAULT) {
-                if let Ok(cred) = git2::Cred::default() {
-                    return Ok(cred);
-                }
-            }
+        let mut remote = self.find_remote(&repo)?;
 
-            // Fallback to username
-            git2::Cred::username(username_from_url.unwrap_or("git"))
-        });
+        let mut callbacks = self.remote_callbacks();
 
-        // Add progress callback for feedback
-        callbacks.push_update_reference(|refname, status| {
-            match status {
-                Some(msg) => println!("Push failed for {}: {}", refname, msg),
-                None => println!("Successfully updated {}", refname),
+        // The remote reports per-ref rejections here rather than failing the push
+        let rejection = Rc::new(RefCell::new(None));
+        let rejection_sink = Rc::clone(&rejection);
+        callbacks.push_update_reference(move |refname, status| {
+            if let Some(status) = status {
+                *rejection_sink.borrow_mut() = Some(format!("{} ({})", status, refname));
             }
             Ok(())
         });
@@ -189,78 +267,167 @@ impl GitManager {
         let mut push_options = git2::PushOptions::new();
         push_options.remote_callbacks(callbacks);
 
-        let result = remote.push(&["refs/heads/main:refs/heads/main"], Some(&mut push_options))
-            .or_else(|_| {
-                // Try master branch if main doesn't work
-                remote.push(&["refs/heads/master:refs/heads/master"], Some(&mut push_options))
-            });
+        let result = if let Some(branch) = &self.config.git_branch {
+            let refspec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
+            remote.push(&[refspec.as_str()], Some(&mut push_options))
+        } else {
+            remote.push(&["refs/heads/main:refs/heads/main"], Some(&mut push_options))
+                .or_else(|_| {
+                    // Try master branch if main doesn't work
+                    remote.push(&["refs/heads/master:refs/heads/master"], Some(&mut push_options))
+                })
+        };
 
         match result {
-            Ok(_) => Ok(()),
+            Ok(_) => match rejection.borrow_mut().take() {
+                Some(reason) => Err(anyhow::anyhow!("Remote rejected the push: {}", reason)),
+                None => Ok(()),
+            },
+            Err(e) if e.code() == git2::ErrorCode::NotFastForward => {
+                Err(anyhow::anyhow!("Remote has commits you don't have yet. Pull (p) first, then push again"))
+            }
             Err(e) => {
                 Err(anyhow::anyhow!(
                     "Failed to push to remote repository: {}. \n\
-                     Make sure you have GitHub CLI installed and authenticated:\n\
+                     Tried: {}.\n\
+                     For HTTPS remotes make sure you have GitHub CLI installed and authenticated:\n\
                      - Install: sudo apt install gh\n\
                      - Login: gh auth login\n\
-                     - Verify: gh auth status", 
-                    e
+                     - Verify: gh auth status\n\
+                     For SSH remotes set git_ssh_key in the config", 
+                    e,
+                    self.auth_methods()
                 ))
             }
         }
     }
 
-    /// Pull changes from remote repository
-    pub fn pull_changes(&self) -> Result<()> {
-        self.pull_changes_with_feedback(true)
+    /// Check that `path` has uncommitted changes that can be thrown away
+    pub fn check_discardable(&self, path: &Path) -> Result<()> {
+        if !self.config.git_enabled {
+            return Err(anyhow::anyhow!("Git integration is not enabled"));
+        }
+
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let relative = self.relative_path(path)?;
+        let name = relative.display();
+
+        let status = repo.status_file(relative)
+            .with_context(|| format!("Failed to get status of {}", name))?;
+        if status.intersects(git2::Status::WT_NEW | git2::Status::INDEX_NEW) {
+            Err(anyhow::anyhow!("{} has never been committed, delete it instead", name))
+        } else if status.is_empty() {
+            Err(anyhow::anyhow!("{} has no uncommitted changes", name))
+        } else {
+            Ok(())
+        }
     }
 
-    /// Pull changes from remote repository with optional feedback
-    pub fn pull_changes_with_feedback(&self, show_feedback: bool) -> Result<()> {
-        if !self.config.git_enabled || self.config.git_repository.is_none() {
-            return Ok(());
+    /// Restore `path` to its version in HEAD, dropping staged and unstaged edits
+    pub fn discard_changes(&self, path: &Path) -> Result<()> {
+        self.check_discardable(path)?;
+
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let relative = self.relative_path(path)?;
+
+        repo.checkout_head(Some(
+            git2::build::CheckoutBuilder::default()
+                .force()
+                .update_index(true)
+                .path(relative),
+        ))
+        .with_context(|| format!("Failed to restore {}", relative.display()))
+    }
+
+    /// Stage the current state of `path`, including its deletion
+    pub fn stage(&self, path: &Path) -> Result<()> {
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let relative = self.relative_path(path)?;
+        let mut index = repo.index().context("Failed to get repository index")?;
+        if path.exists() {
+            index.add_path(relative)
+        } else {
+            index.remove_path(relative)
         }
+        .with_context(|| format!("Failed to stage {}", relative.display()))?;
+        index.write().context("Failed to write index")?;
+        self.invalidate_status_cache();
+        Ok(())
+    }
 
+    /// Take `path` out of the next commit, keeping its changes in the working tree
+    pub fn unstage(&self, path: &Path) -> Result<()> {
         let repo = Repository::open(&self.repo_path)
             .context("Failed to open Git repository")?;
+        let relative = self.relative_path(path)?;
+        match repo.head().and_then(|head| head.peel_to_commit()) {
+            Ok(head) => repo.reset_default(Some(head.as_object()), [relative]),
+            // Before the first commit there is nothing to reset to
+            Err(_) => {
+                let mut index = repo.index().context("Failed to get repository index")?;
+                index.remove_path(relative).and_then(|()| index.write())
+            }
+        }
+        .with_context(|| format!("Failed to unstage {}", relative.display()))?;
+        self.invalidate_status_cache();
+        Ok(())
+    }
 
-        // Fetch from remote - try "origin" first, then "rnotes", then first available
-        let mut remote = repo.find_remote("origin")
-            .or_else(|_| repo.find_remote("rnotes"))
-            .or_else(|_| {
-                // Get the first available remote
-                let remotes = repo.remotes()?;
-                if let Some(remote_name) = remotes.get(0) {
-                    repo.find_remote(remote_name)
-                } else {
-                    Err(git2::Error::from_str("No remote repositories found"))
+    /// Last commit to touch each line of `content`, the current text of `path`
+    ///
+    /// Lines that are new or changed since the last commit are `None`.
+    pub fn blame(&self, path: &Path, content: &str) -> Result<Vec<Option<BlameLine>>> {
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let relative = self.relative_path(path)?;
+        let line_count = content.lines().count();
+
+        let status = repo.status_file(relative)
+            .with_context(|| format!("Failed to get status of {}", relative.display()))?;
+        if status.intersects(git2::Status::WT_NEW | git2::Status::INDEX_NEW) || repo.head().is_err() {
+            return Ok(vec![None; line_count]);
+        }
+
+        let committed = repo.blame_file(relative, None)
+            .with_context(|| format!("Failed to blame {}", relative.display()))?;
+        // Blame the text on screen so edits since the last commit line up
+        let blame = committed.blame_buffer(content.as_bytes())
+            .with_context(|| format!("Failed to blame {}", relative.display()))?;
+
+        Ok((1..=line_count)
+            .map(|line| {
+                let hunk = blame.get_line(line)?;
+                let commit = hunk.final_commit_id();
+                if commit.is_zero() {
+                    return None;
                 }
+                let signature = hunk.final_signature();
+                Some(BlameLine {
+                    commit: commit.to_string().chars().take(7).collect(),
+                    author: signature.name().unwrap_or("unknown").to_string(),
+                })
             })
-            .context("Failed to find any remote repository")?;
+            .collect())
+    }
 
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|_url, username_from_url, allowed_types| {
-            if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
-                // Use git credential helper (works with gh auth)
-                git2::Cred::credential_helper(&git2::Config::open_default().unwrap(), _url, username_from_url)
-            } else if allowed_types.contains(git2::CredentialType::DEFAULT) {
-                git2::Cred::default()
-            } else {
-                git2::Cred::username(username_from_url.unwrap_or("git"))
-            }
-        });
+    fn relative_path<'p>(&self, path: &'p Path) -> Result<&'p Path> {
+        path.strip_prefix(&self.repo_path)
+            .with_context(|| format!("{} is outside the notes repository", path.display()))
+    }
 
-        let mut fetch_options = git2::FetchOptions::new();
-        fetch_options.remote_callbacks(callbacks);
+    /// Pull changes from remote repository
+    pub fn pull_changes(&self) -> Result<PullOutcome> {
+        if !self.config.git_enabled || self.config.git_repository.is_none() {
+            return Ok(PullOutcome::NoRemote);
+        }
 
-        remote.fetch(&["refs/heads/*:refs/remotes/origin/*"], 
-                    Some(&mut fetch_options), 
-                    None)
-            .context("Failed to fetch from remote. Make sure 'gh auth login' is configured.")?;
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
 
-        if show_feedback {
-            println!("✓ Fetched latest changes from remote");
-        }
+        self.fetch(&repo)?;
 
         // Perform merge (simple fast-forward merge)
         let fetch_head = repo.find_reference("FETCH_HEAD")
@@ -273,44 +440,252 @@ impl GitManager {
             .context("Failed to analyze merge")?;
 
         if analysis.0.is_fast_forward() {
-            // Fast-forward merge
-            let refname = format!("refs/heads/{}", 
-                                repo.head()?.shorthand().unwrap_or("main"));
-            let mut reference = repo.find_reference(&refname)
-                .context("Failed to find branch reference")?;
-            reference.set_target(fetch_commit.id(), "Fast-forward")
-                .context("Failed to set target for fast-forward")?;
-            repo.set_head(&refname)
-                .context("Failed to set HEAD")?;
-            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
-                .context("Failed to checkout HEAD")?;
-            
-            if show_feedback {
-                println!("✓ Fast-forward merge completed");
-            }
+            self.fast_forward(&repo, fetch_commit.id())?;
+            Ok(PullOutcome::FastForward)
         } else if analysis.0.is_normal() {
-            // Normal merge would be more complex, for now just warn
-            if show_feedback {
-                eprintln!("Warning: Manual merge required. Please resolve conflicts manually.");
+            self.merge(&repo, &fetch_commit)
+        } else {
+            Ok(PullOutcome::UpToDate)
+        }
+    }
+
+    /// Message for a commit of `changed_files`: the configured template, or `message` with a timestamp
+    fn commit_message(&self, message: &str, changed_files: &[String]) -> String {
+        let timestamp = match self.config.date_format() {
+            Some(format) => chrono::Local::now().format(format).to_string(),
+            None => chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        };
+        match self.config.commit_message_template() {
+            Some(template) => template
+                .replace("{{date}}", &timestamp)
+                .replace("{{count}}", &changed_files.len().to_string())
+                .replace("{{files}}", &changed_files.join(", ")),
+            None => format!("{} - {}", message, timestamp),
+        }
+    }
+
+    /// Merge the fetched commit, committing right away unless there are conflicts
+    fn merge(&self, repo: &Repository, fetch_commit: &git2::AnnotatedCommit) -> Result<PullOutcome> {
+        repo.merge(&[fetch_commit], None, None)
+            .context("Failed to merge remote changes; commit or undo your local edits first")?;
+
+        let mut index = repo.index().context("Failed to get repository index")?;
+        if index.has_conflicts() {
+            // Conflicted files now hold markers; committing after resolving finishes the merge
+            let conflicts = index.conflicts()
+                .context("Failed to read merge conflicts")?
+                .count();
+            return Ok(PullOutcome::Conflicts(conflicts));
+        }
+
+        let tree_id = index.write_tree_to(repo).context("Failed to write merged tree")?;
+        let tree = repo.find_tree(tree_id).context("Failed to find merged tree")?;
+        let head = repo.head()?.peel_to_commit().context("Failed to find HEAD commit")?;
+        let theirs = repo.find_commit(fetch_commit.id()).context("Failed to find fetched commit")?;
+        let signature = self.create_signature()?;
+        let changed_files = changed_files(repo, Some(&head.tree()?), &tree)?;
+
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &self.commit_message("Merge remote changes from RNotes", &changed_files),
+            &tree,
+            &[&head, &theirs],
+        ).context("Failed to create merge commit")?;
+        repo.cleanup_state().context("Failed to finish the merge")?;
+
+        Ok(PullOutcome::Merged)
+    }
+
+    /// Files with unresolved merge conflicts, as absolute paths
+    pub fn conflicted_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.config.git_enabled {
+            return Ok(Vec::new());
+        }
+
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let statuses = repo.statuses(None)
+            .context("Failed to get repository status")?;
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| entry.status().contains(git2::Status::CONFLICTED))
+            .filter_map(|entry| entry.path().map(|path| self.repo_path.join(path)))
+            .collect())
+    }
+
+    /// Stage a conflicted file, marking its conflict as resolved
+    pub fn mark_resolved(&self, path: &Path) -> Result<()> {
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let mut index = repo.index().context("Failed to get repository index")?;
+        index.add_path(self.relative_path(path)?).context("Failed to stage resolved file")?;
+        index.write().context("Failed to write index")
+    }
+
+    /// Fetch all branches from the sync remote into its remote-tracking refs
+    fn fetch(&self, repo: &Repository) -> Result<()> {
+        let mut remote = self.find_remote(repo)?;
+        let remote_name = remote.name().unwrap_or("origin").to_string();
+
+        let mut callbacks = self.remote_callbacks();
+        if let Some(sender) = self.progress.clone() {
+            callbacks.transfer_progress(move |stats| {
+                // The fetch goes on even if nobody is listening any more
+                let _ = sender.send(FetchProgress {
+                    received_objects: stats.received_objects(),
+                    total_objects: stats.total_objects(),
+                    received_bytes: stats.received_bytes(),
+                });
+                true
+            });
+        }
+
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+
+        let refspec = format!("refs/heads/*:refs/remotes/{}/*", remote_name);
+        remote.fetch(&[refspec.as_str()], 
+                    Some(&mut fetch_options), 
+                    None)
+            .with_context(|| format!("Failed to fetch from remote (tried: {})", self.auth_methods()))
+    }
+
+    /// Fast-forward the current branch to `target`
+    ///
+    /// Uncommitted edits are kept; the checkout fails instead of overwriting
+    /// a file that changed on both sides.
+    fn fast_forward(&self, repo: &Repository, target: git2::Oid) -> Result<()> {
+        let target = repo.find_commit(target)
+            .context("Failed to find fetched commit")?;
+
+        repo.checkout_tree(target.as_object(), Some(git2::build::CheckoutBuilder::default().safe()))
+            .context("Failed to update files; commit or undo your local edits to the changed notes first")?;
+
+        let branch = match &self.config.git_branch {
+            Some(branch) => branch.clone(),
+            None => repo.head()?.shorthand().unwrap_or("main").to_string(),
+        };
+        let refname = format!("refs/heads/{}", branch);
+        let mut reference = repo.find_reference(&refname)
+            .context("Failed to find branch reference")?;
+        reference.set_target(target.id(), "Fast-forward")
+            .context("Failed to set target for fast-forward")?;
+        repo.set_head(&refname)
+            .context("Failed to set HEAD")?;
+        Ok(())
+    }
+
+    /// Build the credential callbacks shared by push and fetch
+    ///
+    /// SSH remotes use the configured private key, HTTPS remotes the git
+    /// credential helper (works with gh auth), then default credentials.
+    fn remote_callbacks(&self) -> git2::RemoteCallbacks<'static> {
+        let ssh_key = self.config.git_ssh_key.clone();
+        let ssh_passphrase = self.config.git_ssh_passphrase.clone();
+        // libgit2 keeps asking while credentials are rejected, so only offer the key once
+        let mut ssh_key_tried = false;
+
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.credentials(move |url, username_from_url, allowed_types| {
+            if allowed_types.contains(git2::CredentialType::SSH_KEY) && !ssh_key_tried {
+                if let Some(key) = &ssh_key {
+                    ssh_key_tried = true;
+                    return git2::Cred::ssh_key(
+                        username_from_url.unwrap_or("git"),
+                        None,
+                        key,
+                        ssh_passphrase.as_deref(),
+                    );
+                }
             }
-        } else if analysis.0.is_up_to_date() {
-            if show_feedback {
-                println!("✓ Already up to date");
+
+            if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+                if let Ok(config) = git2::Config::open_default() {
+                    if let Ok(cred) = git2::Cred::credential_helper(&config, url, username_from_url) {
+                        return Ok(cred);
+                    }
+                }
             }
+
+            if allowed_types.contains(git2::CredentialType::DEFAULT) {
+                if let Ok(cred) = git2::Cred::default() {
+                    return Ok(cred);
+                }
+            }
+
+            // Fallback to username
+            git2::Cred::username(username_from_url.unwrap_or("git"))
+        });
+        callbacks
+    }
+
+    /// Describe the authentication methods `remote_callbacks` attempts
+    fn auth_methods(&self) -> String {
+        let mut methods = Vec::new();
+        if let Some(key) = &self.config.git_ssh_key {
+            methods.push(format!("SSH key {}", key.display()));
         }
+        methods.push("git credential helper".to_string());
+        methods.push("default credentials".to_string());
+        methods.join(", ")
+    }
 
-        Ok(())
+    /// Find the remote to sync with
+    ///
+    /// Uses the configured remote name if set, otherwise tries "origin", then
+    /// "rnotes", then the first available remote.
+    fn find_remote<'r>(&self, repo: &'r Repository) -> Result<git2::Remote<'r>> {
+        if let Some(name) = &self.config.git_remote_name {
+            return repo.find_remote(name)
+                .with_context(|| format!("Failed to find remote '{}'", name));
+        }
+
+        repo.find_remote("origin")
+            .or_else(|_| repo.find_remote("rnotes"))
+            .or_else(|_| {
+                // Get the first available remote
+                let remotes = repo.remotes()?;
+                if let Some(remote_name) = remotes.get(0) {
+                    repo.find_remote(remote_name)
+                } else {
+                    Err(git2::Error::from_str("No remote repositories found"))
+                }
+            })
+            .context("Failed to find any remote repository")
+    }
+
+    /// The configured author, then the global git identity, with `None` for any part neither sets
+    fn author(&self) -> (Option<String>, Option<String>) {
+        let (global_name, global_email) = global_identity();
+        (self.config.git_username.clone().or(global_name), self.config.git_email.clone().or(global_email))
+    }
+
+    /// Whether commits fall back to `PLACEHOLDER_AUTHOR` for the name or email
+    pub fn uses_placeholder_author(&self) -> bool {
+        let (name, email) = self.author();
+        name.is_none() || email.is_none()
     }
 
     /// Create a signature for commits
+    ///
+    /// Uses the configured author, then the global git identity, then a placeholder.
     fn create_signature(&self) -> Result<Signature> {
-        let name = self.config.git_username.as_deref().unwrap_or("RNotes User");
-        let email = self.config.git_email.as_deref().unwrap_or("rnotes@localhost");
+        let (name, email) = self.author();
+        let name = name.unwrap_or_else(|| PLACEHOLDER_AUTHOR.0.to_string());
+        let email = email.unwrap_or_else(|| PLACEHOLDER_AUTHOR.1.to_string());
         
-        Signature::now(name, email)
+        Signature::now(&name, &email)
             .context("Failed to create Git signature")
     }
 
+    /// Whether Git integration is on for this notes directory
+    pub fn is_enabled(&self) -> bool {
+        self.config.git_enabled
+    }
+
     /// Check if the directory is a Git repository
     pub fn is_git_repository(&self) -> bool {
         Repository::open(&self.repo_path).is_ok()
@@ -322,38 +697,251 @@ impl GitManager {
             return Ok(GitStatus::default());
         }
 
+        let StatusScan { files, ahead_behind: (ahead, behind), .. } = self.scan_status()?;
+        let count = |matches: fn(&FileStatus) -> bool| files.values().filter(|status| matches(status)).count();
+
+        Ok(GitStatus {
+            modified: count(|status| status.modified || (status.deleted && !status.staged)),
+            untracked: count(|status| status.new),
+            staged: count(|status| status.staged),
+            ahead,
+            behind,
+            has_remote: self.config.git_repository.is_some(),
+        })
+    }
+
+    /// Uncommitted changes by absolute path, shared with the cached scan
+    pub fn file_statuses(&self) -> Result<FileStatuses> {
+        if !self.config.git_enabled {
+            return Ok(Arc::default());
+        }
+        Ok(self.scan_status()?.files)
+    }
+
+    /// Changed files and the folders containing them, shared with the cached scan
+    pub fn changed_paths(&self) -> Result<Arc<HashSet<PathBuf>>> {
+        if !self.config.git_enabled {
+            return Ok(Arc::default());
+        }
+        Ok(self.scan_status()?.changed)
+    }
+
+    /// The cached scan, rescanned after `invalidate_status_cache` or `STATUS_CACHE_TTL`
+    fn scan_status(&self) -> Result<StatusScan> {
+        let mut cache = self.status_cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((scanned, scan)) = cache.as_ref() {
+            if scanned.elapsed() < STATUS_CACHE_TTL {
+                return scan.clone().map_err(anyhow::Error::msg);
+            }
+        }
+
+        let scan = self.read_status();
+        *cache = Some((Instant::now(), scan.as_ref().map(StatusScan::clone).map_err(|e| format!("{:#}", e))));
+        scan
+    }
+
+    fn read_status(&self) -> Result<StatusScan> {
         let repo = Repository::open(&self.repo_path)
             .context("Failed to open Git repository")?;
 
         let statuses = repo.statuses(None)
             .context("Failed to get repository status")?;
 
-        let mut modified = 0;
-        let mut untracked = 0;
-        let mut staged = 0;
-
+        let mut files = HashMap::new();
         for entry in statuses.iter() {
             let status = entry.status();
-            if status.contains(git2::Status::WT_MODIFIED) || 
-               status.contains(git2::Status::WT_DELETED) {
-                modified += 1;
+            let file_status = FileStatus {
+                modified: status.contains(git2::Status::WT_MODIFIED),
+                new: status.contains(git2::Status::WT_NEW),
+                deleted: status.intersects(git2::Status::WT_DELETED | git2::Status::INDEX_DELETED),
+                staged: status.intersects(
+                    git2::Status::INDEX_MODIFIED | git2::Status::INDEX_NEW | git2::Status::INDEX_DELETED,
+                ),
+            };
+            if let (true, Some(path)) = (file_status.is_changed(), entry.path()) {
+                files.insert(self.repo_path.join(path), file_status);
             }
-            if status.contains(git2::Status::WT_NEW) {
-                untracked += 1;
+        }
+
+        let changed = files
+            .keys()
+            .flat_map(|path| path.ancestors().take_while(|ancestor| ancestor.starts_with(&self.repo_path)))
+            .map(Path::to_path_buf)
+            .collect();
+        Ok(StatusScan {
+            files: Arc::new(files),
+            changed: Arc::new(changed),
+            ahead_behind: self.ahead_behind(&repo).unwrap_or((0, 0)),
+        })
+    }
+
+    /// Number of commits on the current branch and when the last one was made
+    ///
+    /// Walking the history is slow on big repositories, so the result is kept
+    /// until `invalidate_status_cache`.
+    pub fn history(&self) -> Result<Option<History>> {
+        let mut cache = self.history_cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(history) = cache.as_ref() {
+            return Ok(history.clone());
+        }
+
+        let repo = Repository::open(&self.repo_path)
+            .context("Failed to open Git repository")?;
+        let head = match repo.head().ok().and_then(|head| head.target()) {
+            Some(head) => head,
+            None => {
+                *cache = Some(None);
+                return Ok(None);
             }
-            if status.contains(git2::Status::INDEX_MODIFIED) || 
-               status.contains(git2::Status::INDEX_NEW) || 
-               status.contains(git2::Status::INDEX_DELETED) {
-                staged += 1;
+        };
+        let last_commit_time = repo.find_commit(head)
+            .context("Failed to read the last commit")?
+            .time()
+            .seconds();
+
+        let mut revwalk = repo.revwalk().context("Failed to walk the history")?;
+        revwalk.push(head).context("Failed to walk the history")?;
+        let history = History {
+            commits: revwalk.count(),
+            last_commit_time,
+        };
+
+        *cache = Some(Some(history.clone()));
+        Ok(Some(history))
+    }
+
+    /// Forget the cached file statuses and history after the repository or the notes changed
+    pub fn invalidate_status_cache(&self) {
+        *self.status_cache.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        *self.history_cache.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Count commits ahead of and behind the tracking branch, using the last fetched refs
+    fn ahead_behind(&self, repo: &Repository) -> Option<(usize, usize)> {
+        let local = repo.head().ok()?.target()?;
+        let upstream = self.upstream_target(repo)?;
+        repo.graph_ahead_behind(local, upstream).ok()
+    }
+
+    /// Last fetched commit of the current branch's tracking branch
+    fn upstream_target(&self, repo: &Repository) -> Option<git2::Oid> {
+        let head = repo.head().ok()?;
+        let branch_name = head.shorthand()?.to_string();
+
+        // Prefer the configured upstream, otherwise the same branch on the sync remote
+        match repo.find_branch(&branch_name, git2::BranchType::Local)
+            .ok()
+            .and_then(|branch| branch.upstream().ok())
+        {
+            Some(upstream) => upstream.get().target(),
+            None => {
+                let remote = self.find_remote(repo).ok()?;
+                let refname = format!("refs/remotes/{}/{}", remote.name()?, branch_name);
+                repo.refname_to_id(&refname).ok()
             }
         }
+    }
+}
 
-        Ok(GitStatus {
-            modified,
-            untracked,
-            staged,
-            has_remote: self.config.git_repository.is_some(),
-        })
+/// Name and email of commits made when no author is configured anywhere
+pub const PLACEHOLDER_AUTHOR: (&str, &str) = ("RNotes User", "rnotes@localhost");
+
+/// Read `user.name` and `user.email` from the global git configuration
+pub fn global_identity() -> (Option<String>, Option<String>) {
+    match git2::Config::open_default() {
+        Ok(config) => (
+            config.get_string("user.name").ok().filter(|s| !s.trim().is_empty()),
+            config.get_string("user.email").ok().filter(|s| !s.trim().is_empty()),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
+/// Loose check that `email` looks like `local@domain.tld`
+pub fn is_valid_email(email: &str) -> bool {
+    let email = email.trim();
+    if email.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+/// Whether `text` contains git conflict markers
+pub fn has_conflict_markers(text: &str) -> bool {
+    let mut lines = text.lines();
+    lines.any(|line| line.starts_with("<<<<<<< ") || line == "<<<<<<<")
+        && lines.any(|line| line.starts_with(">>>>>>> ") || line == ">>>>>>>")
+}
+
+/// Paths that differ between `parent` (nothing for a first commit) and `tree`
+fn changed_files(repo: &Repository, parent: Option<&git2::Tree>, tree: &git2::Tree) -> Result<Vec<String>> {
+    let diff = repo.diff_tree_to_tree(parent, Some(tree), None)
+        .context("Failed to create diff")?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Result of a commit-and-push run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommitOutcome {
+    NoChanges,
+    Committed,
+    Pushed,
+    /// Committed locally but not pushed because the remote has this many new commits
+    BehindRemote(usize),
+}
+
+impl CommitOutcome {
+    pub fn message(&self) -> String {
+        match self {
+            CommitOutcome::NoChanges => "ℹ No changes to commit".to_string(),
+            CommitOutcome::Committed => "✓ Changes committed locally".to_string(),
+            CommitOutcome::Pushed => "✓ Changes committed and pushed to remote repository".to_string(),
+            CommitOutcome::BehindRemote(behind) => format!(
+                "⚠ Committed locally, not pushed: remote has {} new commit{}. Pull (p) first",
+                behind,
+                if *behind == 1 { "" } else { "s" }
+            ),
+        }
+    }
+}
+
+/// Result of a pull run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PullOutcome {
+    NoRemote,
+    UpToDate,
+    FastForward,
+    Merged,
+    /// Merged with conflicts left in this many files
+    Conflicts(usize),
+}
+
+impl PullOutcome {
+    pub fn message(&self) -> String {
+        match self {
+            PullOutcome::NoRemote => "⚠ No remote repository configured".to_string(),
+            PullOutcome::UpToDate => "✓ Already up to date".to_string(),
+            PullOutcome::FastForward => "✓ Fast-forward merge completed".to_string(),
+            PullOutcome::Merged => "✓ Merged remote changes".to_string(),
+            PullOutcome::Conflicts(count) => format!(
+                "⚠ Merge conflicts in {} file{}. Press M to resolve them",
+                count,
+                if *count == 1 { "" } else { "s" }
+            ),
+        }
     }
 }
 
@@ -362,11 +950,197 @@ pub struct GitStatus {
     pub modified: usize,
     pub untracked: usize,
     pub staged: usize,
+    pub ahead: usize,
+    pub behind: usize,
     pub has_remote: bool,
 }
 
+/// Objects and bytes a fetch has received so far
+#[derive(Debug, Clone, Copy)]
+pub struct FetchProgress {
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+}
+
+/// Size and age of the current branch's history
+#[derive(Debug, Clone)]
+pub struct History {
+    pub commits: usize,
+    // Seconds since the Unix epoch
+    pub last_commit_time: i64,
+}
+
+/// The commit that last changed a line
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    // Abbreviated commit hash
+    pub commit: String,
+    pub author: String,
+}
+
+/// Uncommitted changes to a single file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FileStatus {
+    // Changed in the working tree since it was last staged
+    pub modified: bool,
+    // Not tracked yet
+    pub new: bool,
+    // Deleted in the working tree or the index
+    pub deleted: bool,
+    // Has changes in the index
+    pub staged: bool,
+}
+
+impl FileStatus {
+    pub fn is_changed(&self) -> bool {
+        self.modified || self.new || self.deleted || self.staged
+    }
+}
+
 impl GitStatus {
     pub fn has_changes(&self) -> bool {
         self.modified > 0 || self.untracked > 0 || self.staged > 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(root: &Path, remote: Option<&Path>) -> GitManager {
+        let mut config = Config::default();
+        config.root_directory = root.to_path_buf();
+        config.git_enabled = true;
+        config.git_repository = remote.map(|remote| remote.to_string_lossy().to_string());
+        config.git_username = Some("Test".to_string());
+        config.git_email = Some("test@example.com".to_string());
+        let manager = GitManager::new(config);
+        manager.init_repository().unwrap();
+        manager
+    }
+
+    fn head_tree(root: &Path) -> Vec<String> {
+        let repo = Repository::open(root).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        tree.iter().filter_map(|entry| entry.name().map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn changes_are_committed_even_if_the_fetch_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes");
+        fs::create_dir(&notes).unwrap();
+        let manager = manager(&notes, Some(&dir.path().join("missing.git")));
+        fs::write(notes.join("note.md"), "# Note").unwrap();
+
+        let error = manager.commit_and_push().unwrap_err();
+        assert!(format!("{:#}", error).starts_with("Committed locally"), "{:#}", error);
+        assert!(head_tree(&notes).contains(&"note.md".to_string()));
+    }
+
+    #[test]
+    fn only_staged_changes_are_committed_when_some_are_staged() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), None);
+        fs::write(dir.path().join("a.md"), "a").unwrap();
+        fs::write(dir.path().join("b.md"), "b").unwrap();
+        manager.stage(&dir.path().join("a.md")).unwrap();
+
+        assert!(manager.commit_and_push().unwrap() == CommitOutcome::Committed);
+        assert!(head_tree(dir.path()).contains(&"a.md".to_string()));
+        assert!(!head_tree(dir.path()).contains(&"b.md".to_string()));
+
+        // With nothing staged, everything is committed again
+        assert!(manager.autocommit(false).unwrap() == CommitOutcome::Committed);
+        assert!(head_tree(dir.path()).contains(&"b.md".to_string()));
+    }
+
+    #[test]
+    fn statuses_are_reused_until_invalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), None);
+        fs::write(dir.path().join("a.md"), "a").unwrap();
+
+        let first = manager.file_statuses().unwrap();
+        assert!(first.contains_key(&dir.path().join("a.md")));
+
+        fs::write(dir.path().join("b.md"), "b").unwrap();
+        assert!(Arc::ptr_eq(&first, &manager.file_statuses().unwrap()));
+
+        manager.invalidate_status_cache();
+        assert!(manager.file_statuses().unwrap().contains_key(&dir.path().join("b.md")));
+    }
+
+    #[test]
+    fn folders_above_changed_files_count_as_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), None);
+        fs::create_dir_all(dir.path().join("work/2024")).unwrap();
+        fs::write(dir.path().join("work/2024/plan.md"), "plan").unwrap();
+        fs::create_dir(dir.path().join("personal")).unwrap();
+
+        let changed = manager.changed_paths().unwrap();
+        assert!(changed.contains(&dir.path().join("work/2024/plan.md")));
+        assert!(changed.contains(&dir.path().join("work/2024")));
+        assert!(changed.contains(&dir.path().join("work")));
+        assert!(!changed.contains(&dir.path().join("personal")));
+    }
+
+    #[test]
+    fn a_failed_scan_is_kept_until_invalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), None);
+        fs::rename(dir.path().join(".git"), dir.path().join("moved")).unwrap();
+        assert!(manager.file_statuses().is_err());
+
+        fs::rename(dir.path().join("moved"), dir.path().join(".git")).unwrap();
+        assert!(manager.file_statuses().is_err());
+        manager.invalidate_status_cache();
+        assert!(manager.file_statuses().is_ok());
+    }
+
+    #[test]
+    fn deleted_notes_are_removed_in_the_next_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), None);
+        fs::write(dir.path().join("keep.md"), "keep").unwrap();
+        fs::write(dir.path().join("gone.md"), "gone").unwrap();
+        assert!(manager.autocommit(false).unwrap() == CommitOutcome::Committed);
+        assert!(head_tree(dir.path()).contains(&"gone.md".to_string()));
+
+        fs::remove_file(dir.path().join("gone.md")).unwrap();
+        manager.invalidate_status_cache();
+        assert!(manager.autocommit(false).unwrap() == CommitOutcome::Committed);
+        assert!(!head_tree(dir.path()).contains(&"gone.md".to_string()));
+        assert!(head_tree(dir.path()).contains(&"keep.md".to_string()));
+    }
+
+    #[test]
+    fn merge_commits_use_the_commit_message_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        Repository::init_bare(&remote).unwrap();
+        let first = dir.path().join("first");
+        fs::create_dir(&first).unwrap();
+        let first = manager(&first, Some(&remote));
+        fs::write(first.repo_path.join("a.md"), "a").unwrap();
+        first.commit_and_push().unwrap();
+
+        Repository::clone(&remote.to_string_lossy(), dir.path().join("second")).unwrap();
+        let mut second = manager(&dir.path().join("second"), Some(&remote));
+        second.config.date_format = Some("%Y".to_string());
+        second.config.commit_message_template = Some("Sync {{date}}: {{files}}".to_string());
+        fs::write(second.repo_path.join("b.md"), "b").unwrap();
+        second.autocommit(false).unwrap();
+
+        fs::write(first.repo_path.join("c.md"), "c").unwrap();
+        first.commit_and_push().unwrap();
+        assert!(second.pull_changes().unwrap() == PullOutcome::Merged);
+
+        let repo = Repository::open(&second.repo_path).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let year = chrono::Local::now().format("%Y").to_string();
+        assert_eq!(head.message(), Some(format!("Sync {}: c.md", year).as_str()));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::commands;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+pub const FILE_TREE: &str = "File tree";
+pub const GIT: &str = "Git";
+
+/// Every key binding that isn't a palette command, grouped by the screen it works in
+pub const KEY_BINDINGS: &[(&str, &[(&str, &str)])] = &[
+    (
+        FILE_TREE,
+        &[
+            ("j / ↓", "Move down"),
+            ("k / ↑", "Move up"),
+            ("PgDn / PgUp", "Move down / up by a screenful"),
+            ("Space / →", "Expand folder / Enter line navigation"),
+            ("Tab / Shift+Tab", "Go back / forward through recently opened notes"),
+        ],
+    ),
+    (GIT, &[]),
+    (
+        "Line navigation",
+        &[
+            ("j / ↓", "Next line"),
+            ("k / ↑", "Previous line"),
+            ("y", "Copy the line"),
+            ("Y", "Copy the whole note"),
+            ("r", "Copy the note as rendered plain text"),
+            ("Enter", "Follow a link to another note"),
+            ("z", "Fold / unfold the section under a heading"),
+            ("B", "Show who last changed each line"),
+            ("i", "Edit the note"),
+            ("← / Esc", "Back to the file tree"),
+        ],
+    ),
+    (
+        "Split view",
+        &[
+            ("j / k", "Scroll"),
+            ("PgUp / PgDn", "Scroll a page"),
+            ("Home / End", "Jump to the top / bottom"),
+            ("i", "Edit the note"),
+            ("v / Esc", "Back to the file tree"),
+        ],
+    ),
+    (
+        "Command palette",
+        &[
+            ("Tab", "Complete the command name"),
+            ("↑ / ↓", "Choose among the matching commands"),
+            ("Enter", "Run the command"),
+            ("Esc", "Close"),
+        ],
+    ),
+    (
+        "Configuration",
+        &[
+            ("↑ / ↓ / Tab", "Move between settings"),
+            ("Space / ← / →", "Toggle a checkbox / pick a choice"),
+            ("← / → / Home / End", "Move the cursor in a text setting, the search and every prompt"),
+            ("Enter", "Save and close"),
+            ("Esc", "Close without saving"),
+        ],
+    ),
+    (
+        "Built-in editor (editor set to \"builtin\")",
+        &[
+            ("Arrows / Home / End", "Move the cursor"),
+            ("PgUp / PgDn", "Move by a screenful"),
+            ("Ctrl+S", "Save the note"),
+            ("Esc", "Close, twice to discard unsaved changes"),
+        ],
+    ),
+];
+
+/// The help text, one section per screen, with the palette commands under their sections
+pub fn help_lines(palette_key: char) -> Vec<Line<'static>> {
+    let sections: Vec<(&str, Vec<(String, &str)>)> = KEY_BINDINGS
+        .iter()
+        .map(|(section, bindings)| {
+            let mut rows: Vec<(String, &str)> = bindings.iter().map(|(key, action)| (key.to_string(), *action)).collect();
+            rows.extend(
+                commands::COMMANDS
+                    .iter()
+                    .filter(|command| command.section == *section)
+                    .map(|command| (command.key.to_string(), command.description)),
+            );
+            if *section == FILE_TREE {
+                rows.push((palette_key.to_string(), "Run a command by name"));
+            }
+            (*section, rows)
+        })
+        .collect();
+
+    let key_width = sections
+        .iter()
+        .flat_map(|(_, rows)| rows.iter())
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (section, rows) in sections {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            section.to_string(),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )));
+        for (key, action) in rows {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<width$}  ", key, width = key_width), Style::default().fg(Color::Cyan)),
+                Span::raw(action.to_string()),
+            ]));
+        }
+    }
+    lines
+}
+
+/// What a file tree navigation key does, `Space` standing for the space bar
+pub fn file_tree_binding(key: char) -> Option<&'static str> {
+    let (_, bindings) = KEY_BINDINGS.iter().find(|(section, _)| *section == FILE_TREE)?;
+    bindings.iter().find_map(|(keys, action)| {
+        keys.split(" / ")
+            .any(|name| match name {
+                "Space" => key == ' ',
+                _ => name.chars().count() == 1 && name.starts_with(key),
+            })
+            .then_some(*action)
+    })
+}
@@ -0,0 +1,364 @@
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::config::Config;
+use crate::file_tree::{self, FileTree, MARKDOWN_EXTENSIONS};
+
+/// A link found in a note, with its resolved target if it points inside the vault
+#[derive(Debug, Clone)]
+pub struct NoteLink {
+    pub source: PathBuf,
+    // 0-based line of the source the link is on
+    pub line: usize,
+    // The link as written, e.g. `[[Ideas]]` or `ideas.md`
+    pub text: String,
+    pub target: Option<PathBuf>,
+}
+
+/// Vault-wide index of links between notes
+pub struct LinkIndex {
+    links: Vec<NoteLink>,
+}
+
+impl LinkIndex {
+    /// Scan every note in the vault and resolve its markdown and `[[wiki]]` links
+    pub fn build(config: &Config) -> Self {
+        let root = &config.root_directory;
+        let files = FileTree::collect_markdown_files(config);
+
+        // Wiki-links refer to notes by name, so map lowercase stems to paths
+        let mut by_stem: HashMap<String, PathBuf> = HashMap::new();
+        for path in &files {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                by_stem.entry(stem.to_lowercase()).or_insert_with(|| path.clone());
+            }
+        }
+
+        let markdown_link = markdown_link_regex();
+        let wiki_link = wiki_link_regex();
+
+        let mut links = Vec::new();
+        for source in &files {
+            let content = match fs::read_to_string(source) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+
+            for (number, line) in content.lines().enumerate() {
+                for captures in markdown_link.captures_iter(line) {
+                    let url = &captures[1];
+                    if is_external_url(url) || !is_note_url(url) {
+                        continue;
+                    }
+                    links.push(NoteLink {
+                        source: source.clone(),
+                        line: number,
+                        text: url.to_string(),
+                        target: resolve_relative_link(root, source, url).filter(|p| p.is_file()),
+                    });
+                }
+
+                for captures in wiki_link.captures_iter(line) {
+                    let name = captures[1].trim();
+                    links.push(NoteLink {
+                        source: source.clone(),
+                        line: number,
+                        text: captures[0].to_string(),
+                        target: resolve_wiki_link(root, &by_stem, name),
+                    });
+                }
+            }
+        }
+
+        Self { links }
+    }
+
+    /// Notes that link to `target`, in vault order
+    pub fn backlinks(&self, target: &Path) -> Vec<PathBuf> {
+        let mut sources: Vec<PathBuf> = self.links
+            .iter()
+            .filter(|link| link.target.as_deref() == Some(target) && link.source != target)
+            .map(|link| link.source.clone())
+            .collect();
+        sources.dedup();
+        sources
+    }
+
+    /// Links whose target is not a note in the vault, in vault order
+    pub fn broken_links(&self) -> Vec<NoteLink> {
+        self.links.iter().filter(|link| link.target.is_none()).cloned().collect()
+    }
+}
+
+/// Point links at notes moved by `renames` to where the notes are now
+///
+/// Each rename is an old and a new path; a renamed folder takes every note in
+/// it along. Markdown links keep their style (relative or from the root) and
+/// `[[wiki]]` links their name or path form. Returns how many notes changed.
+pub fn update_links_after_renames(config: &Config, renames: &[(PathBuf, PathBuf)]) -> Result<usize> {
+    let root = &config.root_directory;
+    let undo: Vec<(PathBuf, PathBuf)> = renames.iter().map(|(old, new)| (new.clone(), old.clone())).collect();
+    let markdown_link = markdown_link_regex();
+    let wiki_link = wiki_link_regex();
+
+    let mut changed = 0;
+    for source in FileTree::collect_markdown_files(config) {
+        let content = match fs::read_to_string(&source) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        // Relative links were written from where the note used to be
+        let old_source = renamed_path(&source, &undo).unwrap_or_else(|| source.clone());
+
+        let updated: String = content
+            .split_inclusive('\n')
+            .map(|line| {
+                let line = markdown_link.replace_all(line, |captures: &Captures| {
+                    let url = &captures[1];
+                    let moved = if is_external_url(url) || !is_note_url(url) {
+                        None
+                    } else {
+                        resolve_relative_link(root, &old_source, url)
+                            .and_then(|target| renamed_path(&target, renames))
+                            .map(|target| note_url(root, &source, url, &target))
+                    };
+                    match moved {
+                        Some(new_url) => captures[0].replacen(url, &new_url, 1),
+                        None => captures[0].to_string(),
+                    }
+                });
+                wiki_link
+                    .replace_all(&line, |captures: &Captures| {
+                        let name = captures[1].trim();
+                        match renamed_wiki_name(root, renames, name) {
+                            Some(new_name) => captures[0].replacen(name, &new_name, 1),
+                            None => captures[0].to_string(),
+                        }
+                    })
+                    .into_owned()
+            })
+            .collect();
+
+        if updated != content {
+            fs::write(&source, updated).with_context(|| format!("Failed to update links in {}", source.display()))?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Where `path` is after `renames`, if one of them moved it or a folder it is in
+fn renamed_path(path: &Path, renames: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    renames.iter().find_map(|(old, new)| moved_path(path, old, new))
+}
+
+/// Where `path` is after renaming `old` to `new`, if that moved it
+fn moved_path(path: &Path, old: &Path, new: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(old).ok()?;
+    Some(if rest.as_os_str().is_empty() { new.to_path_buf() } else { new.join(rest) })
+}
+
+/// `url` rewritten to point at `target` from `source`, in the same style
+fn note_url(root: &Path, source: &Path, url: &str, target: &Path) -> String {
+    let fragment = url.find('#').map_or("", |i| &url[i..]);
+    let path = if url.starts_with('/') {
+        Path::new("/").join(target.strip_prefix(root).unwrap_or(target))
+    } else {
+        relative_path(source.parent().unwrap_or(root), target)
+    };
+    format!("{}{}", path.to_string_lossy().replace(' ', "%20"), fragment)
+}
+
+/// The new name for a `[[wiki]]` link whose note was renamed, if it was
+fn renamed_wiki_name(root: &Path, renames: &[(PathBuf, PathBuf)], name: &str) -> Option<String> {
+    if name.contains('/') {
+        let old = normalize_path(&root.join(name));
+        let explicit = old.extension().is_some();
+        let new = renames.iter().find_map(|(old_path, new_path)| {
+            // `[[ideas/plan]]` leaves out the extension of the note it links to
+            let candidate = match old_path.extension() {
+                Some(extension) if !explicit => old.with_extension(extension),
+                _ => old.clone(),
+            };
+            moved_path(&candidate, old_path, new_path)
+        })?;
+        let new = new.strip_prefix(root).ok()?;
+        let new = if explicit { new.to_path_buf() } else { new.with_extension("") };
+        return Some(new.to_string_lossy().to_string());
+    }
+
+    // Name-only links match a note's stem, so keep writing just the stem
+    let explicit = file_tree::is_markdown_file(Path::new(name));
+    let stem = if explicit { Path::new(name).file_stem()?.to_str()? } else { name };
+    let (_, new) = renames.iter().find(|(old, _)| {
+        file_tree::is_markdown_file(old) && old.file_stem().and_then(|s| s.to_str()).is_some_and(|old_stem| old_stem.eq_ignore_ascii_case(stem))
+    })?;
+    let new_name = if explicit { new.file_name() } else { new.file_stem() };
+    Some(new_name?.to_string_lossy().to_string())
+}
+
+/// `to` written relative to the folder `from`, e.g. `../ideas/plan.md`
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let shared = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in shared..from.len() {
+        path.push("..");
+    }
+    for component in &to[shared..] {
+        path.push(component.as_os_str());
+    }
+    path
+}
+
+fn wiki_link_regex() -> Regex {
+    Regex::new(r"\[\[([^\]|#]+)(?:[#|][^\]]*)?\]\]").unwrap()
+}
+
+fn markdown_link_regex() -> Regex {
+    Regex::new(r#"\[[^\]]*\]\(<?([^)\s>]+)>?(?:\s+"[^"]*")?\)"#).unwrap()
+}
+
+/// URL of the first markdown link to another note in `line`
+pub fn note_link_in_line(line: &str) -> Option<String> {
+    markdown_link_regex()
+        .captures_iter(line)
+        .map(|captures| captures[1].to_string())
+        .find(|url| !is_external_url(url) && is_note_url(url))
+}
+
+fn is_external_url(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+fn is_note_url(url: &str) -> bool {
+    let path = url.split('#').next().unwrap_or("");
+    file_tree::is_markdown_file(Path::new(path))
+}
+
+/// Resolve a markdown link URL written in `source` to a path under `root`
+///
+/// Relative URLs are resolved against the source note's directory, absolute ones
+/// against the vault root. Returns `None` if the result would escape the vault.
+pub fn resolve_relative_link(root: &Path, source: &Path, url: &str) -> Option<PathBuf> {
+    let path = url.split('#').next().unwrap_or("").replace("%20", " ");
+    if path.is_empty() {
+        return None;
+    }
+
+    let joined = if let Some(stripped) = path.strip_prefix('/') {
+        root.join(stripped)
+    } else {
+        source.parent().unwrap_or(root).join(path)
+    };
+
+    let normalized = normalize_path(&joined);
+    if normalized.starts_with(root) {
+        Some(normalized)
+    } else {
+        None
+    }
+}
+
+fn resolve_wiki_link(root: &Path, by_stem: &HashMap<String, PathBuf>, name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = normalize_path(&root.join(name));
+        if path.extension().is_some() {
+            return Some(path).filter(|p| p.starts_with(root) && p.is_file());
+        }
+        return MARKDOWN_EXTENSIONS
+            .iter()
+            .map(|extension| path.with_extension(extension))
+            .find(|p| p.starts_with(root) && p.is_file());
+    }
+
+    let stem = if file_tree::is_markdown_file(Path::new(name)) {
+        Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or(name)
+    } else {
+        name
+    };
+    by_stem.get(&stem.to_lowercase()).cloned()
+}
+
+/// Lexically resolve `.` and `..` components without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, name: &str, content: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn read(root: &Path, name: &str) -> String {
+        fs::read_to_string(root.join(name)).unwrap()
+    }
+
+    fn vault(root: &Path) -> Config {
+        let mut config = Config::default();
+        config.root_directory = root.to_path_buf();
+        config
+    }
+
+    #[test]
+    fn relative_paths_climb_out_of_the_source_folder() {
+        assert_eq!(relative_path(Path::new("/v/a"), Path::new("/v/a/b.md")), PathBuf::from("b.md"));
+        assert_eq!(relative_path(Path::new("/v/a/c"), Path::new("/v/b/d.md")), PathBuf::from("../../b/d.md"));
+    }
+
+    #[test]
+    fn links_follow_renamed_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "index.md", "See [intro](docs/01-intro.md#start), [[01-intro]] and [web](https://x.org/01-intro.md)\n");
+        // The notes after swapping places, with the links as they were written
+        write(root, "docs/01-plan.md", "Back to [intro](01-intro.md) and [root](/docs/01-intro.md)\r\n");
+        write(root, "docs/02-intro.md", "# Intro\n");
+
+        let renames = vec![
+            (root.join("docs/01-intro.md"), root.join("docs/02-intro.md")),
+            (root.join("docs/02-plan.md"), root.join("docs/01-plan.md")),
+        ];
+        assert_eq!(update_links_after_renames(&vault(root), &renames).unwrap(), 2);
+        assert_eq!(
+            read(root, "index.md"),
+            "See [intro](docs/02-intro.md#start), [[02-intro]] and [web](https://x.org/01-intro.md)\n"
+        );
+        assert_eq!(read(root, "docs/01-plan.md"), "Back to [intro](02-intro.md) and [root](/docs/02-intro.md)\r\n");
+    }
+
+    #[test]
+    fn links_into_a_renamed_folder_are_updated() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "index.md", "[a](01-ideas/a%20b.md) [[01-ideas/a b]] [[other]]\n");
+        write(root, "02-ideas/a b.md", "[up](../index.md)\n");
+        write(root, "other.md", "");
+
+        let renames = vec![(root.join("01-ideas"), root.join("02-ideas"))];
+        assert_eq!(update_links_after_renames(&vault(root), &renames).unwrap(), 1);
+        assert_eq!(read(root, "index.md"), "[a](02-ideas/a%20b.md) [[02-ideas/a b]] [[other]]\n");
+        assert_eq!(read(root, "02-ideas/a b.md"), "[up](../index.md)\n");
+    }
+}
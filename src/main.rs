@@ -1,36 +1,65 @@
 use anyhow::Result;
-use arboard::Clipboard;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, Clear, ClearType},
 };
 use image::DynamicImage;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
+    buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
     Frame, Terminal,
 };
-use ratatui_image::{picker::Picker, protocol::StatefulProtocol, StatefulImage};
+use ratatui_image::{
+    picker::{Picker, ProtocolType},
+    protocol::StatefulProtocol,
+    StatefulImage,
+};
 use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
     fs,
-    io,
-    path::PathBuf,
+    hash::{Hash, Hasher},
+    io::{self, BufRead},
+    path::{Component, Path, PathBuf},
     process::Command,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
+mod cli;
+mod clipboard;
+mod commands;
 mod config;
+mod crypto;
+mod editor;
+mod export;
 mod file_tree;
+mod frontmatter;
 mod git;
+mod help;
+mod links;
 mod markdown;
+mod search;
+mod settings;
+mod tags;
+mod templates;
 
-use config::Config;
+use cli::CliArgs;
+use clipboard::SystemClipboard;
+use config::{Config, Vault};
+use editor::{LineInput, TextEditor};
 use file_tree::FileTree;
-use git::GitManager;
+use git::{BlameLine, CommitOutcome, FetchProgress, FileStatus, GitManager, PullOutcome};
+use links::{LinkIndex, NoteLink};
 use markdown::MarkdownRenderer;
+use search::{IndexUpdate, SearchHit, SearchIndex};
+use settings::{ConfigField, FieldKind};
+use tags::TagIndex;
 
 #[derive(Debug, Clone, PartialEq)]
 enum AppMode {
@@ -38,7 +67,139 @@ enum AppMode {
     Config,
     Rename,
     DeleteConfirm,
+    DiscardConfirm,
     LineNavigation,
+    QuitConfirm,
+    Tags,
+    Backlinks,
+    Conflicts,
+    Staging,
+    TemplatePicker,
+    IdentityPrompt,
+    SplitPreview,
+    PassphrasePrompt,
+    VaultPicker,
+    Editor,
+    Search,
+    ExportVault,
+    BrokenLinks,
+    CommandPalette,
+}
+
+/// What to do once the passphrase prompt is answered
+#[derive(Debug, Clone, PartialEq)]
+enum PassphraseAction {
+    Decrypt,
+    Encrypt(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GitOperation {
+    Push,
+    CommitStaged,
+    Pull,
+    AutoCommit,
+}
+
+impl GitOperation {
+    fn label(&self) -> &'static str {
+        match self {
+            GitOperation::Push => "Committing and pushing",
+            GitOperation::CommitStaged => "Committing staged changes and pushing",
+            GitOperation::Pull => "Pulling",
+            GitOperation::AutoCommit => "Auto-committing",
+        }
+    }
+}
+
+enum GitTaskResult {
+    Push(Result<CommitOutcome>),
+    Pull(Result<PullOutcome>),
+    AutoCommit(Result<CommitOutcome>),
+}
+
+/// A git operation running on a background thread
+struct GitTask {
+    operation: GitOperation,
+    receiver: Receiver<GitTaskResult>,
+    started: Instant,
+    progress_receiver: Receiver<FetchProgress>,
+    // Latest progress of the fetch the operation makes, if it has started one
+    progress: Option<FetchProgress>,
+}
+
+/// The search index being filled in the background
+struct IndexBuild {
+    receiver: Receiver<IndexUpdate>,
+    indexed: usize,
+    total: Option<usize>,
+}
+
+// Source lines parsed at a time in a large note, and rendered lines kept ready below the view
+const LAZY_CHUNK_LINES: usize = 200;
+const LAZY_BUFFER_LINES: usize = 200;
+
+// Entries kept visible above and below the selection when the file tree scrolls
+const TREE_SCROLL_PADDING: usize = 2;
+
+// Lines an image referenced by a note takes up when drawn inline
+const INLINE_IMAGE_ROWS: usize = 12;
+
+const WELCOME_FILE_NAME: &str = "welcome.md";
+
+// Value of the `editor` setting that edits notes inside RNotes
+const BUILTIN_EDITOR: &str = "builtin";
+
+// Columns taken by the commit and author in the blame view
+const BLAME_GUTTER_WIDTH: usize = 21;
+
+// Longest file name taken from the first line of a note pasted from the clipboard
+const NOTE_NAME_MAX_CHARS: usize = 60;
+
+// Recently opened notes kept for Tab / Shift-Tab
+const RECENT_NOTES_LIMIT: usize = 50;
+
+// Non-empty lines of a note shown when confirming its deletion
+const DELETE_PREVIEW_LINES: usize = 4;
+
+// Matching lines listed by a search
+const SEARCH_RESULTS_LIMIT: usize = 200;
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Pick the spinner frame for an operation that began at `started`
+fn spinner_frame(started: Instant) -> &'static str {
+    let frame = (started.elapsed().as_millis() / 100) as usize % SPINNER_FRAMES.len();
+    SPINNER_FRAMES[frame]
+}
+
+/// Identifies what a cached rendering was made from
+#[derive(PartialEq)]
+struct RenderKey {
+    path: PathBuf,
+    content_hash: u64,
+    wrap_width: usize,
+}
+
+struct RenderCache {
+    key: RenderKey,
+    lines: Vec<Line<'static>>,
+    sources: Vec<Option<usize>>,
+    images: Vec<(usize, PathBuf)>,
+    conflicts: bool,
+}
+
+/// Progress through a large note that is rendered a chunk at a time
+struct LazyRender {
+    // Source line ranges, parsed independently of each other
+    chunks: Vec<std::ops::Range<usize>>,
+    // Number of chunks rendered so far, from the top
+    rendered: usize,
+}
+
+impl LazyRender {
+    fn is_complete(&self) -> bool {
+        self.rendered == self.chunks.len()
+    }
 }
 
 pub struct App {
@@ -46,106 +207,483 @@ pub struct App {
     file_tree: FileTree,
     current_content: String,
     current_file: Option<PathBuf>,
+    current_word_count: usize,
+    current_file_info: Option<String>,
+    // Modification time of the open file when it was loaded, to notice changes made elsewhere
+    current_file_modified: Option<SystemTime>,
+    // How the open note was decoded, so the built-in editor writes it back the same way
+    current_crlf: bool,
+    current_lossy: bool,
     mode: AppMode,
-    config_input: String,
-    config_field: usize, // 0 = root_dir, 1 = editor, 2 = git_enabled, 3 = git_repo, 4 = git_username, 5 = git_email
-    rename_input: String,
+    // Config screen: the settings as edited so far, one row per setting, and the text of the selected one
+    config_draft: serde_json::Value,
+    config_fields: Vec<ConfigField>,
+    config_field: usize,
+    config_input: LineInput,
+    rename_input: LineInput,
+    // Where to write the whole vault as one document
+    export_vault_input: LineInput,
+    // Export target the user was warned already exists, replaced on the next Enter
+    export_vault_overwrite: Option<PathBuf>,
+    // Command palette: the typed command and the highlighted match
+    command_input: LineInput,
+    command_selection: usize,
     delete_target: Option<PathBuf>,
+    delete_preview: Vec<String>,
+    // Files and folders a folder deletion removes, the folder itself included
+    delete_counts: Option<(usize, usize)>,
+    discard_target: Option<PathBuf>,
     // Line navigation fields
     content_lines: Vec<String>,
     rendered_lines: Vec<ratatui::text::Line<'static>>, // For formatted line navigation
+    // Source line shown by each rendered line; empty means they match one to one
+    rendered_source_lines: Vec<Option<usize>>,
+    // Set when the note has merge conflict markers and is shown raw
+    rendered_conflicts: bool,
+    // Last rendered note, reused while its content and the wrap width are unchanged
+    render_cache: Option<RenderCache>,
+    // Set while a large note is only partly rendered
+    lazy_render: Option<LazyRender>,
     line_selection: usize,
+    // Source lines of the headings whose sections are collapsed in the open note
+    folded_headings: HashSet<usize>,
+    // Selected line of each note visited this session, restored when it is reopened
+    line_positions: HashMap<PathBuf, usize>,
+    // Last commit of each source line, shown beside the note in line navigation
+    blame: Option<Vec<Option<BlameLine>>>,
+    // Notes opened this session, most recent first, and the one Tab/Shift-Tab moved to
+    recent_notes: VecDeque<PathBuf>,
+    recent_position: usize,
+    // First source line shown in the split source/preview mode
+    split_scroll: usize,
+    // Inner width of the content pane, measured on the last draw
+    content_width: u16,
+    // Rows of the file tree pane, measured on the last draw
+    tree_height: u16,
+    // Set when something outside ratatui drew on the terminal (e.g. the editor)
+    needs_full_redraw: bool,
+    // Set by --read-only or the config; blocks every key that changes the vault
+    read_only: bool,
+    // Set by --read-only, which keeps read-only mode on whatever the config says
+    cli_read_only: bool,
+    // Draw without colors (NO_COLOR or the monochrome config option)
+    monochrome: bool,
+    // Where the current image was drawn last frame, left in color
+    image_area: Option<Rect>,
     should_quit: bool,
     git_manager: GitManager,
+    // Tag browsing
+    tag_index: Option<TagIndex>,
+    tag_entries: Vec<(String, usize)>,
+    tag_selection: usize,
+    active_tag: Option<String>,
+    // Backlinks to the current note
+    link_index: Option<LinkIndex>,
+    backlink_entries: Vec<PathBuf>,
+    backlink_selection: usize,
+    // Links to notes that do not exist, across the vault
+    broken_link_entries: Vec<NoteLink>,
+    broken_link_selection: usize,
+    // Full-text index of the vault, built in the background and kept up to date
+    search_index: SearchIndex,
+    search_index_build: Option<IndexBuild>,
+    // Set when notes changed since the index was last brought up to date
+    search_index_stale: bool,
+    search_input: LineInput,
+    search_results: Vec<SearchHit>,
+    search_selection: usize,
+    // Files left with merge conflicts
+    conflict_entries: Vec<PathBuf>,
+    conflict_selection: usize,
+    // Changed files listed for staging
+    staging_entries: Vec<(PathBuf, FileStatus)>,
+    staging_selection: usize,
+    // Template picker entries; `None` is the built-in default
+    template_entries: Vec<Option<PathBuf>>,
+    template_selection: usize,
+    vault_selection: usize,
+    // Built-in editor for the open note, and whether Esc was pressed once with unsaved changes
+    text_editor: Option<TextEditor>,
+    editor_discard_pending: bool,
+    // Key binding overlay, drawn over any screen
+    show_help: bool,
+    help_scroll: u16,
+    // Full text of the last failed git operation, and whether it is shown over the screen
+    git_error: Option<String>,
+    show_git_error: bool,
+    // Encrypted notes: session passphrase and decrypted text, which never touches the disk
+    encryption_passphrase: Option<String>,
+    decrypted_notes: HashMap<PathBuf, String>,
+    passphrase_input: LineInput,
+    passphrase_action: Option<PassphraseAction>,
+    clipboard: SystemClipboard,
+    git_task: Option<GitTask>,
+    quit_after_git_task: bool,
+    // Name and email in the global git config, read when the identity prompt opens
+    global_identity: (Option<String>, Option<String>),
+    // Autocommit timer and the time of the last commit it made
+    last_autocommit_check: Instant,
+    last_autocommit: Option<chrono::DateTime<chrono::Local>>,
+    status_message: Option<String>,
     markdown_renderer: MarkdownRenderer,
     // Image handling fields
     current_image: Option<DynamicImage>,
     image_picker: Option<Picker>,
     image_state: Option<Box<dyn StatefulProtocol>>,
+    // Draw images referenced by notes inline; needs a sixel, kitty or iTerm2 capable terminal
+    inline_images: bool,
+    // First rendered line and file of each local image in the open note
+    rendered_images: Vec<(usize, PathBuf)>,
+    // Decoded inline images; `None` when the file could not be loaded
+    inline_image_states: HashMap<PathBuf, Option<Box<dyn StatefulProtocol>>>,
 }
 
 impl App {
-    pub fn new() -> Result<App> {
-        let config = Config::load_or_create()?;
-        let file_tree = FileTree::new(&config.root_directory)?;
-        let git_manager = GitManager::new(config.clone());
+    pub fn new(args: &CliArgs) -> Result<App> {
+        Self::with_config(Config::load_or_create()?, args)
+    }
+
+    /// Start on an already loaded config
+    fn with_config(mut config: Config, args: &CliArgs) -> Result<App> {
+        let config_error = config.take_load_error();
+        if let Some(root) = &args.root {
+            config.override_root(root.clone());
+        }
+
+        // A note given on the command line opens in its vault, or its own folder
+        let mut outside_vault = false;
+        let start_file = match &args.file {
+            Some(file) => {
+                let file = fs::canonicalize(file)
+                    .map_err(|e| anyhow::anyhow!("Cannot open {}: {}", file.display(), e))?;
+                let root = fs::canonicalize(&config.root_directory)
+                    .unwrap_or_else(|_| config.root_directory.clone());
+                match file.strip_prefix(&root) {
+                    Ok(relative) => Some(config.root_directory.join(relative)),
+                    Err(_) => {
+                        outside_vault = true;
+                        config.suspend_git(true);
+                        if let Some(parent) = file.parent() {
+                            config.override_root(parent.to_path_buf());
+                        }
+                        Some(file)
+                    }
+                }
+            }
+            None => None,
+        };
+        if !config.root_directory.is_dir() {
+            return Err(anyhow::anyhow!("Notes directory {} does not exist", config.root_directory.display()));
+        }
+
+        let file_tree = FileTree::new(&config)?;
+        let git_manager = GitManager::new(config.git_settings());
         
         // Initialize Git repository if enabled
-        if config.git_enabled {
+        let mut initial_pull = false;
+        let mut status_message = config_error.map(|e| format!("⚠ {}", e));
+        let read_only = args.read_only || config.read_only;
+        if git_manager.is_enabled() && read_only && !git_manager.is_git_repository() {
+            status_message = Some("⚠ Read-only mode: not initializing a Git repository".to_string());
+        } else if git_manager.is_enabled() {
             if let Err(e) = git_manager.init_repository() {
-                eprintln!("Warning: Failed to initialize Git repository: {}", e);
+                status_message = Some(format!("⚠ Failed to initialize Git repository: {}", e));
             } else {
-                // Perform initial git pull to sync with remote (quiet mode)
-                if let Err(e) = git_manager.pull_changes_with_feedback(false) {
-                    eprintln!("Warning: Failed to pull initial changes: {}", e);
-                }
+                // Perform initial git pull to sync with remote, unless the user pulls manually
+                initial_pull = config.pull_on_startup;
             }
         }
+        if !file_tree.invalid_ignore_patterns().is_empty() && status_message.is_none() {
+            status_message = Some(invalid_patterns_message(file_tree.invalid_ignore_patterns()));
+        }
+        if config.date_format.is_some() && config.date_format().is_none() && status_message.is_none() {
+            status_message = Some("⚠ Invalid date_format in config, using the default".to_string());
+        }
+        if config.command_palette_key() != config.command_palette_key && status_message.is_none() {
+            status_message = Some(format!(
+                "⚠ command_palette_key '{}' is already bound, using ':'",
+                config.command_palette_key
+            ));
+        }
+        if config.commit_message_template.is_some() && config.commit_message_template().is_none() && status_message.is_none() {
+            status_message = Some(format!(
+                "⚠ Invalid commit_message_template in config (placeholders: {}), using the default",
+                config::COMMIT_MESSAGE_PLACEHOLDERS.join(" ")
+            ));
+        }
         
-        // Create welcome file if it doesn't exist
-        let welcome_path = config.root_directory.join("welcome.md");
-        if !welcome_path.exists() {
+        // Create the welcome file in a new vault, unless the user opted out or deleted it
+        let welcome_path = config.root_directory.join(WELCOME_FILE_NAME);
+        let create_welcome = config.create_welcome_file
+            && !config.welcome_file_deleted()
+            && !welcome_path.exists()
+            && !outside_vault
+            && !read_only;
+        if create_welcome && FileTree::collect_markdown_files(&config).is_empty() {
             fs::write(
                 &welcome_path,
                 "# Welcome to RNotes!\n\nThis is your markdown notes manager.\n\n## Features:\n- Navigate through markdown files\n- Edit files with your preferred editor\n- VIM-like interface\n- Git integration for syncing notes\n\n## Usage:\n- Use arrow keys or j/k to navigate\n- Press Enter to edit a file\n- Press 'n' to create a new file\n- Press 'c' to open configuration\n- Press 'q' to quit\n- Press 'g' for Git operations\n\nHappy note-taking!",
             )?;
         }
 
+        // Half blocks are too coarse to be worth the space inline, so keep placeholders then
+        let mut image_picker = None;
+        let mut inline_images = false;
+        if config.inline_images {
+            let mut picker = Picker::from_termios().unwrap_or_else(|_| Picker::new((14, 8)));
+            inline_images = picker.guess_protocol() != ProtocolType::Halfblocks;
+            if !inline_images && status_message.is_none() {
+                status_message = Some("ℹ No terminal image support detected, showing image placeholders".to_string());
+            }
+            image_picker = Some(picker);
+        }
+
+        let monochrome = config.monochrome();
+        let clipboard = SystemClipboard::new(config.clipboard_fallback);
+        let recent_notes = remembered_recent_notes(&config);
         let mut app = App {
             config,
             file_tree,
             current_content: String::new(),
             current_file: None,
+            current_word_count: 0,
+            current_file_info: None,
+            current_file_modified: None,
+            current_crlf: false,
+            current_lossy: false,
             mode: AppMode::Normal,
-            config_input: String::new(),
+            config_draft: serde_json::Value::Null,
+            config_fields: Vec::new(),
             config_field: 0,
-            rename_input: String::new(),
+            config_input: LineInput::default(),
+            rename_input: LineInput::default(),
+            export_vault_input: LineInput::default(),
+            export_vault_overwrite: None,
+            command_input: LineInput::default(),
+            command_selection: 0,
             delete_target: None,
+            delete_preview: Vec::new(),
+            delete_counts: None,
+            discard_target: None,
             content_lines: Vec::new(),
             rendered_lines: Vec::new(),
+            rendered_conflicts: false,
+            render_cache: None,
+            lazy_render: None,
+            rendered_source_lines: Vec::new(),
             line_selection: 0,
+            line_positions: HashMap::new(),
+            blame: None,
+            recent_notes,
+            recent_position: 0,
+            split_scroll: 0,
+            content_width: 0,
+            tree_height: 0,
+            needs_full_redraw: false,
+            read_only,
+            cli_read_only: args.read_only,
+            monochrome,
+            image_area: None,
             should_quit: false,
             git_manager,
+            tag_index: None,
+            tag_entries: Vec::new(),
+            tag_selection: 0,
+            active_tag: None,
+            link_index: None,
+            backlink_entries: Vec::new(),
+            backlink_selection: 0,
+            broken_link_entries: Vec::new(),
+            broken_link_selection: 0,
+            search_index: SearchIndex::new(),
+            search_index_build: None,
+            search_index_stale: false,
+            search_input: LineInput::default(),
+            search_results: Vec::new(),
+            search_selection: 0,
+            conflict_entries: Vec::new(),
+            conflict_selection: 0,
+            staging_entries: Vec::new(),
+            staging_selection: 0,
+            template_entries: Vec::new(),
+            template_selection: 0,
+            vault_selection: 0,
+            text_editor: None,
+            editor_discard_pending: false,
+            show_help: false,
+            help_scroll: 0,
+            git_error: None,
+            show_git_error: false,
+            encryption_passphrase: None,
+            decrypted_notes: HashMap::new(),
+            passphrase_input: LineInput::default(),
+            passphrase_action: None,
+            clipboard,
+            git_task: None,
+            quit_after_git_task: false,
+            global_identity: (None, None),
+            last_autocommit_check: Instant::now(),
+            last_autocommit: None,
+            status_message,
             markdown_renderer: MarkdownRenderer::new(),
             current_image: None,
-            image_picker: None,
+            image_picker,
+            inline_images,
+            rendered_images: Vec::new(),
+            folded_headings: HashSet::new(),
+            inline_image_states: HashMap::new(),
             image_state: None,
         };
         
-        // Load the first file's content automatically
-        app.load_current_file_content()?;
+        // Load the requested note, or the first file's content
+        match &start_file {
+            Some(file) => app.open_note(file)?,
+            None => app.load_current_file_content()?,
+        }
+
+        if initial_pull {
+            app.start_git_task(GitOperation::Pull);
+        }
+        app.markdown_renderer.set_image_rows(if inline_images { INLINE_IMAGE_ROWS } else { 0 });
+        app.markdown_renderer.set_tab_width(app.config.tab_width);
+        app.markdown_renderer.set_flavor(app.config.markdown_flavor);
+        app.check_git_identity();
+        app.start_search_indexing();
         
         Ok(app)
     }
 
     pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        terminal.clear()?;
         loop {
-            // Force a clear and redraw to handle any terminal corruption
-            terminal.clear()?;
-            terminal.draw(|f| self.ui(f))?;
-
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match self.mode {
-                        AppMode::Normal => self.handle_normal_input(key.code)?,
-                        AppMode::Config => self.handle_config_input(key.code)?,
-                        AppMode::Rename => self.handle_rename_input(key.code)?,
-                        AppMode::DeleteConfirm => self.handle_delete_confirm_input(key.code)?,
-                        AppMode::LineNavigation => self.handle_line_navigation_input(key.code)?,
+            self.render_lazy_chunks(self.line_selection + LAZY_BUFFER_LINES);
+            terminal.draw(|f| {
+                self.ui(f);
+                if self.monochrome {
+                    strip_colors(f.buffer_mut(), self.image_area);
+                }
+            })?;
+            self.update_wrap_width();
+
+            // Poll so background git operations can report back while idle
+            if event::poll(Duration::from_millis(100))? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        self.status_message = None;
+                        self.handle_key(key)?;
+                        if self.needs_full_redraw {
+                            terminal.clear()?;
+                            self.needs_full_redraw = false;
+                        }
+                    }
+                    Event::Paste(text) => {
+                        self.status_message = None;
+                        self.handle_paste(&text);
                     }
+                    Event::Resize(_, _) => {
+                        // Ratatui resizes its buffers on the next draw; clear once so
+                        // no stale content survives outside the new area
+                        terminal.clear()?;
+                    }
+                    _ => {}
                 }
             }
 
+            self.poll_git_task()?;
+            self.poll_search_index();
+            self.maybe_autocommit();
+
             if self.should_quit {
+                if self.config.remember_recent_notes {
+                    self.store_recent_notes();
+                    if let Err(e) = self.config.save() {
+                        self.status_message = Some(format!("⚠ Failed to save recent notes: {}", e));
+                    }
+                }
                 break;
             }
         }
         Ok(())
     }
 
+    fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        let key_code = key.code;
+        if self.show_help {
+            self.handle_help_input(key_code);
+            return Ok(());
+        }
+        if self.show_git_error {
+            if matches!(key_code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q' | '!')) {
+                self.show_git_error = false;
+            }
+            return Ok(());
+        }
+        // Screens without text input open the help; elsewhere '?' is typed
+        if key_code == KeyCode::Char('?')
+            && matches!(self.mode, AppMode::Normal | AppMode::LineNavigation | AppMode::SplitPreview)
+        {
+            self.show_help = true;
+            self.help_scroll = 0;
+            return Ok(());
+        }
+
+        if self.read_only && self.is_mutating_key(key_code) {
+            self.status_message = Some("⚠ Read-only mode: changes are disabled".to_string());
+            return Ok(());
+        }
+
+        match self.mode {
+            AppMode::Normal => self.handle_normal_input(key_code),
+            AppMode::Config => self.handle_config_input(key_code),
+            AppMode::Rename => self.handle_rename_input(key_code),
+            AppMode::ExportVault => self.handle_export_vault_input(key_code),
+            AppMode::CommandPalette => self.handle_command_palette_input(key_code),
+            AppMode::DeleteConfirm => self.handle_delete_confirm_input(key_code),
+            AppMode::DiscardConfirm => self.handle_discard_confirm_input(key_code),
+            AppMode::LineNavigation => self.handle_line_navigation_input(key_code),
+            AppMode::QuitConfirm => self.handle_quit_confirm_input(key_code),
+            AppMode::Tags => self.handle_tags_input(key_code),
+            AppMode::Backlinks => self.handle_backlinks_input(key_code),
+            AppMode::BrokenLinks => self.handle_broken_links_input(key_code),
+            AppMode::Conflicts => self.handle_conflicts_input(key_code),
+            AppMode::Staging => self.handle_staging_input(key_code),
+            AppMode::TemplatePicker => self.handle_template_picker_input(key_code),
+            AppMode::IdentityPrompt => self.handle_identity_prompt_input(key_code),
+            AppMode::SplitPreview => self.handle_split_preview_input(key_code),
+            AppMode::PassphrasePrompt => self.handle_passphrase_input(key_code),
+            AppMode::VaultPicker => self.handle_vault_picker_input(key_code),
+            AppMode::Editor => self.handle_editor_input(key),
+            AppMode::Search => self.handle_search_input(key_code),
+        }
+    }
+
+    fn handle_help_input(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q') => self.show_help = false,
+            KeyCode::Char('j') | KeyCode::Down => self.help_scroll = self.help_scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => self.help_scroll = self.help_scroll.saturating_sub(1),
+            KeyCode::PageDown => self.help_scroll = self.help_scroll.saturating_add(10),
+            KeyCode::PageUp => self.help_scroll = self.help_scroll.saturating_sub(10),
+            _ => {}
+        }
+    }
+
+    /// Keys that would modify the vault or push it, in the current mode
+    fn is_mutating_key(&self, key_code: KeyCode) -> bool {
+        match self.mode {
+            AppMode::Normal => matches!(
+                key_code,
+                KeyCode::Char('i' | 'n' | 'P' | 'r' | 'x' | 'd' | 'C' | 'T' | 'I' | 'g' | 'e' | 'E' | 'X' | 'K' | 'u' | 'M' | 'S' | '[' | ']')
+            ),
+            AppMode::LineNavigation | AppMode::SplitPreview => key_code == KeyCode::Char('i'),
+            _ => false,
+        }
+    }
+
     fn handle_normal_input(&mut self, key_code: KeyCode) -> Result<()> {
         match key_code {
-            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Char(c) if c == self.config.command_palette_key() => self.open_command_palette(),
+            KeyCode::Char('q') => self.request_quit(),
             KeyCode::Char('j') | KeyCode::Down => {
                 self.file_tree.next();
                 self.load_current_file_content()?;
@@ -154,6 +692,16 @@ impl App {
                 self.file_tree.previous();
                 self.load_current_file_content()?;
             }
+            KeyCode::PageDown => {
+                self.file_tree.page_down(self.tree_height as usize);
+                self.load_current_file_content()?;
+            }
+            KeyCode::PageUp => {
+                self.file_tree.page_up(self.tree_height as usize);
+                self.load_current_file_content()?;
+            }
+            KeyCode::Tab => self.open_recent_note(1)?,
+            KeyCode::BackTab => self.open_recent_note(-1)?,
             KeyCode::Char(' ') | KeyCode::Right => {
                 // Smart right arrow: expand folder or enter line navigation
                 if let Some(selected_path) = self.file_tree.get_selected_path() {
@@ -172,14 +720,16 @@ impl App {
             }
             KeyCode::Char('i') => self.edit_current_file()?,
             KeyCode::Char('n') => self.create_new_file()?,
+            KeyCode::Char('P') => self.create_note_from_clipboard()?,
             KeyCode::Char('r') => self.start_rename()?,
             KeyCode::Char('x') => self.start_delete()?,
+            KeyCode::Char('u') => self.start_discard(),
+            KeyCode::Char('M') => self.open_conflicts(),
+            KeyCode::Char('S') => self.open_staging(),
             KeyCode::Char('d') => self.create_new_folder()?,
-            KeyCode::Char('c') => {
-                self.mode = AppMode::Config;
-                self.config_input = self.config.root_directory.to_string_lossy().to_string();
-                self.config_field = 0;
-            }
+            KeyCode::Char('C') => self.duplicate_selected_file()?,
+            KeyCode::Char('T') => self.open_journal()?,
+            KeyCode::Char('c') => self.open_config()?,
             KeyCode::Char('g') => {
                 // Git push (commit and push changes)
                 self.perform_git_push()?;
@@ -188,162 +738,1141 @@ impl App {
                 // Git pull changes
                 self.perform_git_pull()?;
             }
-            KeyCode::Char('y') => {
-                // Copy image to clipboard if current selection is an image
-                self.copy_image_to_clipboard()?;
+            KeyCode::Char('y') => self.copy_selected_path(false),
+            KeyCode::Char('Y') => self.copy_selected_path(true),
+            KeyCode::Char('o') => self.open_in_file_manager(false),
+            KeyCode::Char('O') => self.open_in_file_manager(true),
+            KeyCode::Char('V') => self.open_vault_picker(),
+            KeyCode::Char('e') => self.export_current_note_html(),
+            KeyCode::Char('E') => self.export_current_note_pdf(),
+            KeyCode::Char('X') => self.start_vault_export(),
+            KeyCode::Char('t') => self.open_tags(),
+            KeyCode::Char('b') => self.open_backlinks(),
+            KeyCode::Char('B') => self.open_broken_links(),
+            KeyCode::Char('!') => {
+                if self.git_error.is_some() {
+                    self.show_git_error = true;
+                } else {
+                    self.status_message = Some("ℹ No git operation has failed this session".to_string());
+                }
+            }
+            KeyCode::Char('v') => self.enter_split_preview_mode(),
+            KeyCode::Char('I') => self.edit_gitignore()?,
+            KeyCode::Char('K') => self.start_encrypt_current_note(),
+            KeyCode::Char('R') => self.file_tree.toggle_relative_paths(),
+            KeyCode::Char('#') => self.toggle_note_counts()?,
+            KeyCode::Char('L') => self.toggle_line_numbers(),
+            KeyCode::Char('[') => self.move_in_order(true)?,
+            KeyCode::Char(']') => self.move_in_order(false)?,
+            KeyCode::Char('/') => self.open_search(),
+            KeyCode::Char('<') => self.resize_tree(-5),
+            KeyCode::Char('>') => self.resize_tree(5),
+            KeyCode::Esc if self.file_tree.is_filtered() => {
+                // Leave a tag filter and return to the full tree
+                self.active_tag = None;
+                self.file_tree.set_filter(None)?;
+                self.load_current_file_content()?;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_config_input(&mut self, key_code: KeyCode) -> Result<()> {
+    /// Show or hide note counts after folder names and remember the choice
+    fn toggle_note_counts(&mut self) -> Result<()> {
+        self.config.show_note_counts = !self.file_tree.shows_note_counts();
+        self.file_tree.set_show_note_counts(self.config.show_note_counts)?;
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("✗ Saving config failed: {}", e));
+        }
+        Ok(())
+    }
+
+    /// Show or hide source line numbers beside the rendered note and remember the choice
+    fn toggle_line_numbers(&mut self) {
+        self.config.show_line_numbers = !self.config.show_line_numbers;
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("✗ Saving config failed: {}", e));
+        }
+    }
+
+    /// Widen or narrow the file tree and remember the new width
+    fn resize_tree(&mut self, delta: i16) {
+        let (min, max) = config::TREE_WIDTH_RANGE;
+        let width = (self.config.tree_width() as i16 + delta).clamp(min as i16, max as i16) as u16;
+        if width == self.config.tree_width_percent {
+            return;
+        }
+        self.config.tree_width_percent = width;
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("✗ Saving tree width failed: {}", e));
+        }
+    }
+
+    fn request_quit(&mut self) {
+        // Ask for confirmation if there are uncommitted changes that would not be synced
+        if self.git_manager.is_enabled() && !self.read_only {
+            if let Ok(status) = self.git_manager.get_status() {
+                if status.has_changes() {
+                    self.mode = AppMode::QuitConfirm;
+                    return;
+                }
+            }
+        }
+        self.should_quit = true;
+    }
+
+    fn handle_quit_confirm_input(&mut self, key_code: KeyCode) -> Result<()> {
         match key_code {
-            KeyCode::Esc => {
+            KeyCode::Char('c') | KeyCode::Char('C') => {
+                // Commit and push in the background, quitting once it succeeds
+                if self.git_task.is_none() {
+                    self.quit_after_git_task = true;
+                }
+                self.perform_git_push()?;
                 self.mode = AppMode::Normal;
-                self.config_input.clear();
             }
-            KeyCode::Tab => {
-                self.save_current_config_field();
-                self.config_field = (self.config_field + 1) % 6; // Now 6 fields total
-                self.load_current_config_field();
+            KeyCode::Char('q') | KeyCode::Char('Q') => {
+                self.should_quit = true;
             }
-            KeyCode::Enter => {
-                // Save current field and exit config mode
-                self.save_current_config_field();
-                
-                self.config.save()?;
-                
-                // Update git manager with new config
-                self.git_manager = GitManager::new(self.config.clone());
-                
-                // Initialize Git repository if enabled
-                if self.config.git_enabled {
-                    if let Err(e) = self.git_manager.init_repository() {
-                        eprintln!("Warning: Failed to initialize Git repository: {}", e);
-                    }
-                }
-                
-                self.file_tree = FileTree::new(&self.config.root_directory)?;
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
                 self.mode = AppMode::Normal;
-                self.config_input.clear();
             }
-            KeyCode::Char(c) => {
-                if self.config_field == 2 { // Git enabled field
-                    // For boolean field, toggle on any character input
-                    self.config.git_enabled = !self.config.git_enabled;
-                    self.config_input = self.config.git_enabled.to_string();
-                } else {
-                    self.config_input.push(c);
-                }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_tags(&mut self) {
+        let tag_index = self
+            .tag_index
+            .get_or_insert_with(|| TagIndex::build(&self.config));
+        self.tag_entries = tag_index.tags_with_counts();
+        self.tag_selection = 0;
+        self.mode = AppMode::Tags;
+    }
+
+    fn handle_tags_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.mode = AppMode::Normal;
             }
-            KeyCode::Backspace => {
-                if self.config_field != 2 { // Don't allow backspace on boolean field
-                    self.config_input.pop();
+            KeyCode::Char('j') | KeyCode::Down if self.tag_selection + 1 < self.tag_entries.len() => {
+                self.tag_selection += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.tag_selection = self.tag_selection.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some((tag, _)) = self.tag_entries.get(self.tag_selection) {
+                    let tag = tag.clone();
+                    let files = self
+                        .tag_index
+                        .as_ref()
+                        .map(|index| index.files_for(&tag))
+                        .unwrap_or_default();
+                    self.file_tree.set_filter(Some(files))?;
+                    self.active_tag = Some(tag);
+                    self.load_current_file_content()?;
                 }
+                self.mode = AppMode::Normal;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_rename_input(&mut self, key_code: KeyCode) -> Result<()> {
+    fn open_backlinks(&mut self) {
+        if let Some(current_file) = &self.current_file {
+            let link_index = self
+                .link_index
+                .get_or_insert_with(|| LinkIndex::build(&self.config));
+            self.backlink_entries = link_index.backlinks(current_file);
+            self.backlink_selection = 0;
+            self.mode = AppMode::Backlinks;
+        }
+    }
+
+    fn handle_backlinks_input(&mut self, key_code: KeyCode) -> Result<()> {
         match key_code {
-            KeyCode::Esc => {
+            KeyCode::Esc | KeyCode::Char('q') => {
                 self.mode = AppMode::Normal;
-                self.rename_input.clear();
             }
-            KeyCode::Enter => {
-                self.perform_rename()?;
-                self.mode = AppMode::Normal;
-                self.rename_input.clear();
+            KeyCode::Char('j') | KeyCode::Down if self.backlink_selection + 1 < self.backlink_entries.len() => {
+                self.backlink_selection += 1;
             }
-            KeyCode::Char(c) => {
-                self.rename_input.push(c);
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.backlink_selection = self.backlink_selection.saturating_sub(1);
             }
-            KeyCode::Backspace => {
-                self.rename_input.pop();
+            KeyCode::Enter => {
+                if let Some(path) = self.backlink_entries.get(self.backlink_selection).cloned() {
+                    self.open_note(&path)?;
+                }
+                self.mode = AppMode::Normal;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn start_rename(&mut self) -> Result<()> {
-        if let Some(path) = self.file_tree.get_selected_path() {
-            self.mode = AppMode::Rename;
-            if path.is_dir() {
-                // For directories, use the full name
-                self.rename_input = path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-            } else {
-                // For files, use the stem (without extension)
-                self.rename_input = path
-                    .file_stem()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
+    fn open_broken_links(&mut self) {
+        let link_index = self
+            .link_index
+            .get_or_insert_with(|| LinkIndex::build(&self.config));
+        self.broken_link_entries = link_index.broken_links();
+        self.broken_link_selection = 0;
+        self.mode = AppMode::BrokenLinks;
+    }
+
+    fn handle_broken_links_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.broken_link_selection + 1 < self.broken_link_entries.len() => {
+                self.broken_link_selection += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.broken_link_selection = self.broken_link_selection.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some(link) = self.broken_link_entries.get(self.broken_link_selection).cloned() {
+                    self.mode = AppMode::Normal;
+                    self.open_note(&link.source)?;
+                    if self.current_file.as_ref() == Some(&link.source) {
+                        self.select_source_line(link.line);
+                        self.enter_line_navigation_mode()?;
+                    }
+                } else {
+                    self.mode = AppMode::Normal;
+                }
             }
+            _ => {}
         }
         Ok(())
     }
 
-    fn perform_rename(&mut self) -> Result<()> {
-        if let Some(current_path) = self.file_tree.get_selected_path() {
-            let current_path = current_path.clone(); // Clone to avoid borrow issues
-            if !self.rename_input.is_empty() {
-                // Save current tree state
-                let expanded_dirs = self.file_tree.get_expansion_state();
-                
-                let parent = current_path.parent().unwrap_or(&self.config.root_directory);
-                
-                let new_filename = if current_path.is_dir() {
-                    // For directories, use the name as-is
-                    self.rename_input.clone()
-                } else {
-                    // For files, preserve the extension
-                    let extension = current_path.extension().unwrap_or_default();
-                    if extension.is_empty() {
-                        self.rename_input.clone()
-                    } else {
-                        format!("{}.{}", self.rename_input, extension.to_string_lossy())
+    /// Start reading every note into a fresh search index in the background
+    fn start_search_indexing(&mut self) {
+        self.search_index = SearchIndex::new();
+        self.search_index_stale = false;
+        self.search_index_build = Some(IndexBuild {
+            receiver: search::index_in_background(self.config.clone(), HashMap::new()),
+            indexed: 0,
+            total: None,
+        });
+    }
+
+    /// Add the notes the background indexer has read since the last call
+    fn poll_search_index(&mut self) {
+        let build = match &mut self.search_index_build {
+            Some(build) => build,
+            None => return,
+        };
+        let mut received = false;
+        let finished = loop {
+            match build.receiver.try_recv() {
+                Ok(IndexUpdate::Total(total)) => build.total = Some(total),
+                Ok(IndexUpdate::Notes(notes)) => {
+                    build.indexed += notes.len();
+                    for (path, content, modified) in notes {
+                        self.search_index.insert(path, &content, modified);
                     }
-                };
-                
-                let new_path = parent.join(&new_filename);
-                
-                if !new_path.exists() {
-                    fs::rename(&current_path, &new_path)?;
-                    
-                    // Update current_file if it was the renamed item
-                    if Some(&current_path) == self.current_file.as_ref() {
-                        if new_path.is_file() {
-                            self.current_file = Some(new_path.clone());
-                            self.load_current_file_content()?;
-                        } else {
-                            self.current_file = None;
-                            self.current_content.clear();
-                        }
+                    received = true;
+                }
+                Ok(IndexUpdate::Removed(paths)) => {
+                    for path in paths {
+                        self.search_index.remove(&path);
                     }
-                    
-                    // Refresh file tree while preserving state and selecting the renamed item
-                    self.file_tree.refresh_with_state(expanded_dirs, Some(new_path))?;
+                    received = true;
+                }
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        if finished {
+            self.search_index_build = None;
+        }
+        // Results fill in as the index grows
+        if received && self.mode == AppMode::Search {
+            self.run_search();
+        }
+    }
+
+    fn open_search(&mut self) {
+        self.search_input.clear();
+        self.search_results.clear();
+        self.search_selection = 0;
+        self.mode = AppMode::Search;
+    }
+
+    /// Search the index for the typed words, catching up with changed notes in the background
+    fn run_search(&mut self) {
+        if self.search_index_stale && self.search_index_build.is_none() {
+            self.search_index_stale = false;
+            self.search_index_build = Some(IndexBuild {
+                receiver: search::index_in_background(self.config.clone(), self.search_index.modification_times()),
+                indexed: 0,
+                total: None,
+            });
+        }
+        self.search_results = self.search_index.search(self.search_input.as_str(), SEARCH_RESULTS_LIMIT);
+        self.search_selection = self.search_selection.min(self.search_results.len().saturating_sub(1));
+    }
+
+    fn handle_search_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Down if self.search_selection + 1 < self.search_results.len() => {
+                self.search_selection += 1;
+            }
+            KeyCode::Up => {
+                self.search_selection = self.search_selection.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some(hit) = self.search_results.get(self.search_selection).cloned() {
+                    self.mode = AppMode::Normal;
+                    self.open_note(&hit.path)?;
+                    if self.current_file.as_ref() == Some(&hit.path) {
+                        self.select_source_line(hit.line);
+                        self.enter_line_navigation_mode()?;
+                    }
+                }
+            }
+            _ => {
+                let typed = self.search_input.as_str().to_string();
+                edit_line_input(&mut self.search_input, key_code);
+                if self.search_input.as_str() != typed {
+                    self.run_search();
                 }
             }
         }
         Ok(())
     }
 
-    fn load_current_file_content(&mut self) -> Result<()> {
-        if let Some(file_path) = self.file_tree.get_selected_file() {
-            self.current_file = Some(file_path.clone());
-            
-            // Check if it's an image file
-            if FileTree::is_image_file(&file_path) {
-                // Load image
+    /// Select the first rendered line showing source line `line` or one after it
+    fn select_source_line(&mut self, line: usize) {
+        // A large note may not be rendered that far yet
+        self.render_lazy_chunks(usize::MAX);
+        let position = if self.rendered_source_lines.is_empty() {
+            Some(line)
+        } else {
+            self.rendered_source_lines.iter().position(|source| source.is_some_and(|source| source >= line))
+        };
+        if let Some(position) = position {
+            self.line_selection = position.min(self.rendered_lines.len().saturating_sub(1));
+        }
+    }
+
+    fn open_conflicts(&mut self) {
+        match self.git_manager.conflicted_files() {
+            Ok(files) if files.is_empty() => {
+                self.status_message = Some("✓ No merge conflicts".to_string());
+            }
+            Ok(files) => {
+                self.conflict_entries = files;
+                self.conflict_selection = 0;
+                self.mode = AppMode::Conflicts;
+            }
+            Err(e) => self.status_message = Some(format!("✗ Listing conflicts failed: {}", e)),
+        }
+    }
+
+    fn handle_conflicts_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.conflict_selection + 1 < self.conflict_entries.len() => {
+                self.conflict_selection += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.conflict_selection = self.conflict_selection.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some(path) = self.conflict_entries.get(self.conflict_selection).cloned() {
+                    self.resolve_conflict(&path)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_staging(&mut self) {
+        if !self.git_manager.is_enabled() {
+            return;
+        }
+        match self.git_manager.file_statuses() {
+            Ok(files) if files.is_empty() => {
+                self.status_message = Some("✓ No changes to stage".to_string());
+            }
+            Ok(_) => {
+                self.refresh_staging_entries();
+                self.staging_selection = 0;
+                self.mode = AppMode::Staging;
+            }
+            Err(e) => self.status_message = Some(format!("✗ Listing changes failed: {}", e)),
+        }
+    }
+
+    fn refresh_staging_entries(&mut self) {
+        let mut entries: Vec<(PathBuf, FileStatus)> = self.git_manager
+            .file_statuses()
+            .unwrap_or_default()
+            .iter()
+            .map(|(path, status)| (path.clone(), *status))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.staging_entries = entries;
+        self.staging_selection = self.staging_selection.min(self.staging_entries.len().saturating_sub(1));
+    }
+
+    fn handle_staging_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.staging_selection + 1 < self.staging_entries.len() => {
+                self.staging_selection += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.staging_selection = self.staging_selection.saturating_sub(1);
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some((path, status)) = self.staging_entries.get(self.staging_selection).cloned() {
+                    // A file edited again after staging is staged again first
+                    let result = if status.staged && !status.modified {
+                        self.git_manager.unstage(&path)
+                    } else {
+                        self.git_manager.stage(&path)
+                    };
+                    if let Err(e) = result {
+                        self.status_message = Some(format!("✗ {}", e));
+                    }
+                    self.refresh_staging_entries();
+                }
+            }
+            KeyCode::Char('c') => {
+                if self.staging_entries.iter().any(|(_, status)| status.staged) {
+                    self.mode = AppMode::Normal;
+                    self.start_git_task(GitOperation::CommitStaged);
+                } else {
+                    self.status_message = Some("ℹ Nothing staged yet".to_string());
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Edit a conflicted file and stage it once no markers are left
+    fn resolve_conflict(&mut self, path: &Path) -> Result<()> {
+        if !self.run_editor(path)? {
+            return Ok(());
+        }
+        self.invalidate_vault_index();
+
+        let resolved = fs::read_to_string(path)
+            .map(|text| !git::has_conflict_markers(&text))
+            // A file deleted while resolving is resolved too
+            .unwrap_or(true);
+        if resolved {
+            match self.git_manager.mark_resolved(path) {
+                Ok(()) => self.status_message = Some("✓ Conflict resolved".to_string()),
+                Err(e) => self.status_message = Some(format!("✗ {}", e)),
+            }
+        } else {
+            self.status_message = Some("⚠ The file still contains conflict markers".to_string());
+        }
+
+        self.conflict_entries = self.git_manager.conflicted_files().unwrap_or_default();
+        self.conflict_selection = self.conflict_selection.min(self.conflict_entries.len().saturating_sub(1));
+        if self.conflict_entries.is_empty() {
+            self.status_message = Some("✓ All conflicts resolved. Press g to commit the merge".to_string());
+            self.mode = AppMode::Normal;
+        }
+
+        let expanded_dirs = self.file_tree.get_expansion_state();
+        let selected_path = self.file_tree.get_selected_path().cloned();
+        self.file_tree.refresh_with_state(expanded_dirs, selected_path)?;
+        self.load_current_file_content()
+    }
+
+    /// Reveal a note in the tree and show it in the content pane
+    fn open_note(&mut self, path: &Path) -> Result<()> {
+        if self.file_tree.is_filtered() {
+            self.active_tag = None;
+            self.file_tree.set_filter(None)?;
+        }
+        self.file_tree.reveal_path(path)?;
+        self.load_current_file_content()?;
+        self.remember_recent_note();
+        Ok(())
+    }
+
+    /// Put the open note at the top of the recently opened notes
+    fn remember_recent_note(&mut self) {
+        let path = match &self.current_file {
+            Some(path) if !FileTree::is_image_file(path) => path.clone(),
+            _ => return,
+        };
+        self.recent_notes.retain(|recent| *recent != path);
+        self.recent_notes.push_front(path);
+        self.recent_notes.truncate(RECENT_NOTES_LIMIT);
+        self.recent_position = 0;
+    }
+
+    /// Step `steps` notes back (positive) or forward (negative) through the recently opened notes
+    fn open_recent_note(&mut self, steps: isize) -> Result<()> {
+        // A note reached through the tree becomes the one to come back to
+        if self.current_file.is_some() && self.current_file.as_ref() != self.recent_notes.get(self.recent_position) {
+            self.remember_recent_note();
+        }
+
+        let mut position = self.recent_position;
+        loop {
+            position = match position.checked_add_signed(steps) {
+                Some(position) if position < self.recent_notes.len() => position,
+                _ => {
+                    self.status_message = Some(if steps > 0 {
+                        "ℹ No earlier note".to_string()
+                    } else {
+                        "ℹ No later note".to_string()
+                    });
+                    return Ok(());
+                }
+            };
+            if self.recent_notes[position].is_file() {
+                break;
+            }
+            // Skip notes deleted or moved outside RNotes
+            self.recent_notes.remove(position);
+            if steps > 0 {
+                position -= 1;
+            } else if position < self.recent_position {
+                self.recent_position -= 1;
+            }
+        }
+
+        let path = self.recent_notes[position].clone();
+        if self.file_tree.is_filtered() {
+            self.active_tag = None;
+            self.file_tree.set_filter(None)?;
+        }
+        self.file_tree.reveal_path(&path)?;
+        self.load_current_file_content()?;
+        self.recent_position = position;
+        if self.current_file.as_ref() != Some(&path) {
+            self.status_message = Some(format!("⚠ {} is hidden from the file tree", path.display()));
+        }
+        Ok(())
+    }
+
+    /// Keep the recently opened notes in the config under the current notes directory
+    fn store_recent_notes(&mut self) {
+        if !self.config.remember_recent_notes {
+            return;
+        }
+        let root = self.config.root_directory.clone();
+        let notes = self
+            .recent_notes
+            .iter()
+            .filter_map(|path| path.strip_prefix(&root).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        self.config.set_recent_notes(notes);
+    }
+
+    /// Forget vault-wide caches after files were created, edited or removed
+    fn invalidate_vault_index(&mut self) {
+        self.tag_index = None;
+        self.link_index = None;
+        self.search_index_stale = true;
+        self.decrypted_notes.clear();
+        self.inline_image_states.clear();
+        self.git_manager.invalidate_status_cache();
+    }
+
+    /// Whether the open note is shown as markdown, including unlocked encrypted notes
+    fn current_is_markdown(&self) -> bool {
+        match &self.current_file {
+            Some(path) if crypto::is_encrypted_note(path) => self.decrypted_notes.contains_key(path),
+            Some(path) => file_tree::is_markdown_file(path),
+            None => false,
+        }
+    }
+
+    /// Whether the open note is an encrypted one, whose text must not reach the disk
+    fn current_is_encrypted(&self) -> bool {
+        self.current_file.as_deref().is_some_and(crypto::is_encrypted_note)
+    }
+
+    fn handle_passphrase_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc => {
+                self.passphrase_input.clear();
+                self.passphrase_action = None;
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Enter if !self.passphrase_input.is_empty() => {
+                self.encryption_passphrase = Some(std::mem::take(&mut self.passphrase_input).into_string());
+                self.mode = AppMode::Normal;
+                match self.passphrase_action.take() {
+                    Some(PassphraseAction::Encrypt(path)) => self.encrypt_note(&path)?,
+                    _ => self.load_current_file_content()?,
+                }
+            }
+            _ => edit_line_input(&mut self.passphrase_input, key_code),
+        }
+        Ok(())
+    }
+
+    fn prompt_for_passphrase(&mut self, action: PassphraseAction) {
+        self.passphrase_input.clear();
+        self.passphrase_action = Some(action);
+        self.mode = AppMode::PassphrasePrompt;
+    }
+
+    /// Replace the selected plain note with an encrypted copy
+    fn start_encrypt_current_note(&mut self) {
+        if !self.config.encryption_enabled {
+            self.status_message = Some("⚠ Set encryption_enabled in the config to use encrypted notes".to_string());
+            return;
+        }
+        let path = match &self.current_file {
+            Some(path) if file_tree::is_markdown_file(path) => path.clone(),
+            _ => {
+                self.status_message = Some("⚠ Select a plain markdown note to encrypt".to_string());
+                return;
+            }
+        };
+        if self.encryption_passphrase.is_some() {
+            if let Err(e) = self.encrypt_note(&path) {
+                self.status_message = Some(format!("✗ Encryption failed: {}", e));
+            }
+        } else {
+            self.prompt_for_passphrase(PassphraseAction::Encrypt(path));
+        }
+    }
+
+    fn encrypt_note(&mut self, path: &Path) -> Result<()> {
+        let passphrase = match &self.encryption_passphrase {
+            Some(passphrase) => passphrase.clone(),
+            None => return Ok(()),
+        };
+        let target = crypto::encrypted_path(path);
+        if target.exists() {
+            self.status_message = Some(format!("⚠ {} already exists", target.display()));
+            return Ok(());
+        }
+
+        let plaintext = fs::read_to_string(path)?;
+        if let Err(e) = crypto::encrypt_note(&target, &plaintext, &passphrase) {
+            self.status_message = Some(format!("✗ Encryption failed: {}", e));
+            return Ok(());
+        }
+        fs::remove_file(path)?;
+        self.invalidate_vault_index();
+        self.decrypted_notes.insert(target.clone(), plaintext);
+        self.open_note(&target)?;
+        self.status_message = Some(format!("✓ Encrypted to {}", target.file_name().unwrap_or_default().to_string_lossy()));
+        Ok(())
+    }
+
+    /// Make sure commits get a real author when git is enabled
+    fn check_git_identity(&mut self) {
+        if !self.git_manager.is_enabled() || self.read_only {
+            return;
+        }
+
+        if let Some(email) = &self.config.git_email {
+            if !git::is_valid_email(email) {
+                self.status_message = Some(format!("⚠ Git email '{}' doesn't look like an email address", email));
+                return;
+            }
+        }
+
+        if self.config.git_username.is_some() && self.config.git_email.is_some() {
+            return;
+        }
+
+        // Offer the global git identity before falling back to a placeholder author
+        self.global_identity = git::global_identity();
+        let fills_name = self.config.git_username.is_some() || self.global_identity.0.is_some();
+        let fills_email = self.config.git_email.is_some() || self.global_identity.1.is_some();
+        if fills_name && fills_email {
+            self.mode = AppMode::IdentityPrompt;
+        } else {
+            self.status_message = Some(format!(
+                "⚠ Git username/email not set; commits will use '{} <{}>'. Set them in config (c)",
+                git::PLACEHOLDER_AUTHOR.0,
+                git::PLACEHOLDER_AUTHOR.1
+            ));
+        }
+    }
+
+    fn handle_identity_prompt_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let (global_name, global_email) = self.global_identity.clone();
+                if self.config.git_username.is_none() {
+                    self.config.git_username = global_name;
+                }
+                if self.config.git_email.is_none() {
+                    self.config.git_email = global_email;
+                }
+                self.config.save()?;
+                self.git_manager = GitManager::new(self.config.git_settings());
+                self.mode = AppMode::Normal;
+                self.status_message = Some("✓ Git author saved to config".to_string());
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_config(&mut self) -> Result<()> {
+        self.config_draft = serde_json::to_value(&self.config)?;
+        self.config_fields = settings::fields(&self.config_draft);
+        self.config_field = 0;
+        self.load_current_config_field();
+        self.mode = AppMode::Config;
+        Ok(())
+    }
+
+    fn handle_config_input(&mut self, key_code: KeyCode) -> Result<()> {
+        let kind = match self.config_fields.get(self.config_field) {
+            Some(field) => field.kind.clone(),
+            None => FieldKind::Text,
+        };
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+                self.config_input.clear();
+            }
+            // Moving on keeps the selected field until its text is valid
+            KeyCode::Tab | KeyCode::Down | KeyCode::BackTab | KeyCode::Up => {
+                if !self.save_current_config_field() || self.config_fields.is_empty() {
+                    return Ok(());
+                }
+                let count = self.config_fields.len();
+                self.config_field = if matches!(key_code, KeyCode::Tab | KeyCode::Down) {
+                    (self.config_field + 1) % count
+                } else {
+                    self.config_field.checked_sub(1).unwrap_or(count - 1)
+                };
+                self.load_current_config_field();
+            }
+            KeyCode::Enter => {
+                // Save current field and exit config mode
+                if !self.save_current_config_field() {
+                    return Ok(());
+                }
+                let previous_root = self.config.root_directory.clone();
+                // Saved with the rest, under the notes directory they were opened in
+                self.store_recent_notes();
+                let mut config = match self.config.with_settings(self.config_draft.clone()) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        self.status_message = Some(format!("✗ Invalid configuration: {}", e));
+                        return Ok(());
+                    }
+                };
+                if config.root_directory != previous_root {
+                    config.root_directory = config::expand_home(&config.root_directory.to_string_lossy());
+                    if !config.root_directory.is_absolute() {
+                        self.status_message = Some(format!(
+                            "✗ The notes directory must be an absolute path (~ is your home folder): {}",
+                            config.root_directory.display()
+                        ));
+                        return Ok(());
+                    }
+                    let created = fs::create_dir_all(&config.root_directory)
+                        .and_then(|_| config.root_directory.canonicalize());
+                    match created {
+                        Ok(root) => {
+                            config.root_directory = root;
+                            config.suspend_git(false);
+                        }
+                        Err(e) => {
+                            self.status_message = Some(format!(
+                                "✗ Cannot create the notes directory {}: {}",
+                                config.root_directory.display(),
+                                e
+                            ));
+                            return Ok(());
+                        }
+                    }
+                }
+                self.config = config;
+                self.read_only = self.cli_read_only || self.config.read_only;
+                
+                self.config.save()?;
+                
+                // Update git manager with new config
+                self.git_manager = GitManager::new(self.config.git_settings());
+                
+                // Initialize Git repository if enabled, but never in read-only mode
+                if self.git_manager.is_enabled() && self.read_only && !self.git_manager.is_git_repository() {
+                    self.status_message = Some("⚠ Read-only mode: not initializing a Git repository".to_string());
+                } else if self.git_manager.is_enabled() {
+                    if let Err(e) = self.git_manager.init_repository() {
+                        self.report_git_error("Failed to initialize Git repository", &e, false);
+                    }
+                }
+                
+                // Another vault starts from a fresh tree, the same one keeps its expanded folders
+                if self.config.root_directory == previous_root {
+                    self.file_tree.apply_config(&self.config)?;
+                } else {
+                    self.file_tree = FileTree::new(&self.config)?;
+                }
+                if !self.file_tree.invalid_ignore_patterns().is_empty() {
+                    self.status_message = Some(invalid_patterns_message(self.file_tree.invalid_ignore_patterns()));
+                }
+                self.markdown_renderer.set_tab_width(self.config.tab_width);
+                self.markdown_renderer.set_flavor(self.config.markdown_flavor);
+                // Ignore patterns and symlinks decide which notes the indexes see
+                self.invalidate_vault_index();
+                if self.config.root_directory != previous_root {
+                    self.start_search_indexing();
+                    self.recent_notes = remembered_recent_notes(&self.config);
+                    self.recent_position = 0;
+                }
+                self.mode = AppMode::Normal;
+                self.config_input.clear();
+                self.load_current_file_content()?;
+                self.check_git_identity();
+            }
+            // Leaving read-only mode is itself a change, so it is left to the config file
+            KeyCode::Char(_) if self.read_only && self.config_fields.get(self.config_field).is_some_and(|field| field.key == "read_only") => {
+                self.status_message = Some("⚠ Read-only mode: turn it off in the config file or start without --read-only".to_string());
+            }
+            KeyCode::Char(_) if kind == FieldKind::Bool => {
+                // For boolean fields, toggle on any character input
+                let key = self.config_fields[self.config_field].key.clone();
+                let enabled = settings::get(&self.config_draft, &key).and_then(|value| value.as_bool()).unwrap_or(false);
+                settings::set(&mut self.config_draft, &key, serde_json::Value::Bool(!enabled));
+            }
+            KeyCode::Char(' ') | KeyCode::Right | KeyCode::Left if matches!(kind, FieldKind::Choice(_)) => {
+                if let FieldKind::Choice(choices) = kind {
+                    let key = self.config_fields[self.config_field].key.clone();
+                    let current = settings::get(&self.config_draft, &key).and_then(|value| value.as_str()).unwrap_or("");
+                    let index = choices.iter().position(|choice| *choice == current).unwrap_or(0);
+                    let index = if key_code == KeyCode::Left {
+                        index.checked_sub(1).unwrap_or(choices.len() - 1)
+                    } else {
+                        (index + 1) % choices.len()
+                    };
+                    settings::set(&mut self.config_draft, &key, serde_json::Value::String(choices[index].to_string()));
+                }
+            }
+            _ if kind == FieldKind::Text => {
+                edit_line_input(&mut self.config_input, key_code);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Insert pasted text into the input being typed in; inputs of one line get it joined into one
+    fn handle_paste(&mut self, text: &str) {
+        let text = normalize_line_endings(text);
+        let single_line = text.trim_end_matches('\n').replace('\n', " ");
+        match self.mode {
+            AppMode::Editor => {
+                if let Some(editor) = &mut self.text_editor {
+                    for c in text.chars() {
+                        if c == '\n' {
+                            editor.insert_newline();
+                        } else {
+                            editor.insert_char(c);
+                        }
+                    }
+                }
+            }
+            AppMode::Search => {
+                self.search_input.insert_str(&single_line);
+                self.run_search();
+            }
+            AppMode::Rename => self.rename_input.insert_str(&single_line),
+            AppMode::ExportVault => {
+                self.export_vault_input.insert_str(&single_line);
+                self.export_vault_overwrite = None;
+            }
+            AppMode::CommandPalette => {
+                self.command_input.insert_str(&single_line);
+                self.command_selection = 0;
+            }
+            AppMode::PassphrasePrompt => self.passphrase_input.insert_str(&single_line),
+            AppMode::Config if self.config_fields.get(self.config_field).is_some_and(|field| field.kind == FieldKind::Text) => {
+                self.config_input.insert_str(&single_line);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_rename_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+                self.rename_input.clear();
+            }
+            KeyCode::Enter => {
+                self.perform_rename()?;
+                self.mode = AppMode::Normal;
+                self.rename_input.clear();
+            }
+            _ => {
+                edit_line_input(&mut self.rename_input, key_code);
+            }
+        }
+        Ok(())
+    }
+
+    fn start_rename(&mut self) -> Result<()> {
+        if let Some(path) = self.file_tree.get_selected_path() {
+            self.mode = AppMode::Rename;
+            if path.is_dir() {
+                // For directories, use the full name
+                self.rename_input.set(path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string());
+            } else {
+                // For files, use the stem (without extension)
+                self.rename_input.set(path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn perform_rename(&mut self) -> Result<()> {
+        if let Some(current_path) = self.file_tree.get_selected_path() {
+            let current_path = current_path.clone(); // Clone to avoid borrow issues
+            if !self.rename_input.is_empty() {
+                // Save current tree state
+                let expanded_dirs = self.file_tree.get_expansion_state();
+                
+                let parent = current_path.parent().unwrap_or(&self.config.root_directory);
+                
+                let rename_input = self.rename_input.as_str();
+                let new_filename = if current_path.is_dir() {
+                    // For directories, use the name as-is
+                    rename_input.to_string()
+                } else if has_explicit_extension(rename_input) {
+                    // The user typed an extension, so use the name as-is, but keep an encrypted note encrypted
+                    let typed = Path::new(rename_input);
+                    if crypto::is_encrypted_note(&current_path) && file_tree::is_markdown_file(typed) {
+                        crypto::encrypted_path(typed).to_string_lossy().to_string()
+                    } else {
+                        rename_input.to_string()
+                    }
+                } else {
+                    // Otherwise preserve the original extension
+                    format!("{}{}", rename_input, split_extension(&current_path).1)
+                };
+                
+                let new_path = parent.join(&new_filename);
+
+                // A name like ../x must not move the item out of the vault; folders
+                // reached through a symlink may still be renamed in place
+                if !is_inside_root(&self.config.root_directory, &new_path) && !is_inside_root(parent, &new_path) {
+                    self.status_message = Some(format!("✗ Cannot rename to {}: it is outside the notes directory", new_filename));
+                    return Ok(());
+                }
+                
+                if !new_path.exists() {
+                    fs::rename(&current_path, &new_path)?;
+                    self.invalidate_vault_index();
+
+                    if new_path.is_file() && !FileTree::is_visible_file(&new_path) && !crypto::is_encrypted_note(&new_path) {
+                        self.status_message = Some(format!(
+                            "⚠ Renamed to {}; files with this extension are hidden from the tree",
+                            new_filename
+                        ));
+                    }
+                    
+                    let mut expanded_dirs = expanded_dirs;
+                    let moved_current_file = self.follow_rename(&current_path, &new_path, &mut expanded_dirs);
+                    match moved_current_file {
+                        Some(moved) if moved.is_file() => {
+                            // Keep the open note open and selected at its new location
+                            self.current_file = Some(moved.clone());
+                            self.file_tree.refresh_with_state(expanded_dirs, None)?;
+                            self.file_tree.reveal_path(&moved)?;
+                            self.load_current_file_content()?;
+                            return Ok(());
+                        }
+                        Some(_) => {
+                            self.current_file = None;
+                            self.current_content.clear();
+                        }
+                        None => {}
+                    }
+                    
+                    // Refresh file tree while preserving state and selecting the renamed item
+                    self.file_tree.refresh_with_state(expanded_dirs, Some(new_path))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move saved positions, recent notes and expanded folders inside `old_path` to `new_path`
+    ///
+    /// Returns the new path of the open note if it was the renamed item or lives inside a renamed folder.
+    fn follow_rename(&mut self, old_path: &Path, new_path: &Path, expanded_dirs: &mut [PathBuf]) -> Option<PathBuf> {
+        for dir in expanded_dirs.iter_mut() {
+            if let Some(moved) = moved_path(dir, old_path, new_path) {
+                *dir = moved;
+            }
+        }
+
+        // Saved positions follow the renamed notes
+        self.line_positions = std::mem::take(&mut self.line_positions)
+            .into_iter()
+            .map(|(path, line)| (moved_path(&path, old_path, new_path).unwrap_or(path), line))
+            .collect();
+        for path in self.recent_notes.iter_mut() {
+            if let Some(moved) = moved_path(path, old_path, new_path) {
+                *path = moved;
+            }
+        }
+
+        self.current_file
+            .as_ref()
+            .and_then(|file| moved_path(file, old_path, new_path))
+    }
+
+    /// Move the selected entry one place up or down among its siblings by renumbering their order prefixes
+    fn move_in_order(&mut self, up: bool) -> Result<()> {
+        if !self.config.order_prefixes {
+            self.status_message = Some("ℹ Turn on order_prefixes in the config to reorder notes with [ and ]".to_string());
+            return Ok(());
+        }
+        let selected = match self.file_tree.get_selected_path() {
+            Some(path) if !self.file_tree.is_filtered() => path.clone(),
+            _ => return Ok(()),
+        };
+        let parent = selected.parent().unwrap_or(&self.config.root_directory).to_path_buf();
+
+        // Folders are always listed before notes, so each kind is ordered on its own
+        let is_dir = selected.is_dir();
+        let mut siblings: Vec<PathBuf> = self.file_tree
+            .get_item_paths()
+            .into_iter()
+            .filter(|path| path.parent() == Some(parent.as_path()) && path.is_dir() == is_dir)
+            .map(|path| path.to_path_buf())
+            .collect();
+        let index = match siblings.iter().position(|path| *path == selected) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let neighbor = if up { index.checked_sub(1) } else { Some(index + 1).filter(|&i| i < siblings.len()) };
+        match neighbor {
+            Some(neighbor) => siblings.swap(index, neighbor),
+            None => return Ok(()),
+        }
+
+        // Number every sibling in its new place, e.g. 01-intro.md, 02-plan.md
+        let width = siblings.len().to_string().len().max(2);
+        let renames: Vec<(PathBuf, PathBuf)> = siblings
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let base = file_tree::order_prefix(&name).map_or(name.as_str(), |(_, rest)| rest);
+                (path.clone(), parent.join(format!("{:0width$}-{}", i + 1, base, width = width)))
+            })
+            .filter(|(old_path, new_path)| old_path != new_path)
+            .collect();
+        if let Some((_, taken)) = renames.iter().find(|(_, new_path)| new_path.exists() && !siblings.contains(new_path)) {
+            self.status_message = Some(format!(
+                "✗ Cannot reorder: {} already exists",
+                taken.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            return Ok(());
+        }
+
+        // Go through temporary names so no rename overwrites a sibling that has yet to move
+        let steps: Vec<(PathBuf, PathBuf)> = renames
+            .iter()
+            .enumerate()
+            .map(|(i, (old_path, _))| (old_path.clone(), parent.join(format!(".rnotes-reorder-{}", i))))
+            .chain(renames.iter().enumerate().map(|(i, (_, new_path))| (parent.join(format!(".rnotes-reorder-{}", i)), new_path.clone())))
+            .collect();
+        if let Err(e) = rename_all(&steps) {
+            self.status_message = Some(format!("✗ Reordering failed, names were restored: {}", e));
+            return Ok(());
+        }
+
+        let mut expanded_dirs = self.file_tree.get_expansion_state();
+        let mut moved_current_file = None;
+        for (old_path, new_path) in &renames {
+            moved_current_file = self.follow_rename(old_path, new_path, &mut expanded_dirs).or(moved_current_file);
+        }
+        if let Err(e) = links::update_links_after_renames(&self.config, &renames) {
+            self.status_message = Some(format!("⚠ Reordered, but updating links failed: {}", e));
+        }
+        self.invalidate_vault_index();
+
+        let selected = renames
+            .iter()
+            .find(|(old_path, _)| *old_path == selected)
+            .map_or(selected.clone(), |(_, new_path)| new_path.clone());
+        self.file_tree.refresh_with_state(expanded_dirs, Some(selected))?;
+        if let Some(moved) = moved_current_file {
+            self.current_file = Some(moved);
+        }
+        // Reload even if the note stayed put, its links may have been updated
+        self.load_current_file_content()?;
+        Ok(())
+    }
+
+    fn load_current_file_content(&mut self) -> Result<()> {
+        // Remember where we were in the note we are leaving (or reloading)
+        if let Some(previous) = &self.current_file {
+            self.line_positions.insert(previous.clone(), self.line_selection);
+        }
+        // Folds belong to the note they were made in
+        if self.current_file.as_ref() != self.file_tree.get_selected_file() {
+            self.folded_headings.clear();
+        }
+        self.current_word_count = 0;
+        self.rendered_source_lines.clear();
+        self.rendered_images.clear();
+        self.lazy_render = None;
+        self.blame = None;
+        self.current_file_info = None;
+        self.current_file_modified = None;
+        self.current_crlf = false;
+        self.current_lossy = false;
+        if let Some(file_path) = self.file_tree.get_selected_file() {
+            self.current_file = Some(file_path.clone());
+            self.current_file_modified = fs::metadata(file_path).and_then(|metadata| metadata.modified()).ok();
+            self.current_file_info = fs::metadata(file_path).ok().map(|metadata| {
+                let modified = metadata
+                    .modified()
+                    .map(|time| chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|_| "unknown".to_string());
+                format!("{}, modified {}", format_file_size(metadata.len()), modified)
+            });
+            
+            // Check if it's an image file
+            if FileTree::is_image_file(&file_path) {
+                // Load image
                 match image::open(&file_path) {
                     Ok(img) => {
                         self.current_image = Some(img);
@@ -369,95 +1898,666 @@ impl App {
                         self.line_selection = 0;
                     }
                 }
-            } else if file_path.extension().and_then(|s| s.to_str()) == Some("md") {
+            } else if crypto::is_encrypted_note(file_path) && self.config.encryption_enabled {
+                self.current_image = None;
+                self.image_state = None;
+
+                let decrypted = match (self.decrypted_notes.get(file_path.as_path()), &self.encryption_passphrase) {
+                    (Some(text), _) => Some(Ok(text.clone())),
+                    (None, Some(passphrase)) => Some(crypto::decrypt_note(file_path, passphrase)),
+                    (None, None) => None,
+                };
+                let message = match decrypted {
+                    Some(Ok(text)) => {
+                        self.decrypted_notes.insert(file_path.clone(), text.clone());
+                        self.current_word_count = text.split_whitespace().count();
+                        self.current_content = text;
+                        self.content_lines = self.current_content.lines().map(|s| s.to_string()).collect();
+                        self.rebuild_rendered_lines();
+                        self.restore_line_position();
+                        self.report_markdown_problem();
+                        return Ok(());
+                    }
+                    Some(Err(e)) => {
+                        // Ask again on the next attempt rather than reusing a bad passphrase
+                        self.encryption_passphrase = None;
+                        format!("🔒 Could not decrypt: {}", e)
+                    }
+                    None => {
+                        if self.mode == AppMode::Normal {
+                            self.prompt_for_passphrase(PassphraseAction::Decrypt);
+                        }
+                        "🔒 Encrypted note".to_string()
+                    }
+                };
+                self.current_content = message.clone();
+                self.content_lines = vec![message.clone()];
+                self.rendered_lines = vec![Line::from(message)];
+                self.line_selection = 0;
+            } else if file_tree::is_markdown_file(file_path) {
                 // Clear image data when loading non-image files
                 self.current_image = None;
                 self.image_state = None;
                 
-                match fs::read_to_string(&file_path) {
-                    Ok(content) => {
+                match fs::read(file_path) {
+                    Ok(bytes) => {
+                        let (content, lossy) = decode_note(bytes);
+                        if lossy {
+                            self.status_message = Some(format!(
+                                "⚠ {} is not valid UTF-8, undecodable bytes are shown as �",
+                                file_path.file_name().unwrap_or_default().to_string_lossy()
+                            ));
+                            self.current_lossy = true;
+                        }
+                        // Windows line endings would leave stray '\r' in rendered and copied text
+                        let content = if content.contains('\r') {
+                            self.current_crlf = content.contains("\r\n");
+                            if let Some(info) = &mut self.current_file_info {
+                                info.push_str(", CRLF");
+                            }
+                            normalize_line_endings(&content)
+                        } else {
+                            content
+                        };
+                        self.current_word_count = content.split_whitespace().count();
                         self.current_content = content.clone();
                         self.content_lines = content.lines().map(|s| s.to_string()).collect();
                         
                         // Generate formatted lines for line navigation
-                        match self.markdown_renderer.parse_markdown(&content) {
-                            Ok(elements) => {
-                                let rendered_text = self.markdown_renderer.render_to_text(&elements);
-                                self.rendered_lines = rendered_text.lines.into_iter().collect();
-                            }
-                            Err(_) => {
-                                // Fallback to plain text lines
-                                self.rendered_lines = self.content_lines.iter()
-                                    .map(|line| Line::from(line.clone()))
-                                    .collect();
-                            }
-                        }
-                        
-                        self.line_selection = 0;
+                        self.rebuild_rendered_lines();
+                        self.restore_line_position();
+                        self.report_markdown_problem();
                     },
-                    Err(_) => {
-                        self.current_content = "Error reading file".to_string();
-                        self.content_lines = vec!["Error reading file".to_string()];
-                        self.rendered_lines = vec![Line::from("Error reading file".to_string())];
+                    Err(e) => {
+                        let message = format!("Error reading file: {}", e);
+                        self.current_content = message.clone();
+                        self.content_lines = vec![message.clone()];
+                        self.rendered_lines = vec![Line::from(message)];
                         self.line_selection = 0;
                     }
                 }
-            } else {
-                // Clear image data for other file types
-                self.current_image = None;
-                self.image_state = None;
-                
-                self.current_content = "Not a markdown file".to_string();
-                self.content_lines = vec!["Not a markdown file".to_string()];
-                self.rendered_lines = vec![Line::from("Not a markdown file".to_string())];
-                self.line_selection = 0;
+            } else {
+                // Clear image data for other file types
+                self.current_image = None;
+                self.image_state = None;
+                
+                self.current_content = "Not a markdown file".to_string();
+                self.content_lines = vec!["Not a markdown file".to_string()];
+                self.rendered_lines = vec![Line::from("Not a markdown file".to_string())];
+                self.line_selection = 0;
+            }
+        } else {
+            // Clear all content when no file is selected
+            self.current_image = None;
+            self.image_state = None;
+            self.current_content.clear();
+            self.content_lines.clear();
+            self.rendered_lines.clear();
+            self.current_file = None;
+            self.line_selection = 0;
+        }
+        Ok(())
+    }
+
+    fn restore_line_position(&mut self) {
+        let saved = self.current_file
+            .as_ref()
+            .and_then(|path| self.line_positions.get(path))
+            .copied()
+            .unwrap_or(0);
+        self.render_lazy_chunks(saved + LAZY_BUFFER_LINES);
+        self.line_selection = saved.min(self.rendered_lines.len().saturating_sub(1));
+    }
+
+    fn rebuild_rendered_lines(&mut self) {
+        self.render_note();
+        self.apply_folds();
+    }
+
+    fn render_note(&mut self) {
+        self.lazy_render = None;
+        let key = RenderKey {
+            path: self.current_file.clone().unwrap_or_default(),
+            content_hash: {
+                let mut hasher = DefaultHasher::new();
+                self.current_content.hash(&mut hasher);
+                hasher.finish()
+            },
+            wrap_width: self.markdown_renderer.wrap_width(),
+        };
+        if let Some(cache) = self.render_cache.as_ref().filter(|cache| cache.key == key) {
+            self.rendered_lines = cache.lines.clone();
+            self.rendered_source_lines = cache.sources.clone();
+            self.rendered_images = cache.images.clone();
+            self.rendered_conflicts = cache.conflicts;
+            return;
+        }
+
+        self.rendered_conflicts = git::has_conflict_markers(&self.current_content);
+        let threshold = self.config.large_note_threshold_kb.saturating_mul(1024);
+        if !self.rendered_conflicts && self.current_content.len() as u64 > threshold {
+            // Parsing everything up front would make opening the note slow
+            self.rendered_lines.clear();
+            self.rendered_source_lines.clear();
+            self.rendered_images.clear();
+            self.lazy_render = Some(LazyRender {
+                chunks: markdown::split_into_chunks(&self.content_lines, LAZY_CHUNK_LINES),
+                rendered: 0,
+            });
+            self.render_lazy_chunks(self.line_selection + LAZY_BUFFER_LINES);
+            return;
+        }
+        if self.rendered_conflicts {
+            // Markdown would turn the ======= markers into headings
+            self.rendered_lines = markdown::render_conflicts(&self.current_content, self.markdown_renderer.tab_width());
+            self.rendered_source_lines.clear();
+            self.rendered_images.clear();
+        } else {
+            match self.markdown_renderer.parse_markdown_with_spans(&self.current_content) {
+                Ok((elements, spans)) => {
+                    let (lines, sources, images) = self.markdown_renderer.render_with_source_map(&elements, &spans);
+                    self.rendered_lines = lines;
+                    self.rendered_source_lines = sources;
+                    self.rendered_images = self.resolve_images(images, 0);
+                }
+                Err(e) => {
+                    // Fallback to plain text lines
+                    self.status_message = Some(format!("⚠ Could not parse markdown, showing plain text: {}", e));
+                    let tab_width = self.markdown_renderer.tab_width();
+                    self.rendered_lines = self.content_lines.iter()
+                        .map(|line| Line::from(markdown::expand_tabs(line, tab_width)))
+                        .collect();
+                    self.rendered_source_lines.clear();
+                    self.rendered_images.clear();
+                }
+            }
+        }
+
+        self.render_cache = Some(RenderCache {
+            key,
+            lines: self.rendered_lines.clone(),
+            sources: self.rendered_source_lines.clone(),
+            images: self.rendered_images.clone(),
+            conflicts: self.rendered_conflicts,
+        });
+    }
+
+    /// Drop the rendered lines inside folded sections, marking each folded heading
+    ///
+    /// A section runs to the next heading of the same or a higher level.
+    fn apply_folds(&mut self) {
+        if self.folded_headings.is_empty() || self.rendered_conflicts {
+            return;
+        }
+        // Folding needs the whole note, so finish rendering a large one
+        self.render_lazy_chunks(usize::MAX);
+
+        let headings = markdown::heading_lines(&self.content_lines);
+        let mut hidden = Vec::new();
+        for (index, &(line, level)) in headings.iter().enumerate() {
+            if self.folded_headings.contains(&line) {
+                let end = headings[index + 1..]
+                    .iter()
+                    .find(|&&(_, next_level)| next_level <= level)
+                    .map_or(self.content_lines.len(), |&(next_line, _)| next_line);
+                hidden.push((line, line + 1..end));
+            }
+        }
+        // Drop folds whose headings were edited away
+        self.folded_headings.retain(|line| hidden.iter().any(|(heading, _)| heading == line));
+
+        let lines = std::mem::take(&mut self.rendered_lines);
+        let sources = std::mem::take(&mut self.rendered_source_lines);
+        let mut new_index = Vec::with_capacity(lines.len());
+        let mut hiding = false;
+        let mut last_source = None;
+        for (i, mut line) in lines.into_iter().enumerate() {
+            let source = if sources.is_empty() { Some(i) } else { sources.get(i).copied().flatten() };
+            if let Some(source) = source {
+                hiding = hidden.iter().any(|(_, range)| range.contains(&source));
+            }
+            if hiding {
+                new_index.push(None);
+                continue;
+            }
+            // Mark the heading's first rendered line with the size of its section
+            if source.is_some() && source != last_source {
+                if let Some((_, range)) = hidden.iter().find(|(heading, _)| Some(*heading) == source) {
+                    line.spans.push(Span::styled(
+                        format!(" … {} lines", range.len()),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                last_source = source;
             }
-        } else {
-            // Clear all content when no file is selected
-            self.current_image = None;
-            self.image_state = None;
-            self.current_content.clear();
-            self.content_lines.clear();
-            self.rendered_lines.clear();
-            self.current_file = None;
-            self.line_selection = 0;
+            new_index.push(Some(self.rendered_lines.len()));
+            self.rendered_lines.push(line);
+            self.rendered_source_lines.push(source);
         }
-        Ok(())
+        self.rendered_images = std::mem::take(&mut self.rendered_images)
+            .into_iter()
+            .filter_map(|(line, path)| new_index.get(line).copied().flatten().map(|line| (line, path)))
+            .collect();
     }
 
-    fn edit_current_file(&mut self) -> Result<()> {
-        if let Some(file_path) = &self.current_file {
-            // Temporarily disable raw mode for the editor
-            disable_raw_mode()?;
-            execute!(io::stdout(), LeaveAlternateScreen)?;
+    /// Collapse or expand the section under the heading on the selected line
+    fn toggle_fold(&mut self) {
+        if !self.current_is_markdown() || self.rendered_conflicts {
+            return;
+        }
+        let source_line = match self.rendered_source_lines.get(self.line_selection) {
+            Some(source_line) => *source_line,
+            None => Some(self.line_selection),
+        };
+        let heading = match source_line {
+            Some(line) if markdown::heading_lines(&self.content_lines).iter().any(|&(heading, _)| heading == line) => line,
+            _ => {
+                self.status_message = Some("ℹ Select a heading to fold its section".to_string());
+                return;
+            }
+        };
+
+        if !self.folded_headings.remove(&heading) {
+            self.folded_headings.insert(heading);
+        }
+        self.rebuild_rendered_lines();
+        self.line_selection = self.rendered_source_lines
+            .iter()
+            .position(|&source| source == Some(heading))
+            .unwrap_or(self.line_selection)
+            .min(self.rendered_lines.len().saturating_sub(1));
+    }
 
-            let status = Command::new(&self.config.editor)
-                .arg(file_path)
-                .status()?;
+    /// Warn about malformed markdown in the open note, unless something more important is shown
+    fn report_markdown_problem(&mut self) {
+        if self.status_message.is_none() {
+            self.status_message = markdown::find_problem(&self.content_lines).map(|problem| format!("⚠ {}", problem));
+        }
+    }
 
-            // Re-enable raw mode and properly restore terminal
-            enable_raw_mode()?;
-            execute!(io::stdout(), EnterAlternateScreen)?;
-            
-            // Clear the screen to avoid corruption
-            execute!(io::stdout(), Clear(ClearType::All))?;
+    /// Resolve image URLs to files in the vault, shifting their lines by `offset`
+    fn resolve_images(&self, images: Vec<(usize, String)>, offset: usize) -> Vec<(usize, PathBuf)> {
+        let source = match &self.current_file {
+            Some(path) => path,
+            None => return Vec::new(),
+        };
+        images
+            .into_iter()
+            .filter(|(_, url)| !url.contains("://") && !url.starts_with("data:"))
+            .filter_map(|(line, url)| {
+                links::resolve_relative_link(&self.config.root_directory, source, &url)
+                    .filter(FileTree::is_image_file)
+                    .map(|path| (line + offset, path))
+            })
+            .collect()
+    }
+
+    /// Render chunks of a large note until `line` and the lines above it are rendered
+    fn render_lazy_chunks(&mut self, line: usize) {
+        loop {
+            let range = match &mut self.lazy_render {
+                Some(lazy) if self.rendered_lines.len() <= line && !lazy.is_complete() => {
+                    lazy.rendered += 1;
+                    lazy.chunks[lazy.rendered - 1].clone()
+                }
+                _ => return,
+            };
+
+            let text = self.content_lines[range.clone()].join("\n");
+            let (lines, sources, images) = match self.markdown_renderer.parse_markdown_with_spans(&text) {
+                Ok((elements, spans)) => self.markdown_renderer.render_with_source_map(&elements, &spans),
+                Err(_) => (
+                    self.content_lines[range.clone()].iter().map(|line| Line::from(line.clone())).collect(),
+                    (0..range.len()).map(Some).collect(),
+                    Vec::new(),
+                ),
+            };
+            let images = self.resolve_images(images, self.rendered_lines.len());
+            self.rendered_images.extend(images);
+            self.rendered_lines.extend(lines);
+            self.rendered_source_lines
+                .extend(sources.into_iter().map(|source| source.map(|source| source + range.start)));
+        }
+    }
+
+    /// Re-wrap markdown when the content pane width changes
+    fn update_wrap_width(&mut self) {
+        let width = self.content_width as usize;
+        if width == 0 || width == self.markdown_renderer.wrap_width() {
+            return;
+        }
+        self.markdown_renderer.set_wrap_width(width);
+
+        if self.current_is_markdown() {
+            self.rebuild_rendered_lines();
+            self.line_selection = self.line_selection.min(self.rendered_lines.len().saturating_sub(1));
+        }
+    }
+
+    /// Open `path` in the configured editor, returning whether it exited successfully
+    fn run_editor(&mut self, path: &Path) -> Result<bool> {
+        // The built-in editor only edits the open note
+        if self.uses_builtin_editor() {
+            self.status_message = Some(format!(
+                "ℹ Set an external editor in the config to edit {}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            return Ok(false);
+        }
+
+        // Like $EDITOR, the setting may carry quoted words and arguments, e.g. `code --wait`
+        let (program, args) = match self.config.editor_command() {
+            Some(words) if !words.is_empty() => (words[0].clone(), words[1..].to_vec()),
+            _ => {
+                self.status_message = Some(format!("✗ Cannot run the editor, check its quotes: {}", self.config.editor));
+                return Ok(false);
+            }
+        };
+
+        // Temporarily disable raw mode for the editor
+        disable_raw_mode()?;
+        execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
+
+        let status = Command::new(&program)
+            .args(&args)
+            .arg(path)
+            .status();
+
+        // Re-enable raw mode and properly restore terminal, even if the editor did not start
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+
+        // Clear the screen to avoid corruption
+        execute!(io::stdout(), Clear(ClearType::All))?;
+        self.needs_full_redraw = true;
+
+        match status {
+            Ok(status) if status.success() => Ok(true),
+            Ok(status) => {
+                self.status_message = Some(format!("⚠ The editor exited with {}", status));
+                Ok(false)
+            }
+            Err(e) => {
+                self.status_message = Some(format!("✗ Cannot run the editor {}: {}", program, e));
+                Ok(false)
+            }
+        }
+    }
+
+    fn edit_current_file(&mut self) -> Result<()> {
+        if self.current_is_encrypted() {
+            // An external editor would need the plaintext written to disk
+            self.status_message = Some("⚠ Encrypted notes can't be opened in an external editor".to_string());
+            return Ok(());
+        }
+        if let Some(file_path) = self.current_file.clone() {
+            // Don't let the user edit on the strength of a stale view
+            let modified = fs::metadata(&file_path).and_then(|metadata| metadata.modified()).ok();
+            if modified != self.current_file_modified {
+                self.invalidate_vault_index();
+                self.load_current_file_content()?;
+                self.status_message = Some(format!(
+                    "⚠ {} changed on disk since it was opened and was reloaded, press i again to edit it",
+                    file_path.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                return Ok(());
+            }
 
-            if status.success() {
+            if self.uses_builtin_editor() {
+                self.open_builtin_editor();
+            } else if self.run_editor(&file_path)? {
                 // Reload the file content after editing
+                self.invalidate_vault_index();
                 self.load_current_file_content()?;
-            } else {
-                eprintln!("Editor exited with error");
             }
         }
         Ok(())
     }
 
+    /// Whether notes are edited inside RNotes rather than in an external program
+    fn uses_builtin_editor(&self) -> bool {
+        let editor = self.config.editor.trim();
+        editor.is_empty() || editor == BUILTIN_EDITOR
+    }
+
+    /// Edit the open note in the built-in editor, starting at the selected line
+    fn open_builtin_editor(&mut self) {
+        if !self.current_is_markdown() {
+            self.status_message = Some("ℹ Only markdown notes can be edited here".to_string());
+            return;
+        }
+        if self.current_lossy {
+            // Saving would replace the undecodable bytes with � for good
+            self.status_message = Some("⚠ This note is not valid UTF-8, edit it in an external editor".to_string());
+            return;
+        }
+        let mut editor = TextEditor::new(&self.current_content);
+        let source_line = match self.rendered_source_lines.get(self.line_selection) {
+            Some(source_line) => *source_line,
+            None => Some(self.line_selection),
+        };
+        editor.go_to_line(source_line.unwrap_or(0));
+        self.text_editor = Some(editor);
+        self.editor_discard_pending = false;
+        self.mode = AppMode::Editor;
+    }
+
+    fn handle_editor_input(&mut self, key: KeyEvent) -> Result<()> {
+        let page = self.tree_height.max(1) as usize;
+        let tab_width = self.config.tab_width.max(1);
+        let editor = match &mut self.text_editor {
+            Some(editor) => editor,
+            None => {
+                self.mode = AppMode::Normal;
+                return Ok(());
+            }
+        };
+        let discard_pending = std::mem::take(&mut self.editor_discard_pending);
+
+        match key.code {
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => self.save_builtin_editor()?,
+            KeyCode::Esc if editor.is_modified() && !discard_pending => {
+                self.editor_discard_pending = true;
+                self.status_message = Some("⚠ Unsaved changes: Ctrl+S to save, Esc again to discard them".to_string());
+            }
+            KeyCode::Esc => {
+                self.text_editor = None;
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char(_) if key.modifiers.contains(KeyModifiers::CONTROL) => {}
+            KeyCode::Char(c) => editor.insert_char(c),
+            KeyCode::Tab => (0..tab_width).for_each(|_| editor.insert_char(' ')),
+            KeyCode::Enter => editor.insert_newline(),
+            KeyCode::Backspace => editor.backspace(),
+            KeyCode::Delete => editor.delete(),
+            KeyCode::Left => editor.move_left(),
+            KeyCode::Right => editor.move_right(),
+            KeyCode::Up => editor.move_up(1),
+            KeyCode::Down => editor.move_down(1),
+            KeyCode::PageUp => editor.move_up(page),
+            KeyCode::PageDown => editor.move_down(page),
+            KeyCode::Home => editor.move_home(),
+            KeyCode::End => editor.move_end(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Write the built-in editor's text to the open note and refresh the preview
+    fn save_builtin_editor(&mut self) -> Result<()> {
+        let (file_path, text) = match (&self.current_file, &self.text_editor) {
+            (Some(path), Some(editor)) => (path.clone(), editor.text()),
+            _ => return Ok(()),
+        };
+        let name = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        // Saving over a version written elsewhere would silently lose it
+        let modified = fs::metadata(&file_path).and_then(|metadata| metadata.modified()).ok();
+        if modified != self.current_file_modified {
+            self.status_message = Some(format!("⚠ {} changed on disk while you were editing, not saved", name));
+            return Ok(());
+        }
+        let saved = if crypto::is_encrypted_note(&file_path) {
+            // Encrypted notes are written encrypted again, never as plaintext
+            match &self.encryption_passphrase {
+                Some(passphrase) => crypto::encrypt_note(&file_path, &text, passphrase),
+                None => Err(anyhow::anyhow!("the note is locked")),
+            }
+        } else {
+            fs::write(&file_path, restore_line_endings(&text, self.current_crlf)).map_err(anyhow::Error::from)
+        };
+        if let Err(e) = saved {
+            self.status_message = Some(format!("✗ Saving {} failed: {}", name, e));
+            return Ok(());
+        }
+        if let Some(decrypted) = self.decrypted_notes.get_mut(&file_path) {
+            *decrypted = text;
+        }
+
+        if let Some(editor) = &mut self.text_editor {
+            editor.mark_saved();
+        }
+        self.invalidate_vault_index();
+        self.load_current_file_content()?;
+        self.status_message = Some(format!("✓ Saved {}", name));
+        Ok(())
+    }
+
     fn create_new_file(&mut self) -> Result<()> {
-        // Save current tree state before creating the file
-        let expanded_dirs = self.file_tree.get_expansion_state();
-        
-        // Determine the target directory
-        let target_dir = if let Some(selected_path) = self.file_tree.get_selected_path() {
+        let templates = self.config
+            .templates_path()
+            .map(|dir| templates::list_templates(&dir))
+            .unwrap_or_default();
+
+        if templates.is_empty() {
+            return self.create_note_from_template(None);
+        }
+
+        // Let the user pick a template, with the built-in default first
+        self.template_entries = std::iter::once(None)
+            .chain(templates.into_iter().map(Some))
+            .collect();
+        self.template_selection = 0;
+        self.mode = AppMode::TemplatePicker;
+        Ok(())
+    }
+
+    fn handle_template_picker_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.template_selection + 1 < self.template_entries.len() => {
+                self.template_selection += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.template_selection = self.template_selection.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                self.mode = AppMode::Normal;
+                let template = self.template_entries.get(self.template_selection).cloned().flatten();
+                self.create_note_from_template(template.as_deref())?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_vault_picker(&mut self) {
+        if self.config.vaults.is_empty() {
+            self.status_message = Some("ℹ No vaults configured, add them to \"vaults\" in the config file".to_string());
+            return;
+        }
+        self.vault_selection = self.config.vaults
+            .iter()
+            .position(|vault| vault.path == self.config.root_directory)
+            .unwrap_or(0);
+        self.mode = AppMode::VaultPicker;
+    }
+
+    fn handle_vault_picker_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.vault_selection + 1 < self.config.vaults.len() => {
+                self.vault_selection += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.vault_selection = self.vault_selection.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                self.mode = AppMode::Normal;
+                if let Some(vault) = self.config.vaults.get(self.vault_selection).cloned() {
+                    self.switch_vault(&vault)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Make `vault` the notes directory, rebuilding everything that depends on it
+    fn switch_vault(&mut self, vault: &Vault) -> Result<()> {
+        if vault.path == self.config.root_directory {
+            return Ok(());
+        }
+        if let Some(task) = &self.git_task {
+            self.status_message = Some(format!("⚠ {} is still in progress, switch vaults once it is done", task.operation.label()));
+            return Ok(());
+        }
+        if !vault.path.is_dir() {
+            self.status_message = Some(format!("✗ Vault directory not found: {}", vault.path.display()));
+            return Ok(());
+        }
+
+        // The last active vault is opened on the next start, with its own recent notes
+        self.store_recent_notes();
+        self.config.root_directory = vault.path.clone();
+        self.config.suspend_git(false);
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("✗ Saving config failed: {}", e));
+        }
+
+        self.git_manager = GitManager::new(self.config.git_settings());
+        let mut pull = false;
+        if self.git_manager.is_enabled() && (!self.read_only || self.git_manager.is_git_repository()) {
+            match self.git_manager.init_repository() {
+                Ok(()) => pull = self.config.pull_on_startup,
+                Err(e) => self.status_message = Some(format!("⚠ Failed to initialize Git repository: {}", e)),
+            }
+        }
+
+        // Nothing from the previous vault carries over
+        self.invalidate_vault_index();
+        self.active_tag = None;
+        self.current_file = None;
+        self.line_positions.clear();
+        self.recent_notes = remembered_recent_notes(&self.config);
+        self.recent_position = 0;
+        self.file_tree = FileTree::new(&self.config)?;
+        if !self.file_tree.invalid_ignore_patterns().is_empty() && self.status_message.is_none() {
+            self.status_message = Some(invalid_patterns_message(self.file_tree.invalid_ignore_patterns()));
+        }
+        self.load_current_file_content()?;
+        self.start_search_indexing();
+
+        if self.status_message.is_none() {
+            self.status_message = Some(if self.config.git_enabled && !self.git_manager.is_enabled() {
+                format!("✓ Switched to vault {} (Git is off, set its git_repository to sync it)", vault.name)
+            } else {
+                format!("✓ Switched to vault {}", vault.name)
+            });
+        }
+        if pull {
+            self.start_git_task(GitOperation::Pull);
+        }
+        Ok(())
+    }
+
+    /// Folder new notes go in: the selected folder, or the selected file's folder
+    fn new_note_dir(&self) -> PathBuf {
+        if let Some(selected_path) = self.file_tree.get_selected_path() {
             if selected_path.is_dir() {
                 // If a directory is selected, create the file inside it
                 // Make sure this directory is expanded after refresh
@@ -469,17 +2569,70 @@ impl App {
         } else {
             // If nothing is selected, use the root directory
             self.config.root_directory.clone()
-        };
-        
+        }
+    }
+
+    fn create_note_from_template(&mut self, template: Option<&Path>) -> Result<()> {
+        let target_dir = self.new_note_dir();
+
         // Simple implementation - create a file with timestamp
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
-        
-        let filename = format!("note_{}.md", timestamp);
+        let title = self.timestamped_name("note", &target_dir, ".md")?;
+        let filename = format!("{}.md", title);
         let file_path = target_dir.join(&filename);
-        
-        fs::write(&file_path, "# New Note\n\nWrite your notes here...\n")?;
+
+        let template_content = match template {
+            Some(path) => fs::read_to_string(path)?,
+            None => templates::DEFAULT_TEMPLATE.to_string(),
+        };
+        let content = templates::fill_placeholders(&template_content, &title, &chrono::Local::now(), self.config.date_format());
+        self.add_new_note(file_path, &content)
+    }
+
+    /// Create a note from the clipboard text, named after its first line
+    fn create_note_from_clipboard(&mut self) -> Result<()> {
+        let text = match self.clipboard.paste().filter(|text| !text.trim().is_empty()) {
+            Some(text) => text,
+            None => {
+                self.create_new_file()?;
+                if self.status_message.is_none() {
+                    self.status_message = Some("ℹ Clipboard is empty, created a regular note".to_string());
+                }
+                return Ok(());
+            }
+        };
+
+        let target_dir = self.new_note_dir();
+        let first_line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+        let base = note_name_from_line(first_line);
+        let name = if base.is_empty() {
+            self.timestamped_name("note", &target_dir, ".md")?
+        } else {
+            let mut name = base.clone();
+            let mut counter = 2;
+            while target_dir.join(format!("{}.md", name)).exists() {
+                name = format!("{}_{}", base, counter);
+                counter += 1;
+            }
+            name
+        };
+
+        let mut content = normalize_line_endings(&text);
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        self.add_new_note(target_dir.join(format!("{}.md", name)), &content)?;
+        self.status_message = Some(format!("✓ Created {}.md from the clipboard", name));
+        Ok(())
+    }
+
+    /// Write a new note and select it, keeping the tree's expanded folders
+    fn add_new_note(&mut self, file_path: PathBuf, content: &str) -> Result<()> {
+        // Save current tree state before creating the file
+        let expanded_dirs = self.file_tree.get_expansion_state();
+        let target_dir = file_path.parent().unwrap_or(&self.config.root_directory).to_path_buf();
+
+        fs::write(&file_path, content)?;
+        self.invalidate_vault_index();
         
         // If we created a file in a directory, make sure that directory stays expanded
         let mut final_expanded_dirs = expanded_dirs;
@@ -497,6 +2650,53 @@ impl App {
         Ok(())
     }
 
+    /// `<prefix>_<timestamp>` using the configured date format, unique within `dir`
+    fn timestamped_name(&self, prefix: &str, dir: &Path, extension: &str) -> Result<String> {
+        let timestamp = match self.config.date_format() {
+            // A format like %d/%m/%Y must not create subdirectories
+            Some(format) => chrono::Local::now().format(format).to_string().replace(['/', '\\'], "-"),
+            None => SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs()
+                .to_string(),
+        };
+
+        let base = format!("{}_{}", prefix, timestamp);
+        let mut name = base.clone();
+        let mut counter = 2;
+        while dir.join(format!("{}{}", name, extension)).exists() {
+            name = format!("{}_{}", base, counter);
+            counter += 1;
+        }
+        Ok(name)
+    }
+
+    fn open_journal(&mut self) -> Result<()> {
+        let now = chrono::Local::now();
+        let relative_path = templates::fill_placeholders(&self.config.journal_path_pattern, "", &now, self.config.date_format());
+        let journal_path = self.config.root_directory.join(relative_path);
+        if !is_inside_root(&self.config.root_directory, &journal_path) {
+            self.status_message = Some("✗ journal_path_pattern points outside the notes directory".to_string());
+            return Ok(());
+        }
+
+        if !journal_path.exists() {
+            if let Some(parent) = journal_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let template_content = match &self.config.journal_template {
+                Some(template) => fs::read_to_string(self.config.root_directory.join(template))?,
+                None => templates::DEFAULT_JOURNAL_TEMPLATE.to_string(),
+            };
+            let title = journal_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            fs::write(&journal_path, templates::fill_placeholders(&template_content, &title, &now, self.config.date_format()))?;
+            self.invalidate_vault_index();
+        }
+
+        self.open_note(&journal_path)
+    }
+
     fn create_new_folder(&mut self) -> Result<()> {
         // Save current tree state before creating the folder
         let expanded_dirs = self.file_tree.get_expansion_state();
@@ -515,11 +2715,7 @@ impl App {
             self.config.root_directory.clone()
         };
         
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
-        
-        let folder_name = format!("folder_{}", timestamp);
+        let folder_name = self.timestamped_name("folder", &target_dir, "")?;
         let folder_path = target_dir.join(&folder_name);
         
         fs::create_dir(&folder_path)?;
@@ -536,6 +2732,31 @@ impl App {
         Ok(())
     }
 
+    fn duplicate_selected_file(&mut self) -> Result<()> {
+        if let Some(source_path) = self.file_tree.get_selected_file() {
+            let source_path = source_path.clone();
+            let expanded_dirs = self.file_tree.get_expansion_state();
+            let parent = source_path.parent().unwrap_or(&self.config.root_directory).to_path_buf();
+            let (stem, extension) = split_extension(&source_path);
+
+            // Find a free "<name> copy.md", then "<name> copy 2.md", ...
+            let mut copy_path = parent.join(format!("{} copy{}", stem, extension));
+            let mut counter = 2;
+            while copy_path.exists() {
+                copy_path = parent.join(format!("{} copy {}{}", stem, counter, extension));
+                counter += 1;
+            }
+
+            fs::copy(&source_path, &copy_path)?;
+            self.invalidate_vault_index();
+
+            // Refresh file tree while preserving state and selecting the copy
+            self.file_tree.refresh_with_state(expanded_dirs, Some(copy_path))?;
+            self.load_current_file_content()?;
+        }
+        Ok(())
+    }
+
     fn handle_delete_confirm_input(&mut self, key_code: KeyCode) -> Result<()> {
         match key_code {
             KeyCode::Char('y') | KeyCode::Char('Y') => {
@@ -553,13 +2774,47 @@ impl App {
     }
 
     fn start_delete(&mut self) -> Result<()> {
-        if let Some(path) = self.file_tree.get_selected_path() {
-            self.delete_target = Some(path.clone());
+        if let Some(path) = self.file_tree.get_selected_path().cloned() {
+            self.delete_preview = self.note_preview(&path);
+            self.delete_counts = path.is_dir().then(|| count_folder_contents(&path));
+            self.delete_target = Some(path);
             self.mode = AppMode::DeleteConfirm;
         }
         Ok(())
     }
 
+    /// First few non-empty lines of a note, without reading more of it than needed
+    fn note_preview(&self, path: &Path) -> Vec<String> {
+        let first_lines = |text: &str| -> Vec<String> {
+            text.lines()
+                .map(str::trim_end)
+                .filter(|line| !line.trim().is_empty())
+                .take(DELETE_PREVIEW_LINES)
+                .map(str::to_string)
+                .collect()
+        };
+
+        if self.current_file.as_deref() == Some(path) && self.current_is_markdown() {
+            return first_lines(&self.current_content);
+        }
+        if crypto::is_encrypted_note(path) {
+            // Never decrypt just for a preview; only use what is already unlocked
+            return self.decrypted_notes.get(path).map(|text| first_lines(text)).unwrap_or_default();
+        }
+        if !file_tree::is_markdown_file(path) {
+            return Vec::new();
+        }
+        match fs::File::open(path) {
+            Ok(file) => io::BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.trim().is_empty())
+                .take(DELETE_PREVIEW_LINES)
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     fn perform_delete(&mut self) -> Result<()> {
         if let Some(target_path) = &self.delete_target {
             let target_path = target_path.clone(); // Clone to avoid borrow issues
@@ -574,6 +2829,7 @@ impl App {
                 // For files, remove the file
                 std::fs::remove_file(&target_path)?;
             }
+            self.invalidate_vault_index();
             
             // If we deleted the currently viewed file, clear the content
             if Some(&target_path) == self.current_file.as_ref() {
@@ -587,8 +2843,56 @@ impl App {
             // Refresh the file tree while preserving expansion state
             self.file_tree.refresh_with_state(expanded_dirs, selection_target)?;
             
+            if target_path == self.config.root_directory.join(WELCOME_FILE_NAME) && !self.config.welcome_file_deleted() {
+                self.config.mark_welcome_file_deleted();
+                if let Err(e) = self.config.save() {
+                    self.status_message = Some(format!("✗ Saving config failed: {}", e));
+                }
+            }
+
             // Try to load content for the new selection if any
             self.load_current_file_content()?;
+            self.line_positions.retain(|path, _| !path.starts_with(&target_path));
+            self.recent_notes.retain(|path| !path.starts_with(&target_path));
+            self.recent_position = 0;
+        }
+        Ok(())
+    }
+
+    fn start_discard(&mut self) {
+        let path = match self.file_tree.get_selected_file() {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        match self.git_manager.check_discardable(&path) {
+            Ok(()) => {
+                self.discard_target = Some(path);
+                self.mode = AppMode::DiscardConfirm;
+            }
+            Err(e) => self.status_message = Some(format!("ℹ {}", e)),
+        }
+    }
+
+    fn handle_discard_confirm_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                if let Some(path) = self.discard_target.take() {
+                    match self.git_manager.discard_changes(&path) {
+                        Ok(()) => {
+                            self.status_message = Some("✓ Changes discarded".to_string());
+                            self.invalidate_vault_index();
+                            self.load_current_file_content()?;
+                        }
+                        Err(e) => self.status_message = Some(format!("✗ Discarding changes failed: {}", e)),
+                    }
+                }
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+                self.discard_target = None;
+            }
+            _ => {}
         }
         Ok(())
     }
@@ -612,6 +2916,13 @@ impl App {
             KeyCode::Char('y') => {
                 self.copy_current_line()?;
             }
+            KeyCode::Char('Y') => self.copy_note(false),
+            KeyCode::Char('r') => self.copy_note(true),
+            KeyCode::Enter => {
+                self.follow_link_on_current_line()?;
+            }
+            KeyCode::Char('B') => self.toggle_blame(),
+            KeyCode::Char('z') => self.toggle_fold(),
             KeyCode::Char('i') => {
                 // Edit file from line navigation mode
                 self.mode = AppMode::Normal;
@@ -622,138 +2933,625 @@ impl App {
         Ok(())
     }
 
+    /// Show or hide who last changed each line of the open note
+    fn toggle_blame(&mut self) {
+        if self.blame.take().is_some() {
+            return;
+        }
+        let path = match &self.current_file {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        if !self.git_manager.is_enabled() {
+            self.status_message = Some("ℹ Git integration is not enabled".to_string());
+            return;
+        }
+        if crypto::is_encrypted_note(&path) {
+            self.status_message = Some("ℹ Blame is not available for encrypted notes".to_string());
+            return;
+        }
+
+        match self.git_manager.blame(&path, &self.current_content) {
+            Ok(blame) => self.blame = Some(blame),
+            Err(e) => self.status_message = Some(format!("✗ Blame failed: {}", e)),
+        }
+    }
+
+    /// Edit the vault's .gitignore, creating it with the default patterns if missing
+    fn edit_gitignore(&mut self) -> Result<()> {
+        let gitignore_path = self.config.root_directory.join(".gitignore");
+        if !gitignore_path.exists() {
+            if let Err(e) = fs::write(&gitignore_path, git::DEFAULT_GITIGNORE) {
+                self.status_message = Some(format!("✗ Creating .gitignore failed: {}", e));
+                return Ok(());
+            }
+        }
+
+        if self.run_editor(&gitignore_path)? {
+            // Newly ignored files drop out of the tree and the indexes
+            self.invalidate_vault_index();
+            let expanded_dirs = self.file_tree.get_expansion_state();
+            let selected_path = self.file_tree.get_selected_path().cloned();
+            self.file_tree.refresh_with_state(expanded_dirs, selected_path)?;
+            self.load_current_file_content()?;
+        }
+        Ok(())
+    }
+
+    fn enter_split_preview_mode(&mut self) {
+        if self.current_is_markdown() {
+            // The preview scrolls in proportion to the whole note
+            self.render_lazy_chunks(usize::MAX);
+            self.split_scroll = 0;
+            self.mode = AppMode::SplitPreview;
+        }
+    }
+
+    fn handle_split_preview_input(&mut self, key_code: KeyCode) -> Result<()> {
+        let last_line = self.content_lines.len().saturating_sub(1);
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('v') => {
+                self.mode = AppMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.split_scroll = (self.split_scroll + 1).min(last_line);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.split_scroll = self.split_scroll.saturating_sub(1);
+            }
+            KeyCode::PageDown => {
+                self.split_scroll = (self.split_scroll + 20).min(last_line);
+            }
+            KeyCode::PageUp => {
+                self.split_scroll = self.split_scroll.saturating_sub(20);
+            }
+            KeyCode::Home => self.split_scroll = 0,
+            KeyCode::End => self.split_scroll = last_line,
+            KeyCode::Char('i') => {
+                self.edit_current_file()?;
+                let last_line = self.content_lines.len().saturating_sub(1);
+                self.split_scroll = self.split_scroll.min(last_line);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     fn enter_line_navigation_mode(&mut self) -> Result<()> {
         if self.current_file.is_some() && !self.current_content.is_empty() {
-            // Use the pre-processed lines (content_lines for plain text copy, rendered_lines for display)
-            self.line_selection = 0;
+            // Use the pre-processed lines (content_lines for plain text copy, rendered_lines for display);
+            // line_selection keeps the position last used in this note
             self.mode = AppMode::LineNavigation;
+            self.remember_recent_note();
         }
         Ok(())
     }
 
     fn copy_current_line(&mut self) -> Result<()> {
-        if let Some(line) = self.content_lines.get(self.line_selection) {
-            match arboard::Clipboard::new() {
-                Ok(mut clipboard) => {
-                    match clipboard.set_text(line.clone()) {
-                        Ok(_) => {
-                            // Successfully copied to clipboard
-                            // We could add a status message here in the future
-                        }
-                        Err(e) => {
-                            // Failed to copy to clipboard
-                            eprintln!("Failed to copy to clipboard: {}", e);
-                        }
-                    }
-                }
+        // Copy the markdown source behind the selected rendered line
+        let source_line = match self.rendered_source_lines.get(self.line_selection) {
+            Some(Some(source_line)) => *source_line,
+            Some(None) => {
+                self.status_message = Some("ℹ Nothing to copy on this line".to_string());
+                return Ok(());
+            }
+            None => self.line_selection,
+        };
+        if let Some(line) = self.content_lines.get(source_line).cloned() {
+            self.status_message = Some(match self.clipboard.copy(&line, self.current_is_encrypted()) {
+                Ok(outcome) => outcome.message("line"),
+                Err(e) => format!("✗ Copying line failed: {}", e),
+            });
+        }
+        Ok(())
+    }
+
+    /// Copy the whole note, as markdown or as the plain text it renders to
+    fn copy_note(&mut self, rendered: bool) {
+        if !self.current_is_markdown() {
+            return;
+        }
+        let (text, what) = if rendered {
+            match self.markdown_renderer.parse_markdown(&self.current_content) {
+                Ok(elements) => (self.markdown_renderer.render_to_plain_text(&elements), "note as plain text"),
                 Err(e) => {
-                    // Failed to create clipboard
-                    eprintln!("Failed to create clipboard: {}", e);
+                    self.status_message = Some(format!("✗ Rendering note failed: {}", e));
+                    return;
+                }
+            }
+        } else {
+            (self.current_content.clone(), "note")
+        };
+        self.status_message = Some(match self.clipboard.copy(&text, self.current_is_encrypted()) {
+            Ok(outcome) => outcome.message(what),
+            Err(e) => format!("✗ Copying {} failed: {}", what, e),
+        });
+    }
+
+    /// Open the note a relative markdown link on the selected line points to
+    fn follow_link_on_current_line(&mut self) -> Result<()> {
+        let source_line = match self.rendered_source_lines.get(self.line_selection) {
+            Some(source_line) => *source_line,
+            None => Some(self.line_selection),
+        };
+        let url = match source_line
+            .and_then(|line| self.content_lines.get(line))
+            .and_then(|line| links::note_link_in_line(line))
+        {
+            Some(url) => url,
+            None => {
+                self.status_message = Some("ℹ No link to a note on this line".to_string());
+                return Ok(());
+            }
+        };
+        let current_file = match &self.current_file {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+
+        match links::resolve_relative_link(&self.config.root_directory, &current_file, &url) {
+            Some(target) if target.is_file() => {
+                self.open_note(&target)?;
+                if self.current_file.as_ref() != Some(&target) {
+                    self.status_message = Some(format!("⚠ {} is hidden from the file tree", url));
                 }
             }
+            Some(_) => self.status_message = Some(format!("✗ Linked note not found: {}", url)),
+            None => self.status_message = Some(format!("✗ Link points outside the notes directory: {}", url)),
         }
         Ok(())
     }
 
-    fn copy_image_to_clipboard(&mut self) -> Result<()> {
-        if let Some(selected_path) = self.file_tree.get_selected_path() {
-            if FileTree::is_image_file(&selected_path) {
-                // Copy image file path to clipboard (or could copy image data)
-                let mut clipboard = Clipboard::new()?;
-                let image_path = selected_path.to_string_lossy().to_string();
-                clipboard.set_text(image_path)?;
-                
-                // Could also copy the actual image data, but for now we'll copy the path
-                // For copying actual image data, you'd need to read the image and convert it
-                // to a format the clipboard can handle
+    /// Copy the selected item's absolute path, or its path relative to the notes root
+    fn copy_selected_path(&mut self, relative: bool) {
+        let selected_path = match self.file_tree.get_selected_path() {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let path = if relative {
+            selected_path.strip_prefix(&self.config.root_directory).unwrap_or(&selected_path)
+        } else {
+            selected_path.as_path()
+        };
+        let text = path.to_string_lossy().to_string();
+
+        self.status_message = Some(match self.clipboard.copy(&text, false) {
+            Ok(outcome) => outcome.message(&text),
+            Err(e) => format!("✗ Copying path failed: {}", e),
+        });
+    }
+
+    /// Show the selected folder (or the selected file's folder), or the notes root, in the file manager
+    fn open_in_file_manager(&mut self, root: bool) {
+        let directory = match self.file_tree.get_selected_path() {
+            Some(path) if !root && path.is_dir() => path.clone(),
+            Some(path) if !root => path.parent().unwrap_or(&self.config.root_directory).to_path_buf(),
+            _ => self.config.root_directory.clone(),
+        };
+
+        self.status_message = Some(match open::that_detached(&directory) {
+            Ok(()) => format!("✓ Opened {} in the file manager", directory.display()),
+            Err(e) => format!("✗ Opening the file manager failed: {}", e),
+        });
+    }
+
+    fn export_current_note_html(&mut self) {
+        if let Some(file_path) = &self.current_file {
+            if FileTree::is_image_file(file_path) {
+                return;
+            }
+            if crypto::is_encrypted_note(file_path) {
+                self.status_message = Some("⚠ Encrypted notes can't be exported, that would write them to disk unencrypted".to_string());
+                return;
             }
+
+            let export_dir = self.config.export_directory.as_deref();
+            self.status_message = Some(match export::export_html(file_path, &self.current_content, export_dir, self.config.markdown_flavor) {
+                Ok(output_path) => format!("✓ Exported to {}", output_path.display()),
+                Err(e) => format!("✗ HTML export failed: {}", e),
+            });
         }
-        Ok(())
     }
 
-    fn save_current_config_field(&mut self) {
-        match self.config_field {
-            0 => {
-                if let Ok(path) = PathBuf::from(&self.config_input).canonicalize() {
-                    self.config.root_directory = path;
+    fn export_current_note_pdf(&mut self) {
+        if let Some(file_path) = &self.current_file {
+            if FileTree::is_image_file(file_path) {
+                return;
+            }
+            if crypto::is_encrypted_note(file_path) {
+                self.status_message = Some("⚠ Encrypted notes can't be exported, that would write them to disk unencrypted".to_string());
+                return;
+            }
+
+            // The converter is non-interactive, so the TUI can stay up while it runs
+            let export_dir = self.config.export_directory.as_deref();
+            self.status_message = Some(match export::export_pdf(file_path, export_dir, &self.config.pdf_converter) {
+                Ok(output_path) => format!("✓ Exported to {}", output_path.display()),
+                Err(e) => format!("✗ PDF export failed: {}", e),
+            });
+        }
+    }
+
+    fn open_command_palette(&mut self) {
+        self.command_input.clear();
+        self.command_selection = 0;
+        self.mode = AppMode::CommandPalette;
+    }
+
+    fn handle_command_palette_input(&mut self, key_code: KeyCode) -> Result<()> {
+        let (name, _) = commands::parse(self.command_input.as_str());
+        let matches = commands::matching(name);
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+                self.command_input.clear();
+            }
+            KeyCode::Down if self.command_selection + 1 < matches.len() => {
+                self.command_selection += 1;
+            }
+            KeyCode::Up => {
+                self.command_selection = self.command_selection.saturating_sub(1);
+            }
+            KeyCode::Tab => {
+                // Complete as far as the matches agree, then to the highlighted one
+                let completion = match commands::complete(name) {
+                    Some(shared) if shared != name => Some(shared),
+                    _ => matches.get(self.command_selection).map(|command| command.name),
+                };
+                if let Some(completion) = completion {
+                    self.command_input.set(completion.to_string());
+                    self.command_selection = 0;
                 }
             }
-            1 => {
-                self.config.editor = self.config_input.clone();
+            KeyCode::Enter => self.run_palette_command()?,
+            _ => {
+                edit_line_input(&mut self.command_input, key_code);
+                self.command_selection = 0;
             }
-            2 => {
-                // Git enabled is handled in the input handler
+        }
+        Ok(())
+    }
+
+    /// Run the typed command, or the highlighted one if the name is only partly typed
+    fn run_palette_command(&mut self) -> Result<()> {
+        let input = self.command_input.as_str().to_string();
+        let (name, argument) = commands::parse(&input);
+        let matches = commands::matching(name);
+        let command = match matches.iter().find(|command| command.name == name).or(matches.get(self.command_selection)) {
+            Some(command) => command,
+            None => {
+                self.status_message = Some(format!("✗ Unknown command '{}'", name));
+                return Ok(());
             }
-            3 => {
-                if self.config_input.trim().is_empty() {
-                    self.config.git_repository = None;
-                } else {
-                    self.config.git_repository = Some(self.config_input.clone());
+        };
+        if argument.is_some() && command.name != "search" {
+            self.status_message = Some(format!("✗ '{}' does not take an argument", command.name));
+            return Ok(());
+        }
+
+        self.mode = AppMode::Normal;
+        self.command_input.clear();
+        // Pressing the command's key keeps read-only mode and every other check in one place
+        self.handle_key(KeyEvent::new(KeyCode::Char(command.key), KeyModifiers::NONE))?;
+        if let (Some(query), AppMode::Search) = (argument, &self.mode) {
+            self.search_input.set(query.to_string());
+            self.run_search();
+        }
+        Ok(())
+    }
+
+    /// Ask where to write the whole vault, suggesting the export directory or the folder holding the vault
+    fn start_vault_export(&mut self) {
+        let root = &self.config.root_directory;
+        let name = root.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "notes".to_string());
+        let directory = match (&self.config.export_directory, root.parent()) {
+            (Some(dir), _) => config::expand_home(&dir.to_string_lossy()),
+            (None, Some(parent)) => parent.to_path_buf(),
+            (None, None) => root.clone(),
+        };
+        self.export_vault_input.set(directory.join(format!("{}.md", name)).to_string_lossy().to_string());
+        self.mode = AppMode::ExportVault;
+    }
+
+    fn handle_export_vault_input(&mut self, key_code: KeyCode) -> Result<()> {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = AppMode::Normal;
+                self.export_vault_input.clear();
+                self.export_vault_overwrite = None;
+            }
+            KeyCode::Enter if !self.export_vault_input.as_str().trim().is_empty() => {
+                let output = config::expand_home(self.export_vault_input.as_str().trim());
+                if let Err(e) = export::check_vault_export_target(&self.config.root_directory, &output) {
+                    self.status_message = Some(format!("✗ {}", e));
+                    return Ok(());
+                }
+                // Replacing a file takes a second Enter on the same path
+                let overwrite = output.exists();
+                if overwrite && self.export_vault_overwrite.as_ref() != Some(&output) {
+                    self.status_message = Some(format!("⚠ {} already exists, press Enter again to replace it", output.display()));
+                    self.export_vault_overwrite = Some(output);
+                    return Ok(());
                 }
+                let notes = self.file_tree.notes_in_tree_order();
+                self.status_message = Some(match export::export_vault(&self.config.root_directory, &notes, &output, self.config.markdown_flavor, overwrite) {
+                    Ok(count) => format!("✓ Exported {} notes to {}", count, output.display()),
+                    Err(e) => format!("✗ Vault export failed: {:#}", e),
+                });
+                self.mode = AppMode::Normal;
+                self.export_vault_input.clear();
+                self.export_vault_overwrite = None;
             }
-            4 => {
-                if self.config_input.trim().is_empty() {
-                    self.config.git_username = None;
-                } else {
-                    self.config.git_username = Some(self.config_input.clone());
+            _ => {
+                edit_line_input(&mut self.export_vault_input, key_code);
+                self.export_vault_overwrite = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Store the text typed into the selected setting in the draft, returning false if it does not fit the setting
+    fn save_current_config_field(&mut self) -> bool {
+        let field = match self.config_fields.get(self.config_field) {
+            Some(field) if field.kind == FieldKind::Text => field.clone(),
+            _ => return true,
+        };
+
+        // Take the first reading of the text the setting accepts, e.g. a number for tab_width
+        let accepted = settings::candidates(self.config_input.as_str()).into_iter().find(|value| {
+            let mut draft = self.config_draft.clone();
+            settings::set(&mut draft, &field.key, value.clone());
+            self.config.with_settings(draft).is_ok()
+        });
+        let value = match accepted {
+            Some(value) => value,
+            None => {
+                self.status_message = Some(format!("✗ '{}' is not a valid value for {}", self.config_input.as_str(), field.label));
+                return false;
+            }
+        };
+
+        match field.key.as_str() {
+            "root_directory" => {
+                let path = config::expand_home(self.config_input.as_str().trim());
+                if !path.is_absolute() {
+                    self.status_message = Some(format!("✗ {} is not an absolute path (~ is your home folder)", path.display()));
+                    return false;
+                }
+                match path.canonicalize() {
+                    Ok(path) if path.is_dir() => {
+                        settings::set(&mut self.config_draft, &field.key, serde_json::Value::String(path.to_string_lossy().to_string()));
+                    }
+                    Ok(_) => {
+                        self.status_message = Some(format!("✗ {} is not a folder", path.display()));
+                        return false;
+                    }
+                    // Created on saving, like a missing root is on startup
+                    Err(_) if !path.exists() => {
+                        settings::set(&mut self.config_draft, &field.key, serde_json::Value::String(path.to_string_lossy().to_string()));
+                        self.status_message = Some(format!("ℹ {} does not exist yet and will be created on saving", path.display()));
+                    }
+                    Err(e) => {
+                        self.status_message = Some(format!("✗ Cannot use {} as the notes directory: {}", path.display(), e));
+                        return false;
+                    }
                 }
             }
-            5 => {
-                if self.config_input.trim().is_empty() {
-                    self.config.git_email = None;
-                } else {
-                    self.config.git_email = Some(self.config_input.clone());
+            "command_palette_key" => {
+                let key = value.as_str().and_then(|key| key.chars().next()).unwrap_or_default();
+                if let Some(action) = commands::bound_to(key) {
+                    self.status_message = Some(format!("✗ '{}' is already bound to: {}", key, action));
+                    return false;
                 }
+                settings::set(&mut self.config_draft, &field.key, value);
             }
-            _ => {}
+            "git_email" if !self.config_input.as_str().trim().is_empty() && !git::is_valid_email(self.config_input.as_str()) => {
+                self.status_message = Some(format!("⚠ '{}' doesn't look like an email address; not saved", self.config_input.as_str()));
+            }
+            _ => settings::set(&mut self.config_draft, &field.key, value),
         }
+        true
     }
 
     fn load_current_config_field(&mut self) {
-        self.config_input = match self.config_field {
-            0 => self.config.root_directory.to_string_lossy().to_string(),
-            1 => self.config.editor.clone(),
-            2 => self.config.git_enabled.to_string(),
-            3 => self.config.git_repository.clone().unwrap_or_default(),
-            4 => self.config.git_username.clone().unwrap_or_default(),
-            5 => self.config.git_email.clone().unwrap_or_default(),
-            _ => String::new(),
-        };
+        self.config_input.set(match self.config_fields.get(self.config_field) {
+            Some(field) => settings::get(&self.config_draft, &field.key).map(settings::display).unwrap_or_default(),
+            None => String::new(),
+        });
     }
 
     fn perform_git_push(&mut self) -> Result<()> {
-        if !self.config.git_enabled {
+        if !self.git_manager.is_enabled() {
             return Ok(());
         }
 
         // Commit current changes and push
-        if let Err(e) = self.git_manager.commit_and_push() {
-            eprintln!("Git push failed: {}", e);
-        }
+        self.start_git_task(GitOperation::Push);
 
         Ok(())
     }
 
     fn perform_git_pull(&mut self) -> Result<()> {
-        if !self.config.git_enabled {
+        if !self.git_manager.is_enabled() {
             return Ok(());
         }
 
         // Pull changes from remote
-        if let Err(e) = self.git_manager.pull_changes() {
-            eprintln!("Git pull failed: {}", e);
-        } else {
-            // Refresh the file tree after pulling changes
-            let expanded_dirs = self.file_tree.get_expansion_state();
-            let selected_path = self.file_tree.get_selected_path().map(|p| p.clone());
-            self.file_tree.refresh_with_state(expanded_dirs, selected_path)?;
-            self.load_current_file_content()?;
+        self.start_git_task(GitOperation::Pull);
+
+        Ok(())
+    }
+
+    fn start_git_task(&mut self, operation: GitOperation) {
+        if let Some(task) = &self.git_task {
+            self.status_message = Some(format!("⚠ {} is already in progress", task.operation.label()));
+            return;
+        }
+
+        let mut git_manager = self.git_manager.clone();
+        let (progress_sender, progress_receiver) = mpsc::channel();
+        git_manager.report_progress(progress_sender);
+        let push_on_autocommit = self.config.autocommit_push;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = match operation {
+                GitOperation::Push => GitTaskResult::Push(git_manager.commit_and_push()),
+                GitOperation::CommitStaged => GitTaskResult::Push(git_manager.commit_staged()),
+                GitOperation::Pull => GitTaskResult::Pull(git_manager.pull_changes()),
+                GitOperation::AutoCommit => GitTaskResult::AutoCommit(git_manager.autocommit(push_on_autocommit)),
+            };
+            // The receiver may be gone if the app quit in the meantime
+            let _ = sender.send(result);
+        });
+
+        self.git_task = Some(GitTask {
+            operation,
+            receiver,
+            started: Instant::now(),
+            progress_receiver,
+            progress: None,
+        });
+    }
+
+    /// Commit changes in the background once the autocommit interval has passed
+    fn maybe_autocommit(&mut self) {
+        let interval = match self.config.autocommit_interval_secs {
+            Some(secs) if secs > 0 => Duration::from_secs(secs),
+            _ => return,
+        };
+        if !self.git_manager.is_enabled() || self.read_only || self.git_task.is_some() {
+            return;
+        }
+        if self.last_autocommit_check.elapsed() < interval {
+            return;
+        }
+        self.last_autocommit_check = Instant::now();
+
+        let has_changes = self.git_manager
+            .get_status()
+            .map(|status| status.has_changes())
+            .unwrap_or(false);
+        if has_changes {
+            self.start_git_task(GitOperation::AutoCommit);
+        }
+    }
+
+    fn poll_git_task(&mut self) -> Result<()> {
+        if let Some(task) = &mut self.git_task {
+            while let Ok(progress) = task.progress_receiver.try_recv() {
+                task.progress = Some(progress);
+            }
+        }
+        let result = match &self.git_task {
+            Some(task) => match task.receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => {
+                    self.git_task = None;
+                    self.quit_after_git_task = false;
+                    self.status_message = Some("⚠ Git operation stopped unexpectedly".to_string());
+                    return Ok(());
+                }
+            },
+            None => return Ok(()),
+        };
+        self.git_task = None;
+        self.git_manager.invalidate_status_cache();
+
+        match result {
+            GitTaskResult::Push(Ok(outcome)) => {
+                self.status_message = Some(outcome.message());
+                if outcome != CommitOutcome::NoChanges {
+                    self.note_placeholder_author();
+                }
+                // Stay open so the user can pull before quitting
+                if self.quit_after_git_task && !matches!(outcome, CommitOutcome::BehindRemote(_)) {
+                    self.should_quit = true;
+                }
+            }
+            GitTaskResult::Push(Err(e)) => self.report_git_error("Git push failed", &e, true),
+            GitTaskResult::Pull(Ok(outcome)) => {
+                self.status_message = Some(outcome.message());
+                if outcome == PullOutcome::Merged {
+                    self.note_placeholder_author();
+                }
+                self.invalidate_vault_index();
+
+                // Refresh the file tree after pulling changes
+                let expanded_dirs = self.file_tree.get_expansion_state();
+                let selected_path = self.file_tree.get_selected_path().cloned();
+                self.file_tree.refresh_with_state(expanded_dirs, selected_path)?;
+                self.load_current_file_content()?;
+            }
+            GitTaskResult::Pull(Err(e)) => self.report_git_error("Git pull failed", &e, true),
+            GitTaskResult::AutoCommit(Ok(outcome)) => {
+                // Stay quiet on success; the top bar shows the last autocommit time
+                if outcome != CommitOutcome::NoChanges {
+                    self.last_autocommit = Some(chrono::Local::now());
+                    if self.git_manager.uses_placeholder_author() {
+                        self.status_message = None;
+                        self.note_placeholder_author();
+                    }
+                }
+            }
+            // Autocommits run unasked, so don't cover what the user is doing
+            GitTaskResult::AutoCommit(Err(e)) => self.report_git_error("Autocommit failed", &e, false),
         }
+        self.quit_after_git_task = false;
 
         Ok(())
     }
 
+    /// Say so after a commit made under the placeholder author, which would otherwise go unnoticed
+    fn note_placeholder_author(&mut self) {
+        if !self.git_manager.uses_placeholder_author() {
+            return;
+        }
+        let author = format!(
+            "as '{} <{}>', set your Git username/email in config (c)",
+            git::PLACEHOLDER_AUTHOR.0,
+            git::PLACEHOLDER_AUTHOR.1
+        );
+        self.status_message = Some(match self.status_message.take() {
+            Some(message) => format!("{}, {}", message, author),
+            None => format!("⚠ Committed {}", author),
+        });
+    }
+
+    /// Keep the whole error for the `!` overlay and sum it up in the status bar
+    ///
+    /// Git errors often run over several lines, e.g. the authentication hints
+    /// after a failed push, which the status bar cannot show.
+    fn report_git_error(&mut self, what: &str, error: &anyhow::Error, show: bool) {
+        let detail = format!("{:#}", error);
+        let summary = detail.lines().next().unwrap_or_default().trim_end_matches(['.', ' ']).to_string();
+        self.status_message = Some(if detail.lines().count() > 1 || show {
+            format!("✗ {}: {} (! for details)", what, summary)
+        } else {
+            format!("✗ {}: {}", what, summary)
+        });
+        self.git_error = Some(format!("{}\n\n{}", what, detail));
+        self.show_git_error = show;
+    }
+
+    /// Whether each tree entry has uncommitted changes, in tree order
+    fn tree_change_markers(&self) -> Vec<bool> {
+        if !self.git_manager.is_enabled() {
+            return Vec::new();
+        }
+        match self.git_manager.changed_paths() {
+            // A folder is marked when anything inside it changed
+            Ok(changed) => self.file_tree
+                .get_item_paths()
+                .into_iter()
+                .map(|path| changed.contains(path))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     fn ui(&mut self, f: &mut Frame) {
+        self.image_area = None;
         let main_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -771,27 +3569,69 @@ impl App {
             self.render_config_screen(f, main_chunks[1]);
         } else if self.mode == AppMode::Rename {
             self.render_rename_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::CommandPalette {
+            self.render_command_palette_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::ExportVault {
+            self.render_export_vault_screen(f, main_chunks[1]);
         } else if self.mode == AppMode::DeleteConfirm {
             self.render_delete_confirm_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::DiscardConfirm {
+            self.render_discard_confirm_screen(f, main_chunks[1]);
         } else if self.mode == AppMode::LineNavigation {
             self.render_line_navigation_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::QuitConfirm {
+            self.render_quit_confirm_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::Tags {
+            self.render_tags_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::Conflicts {
+            self.render_conflicts_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::Staging {
+            self.render_staging_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::Backlinks {
+            self.render_backlinks_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::BrokenLinks {
+            self.render_broken_links_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::VaultPicker {
+            self.render_vault_picker_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::TemplatePicker {
+            self.render_template_picker_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::IdentityPrompt {
+            self.render_identity_prompt_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::SplitPreview {
+            self.render_split_preview_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::PassphrasePrompt {
+            self.render_passphrase_prompt_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::Editor {
+            self.render_editor_screen(f, main_chunks[1]);
+        } else if self.mode == AppMode::Search {
+            self.render_search_screen(f, main_chunks[1]);
         } else {
+            let tree_width = self.config.tree_width();
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .constraints([Constraint::Percentage(tree_width), Constraint::Percentage(100 - tree_width)])
                 .split(main_chunks[1]);
+            // Notes are wrapped to leave room for the line numbers
+            let gutter_width = self.line_number_gutter_width();
+            self.content_width = chunks[1].width.saturating_sub(2 + gutter_width);
+            self.tree_height = chunks[0].height.saturating_sub(2);
 
             // Create the items vector first
             let file_items = self.file_tree.get_items();
+            let changed = self.tree_change_markers();
             let items: Vec<ListItem> = file_items
                 .iter()
-                .map(|item| {
+                .enumerate()
+                .map(|(i, item)| {
                     let style = if item.contains("▶") || item.contains("▼") {
                         // Directory
                         Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
-                    } else if item.ends_with(".md") {
+                    } else if file_tree::is_markdown_file(Path::new(item)) {
                         // Markdown file
                         Style::default().fg(Color::Green)
+                    } else if crypto::is_encrypted_note(Path::new(item)) {
+                        // Encrypted note
+                        Style::default().fg(Color::Yellow)
                     } else if item.ends_with(".png") || item.ends_with(".jpg") || item.ends_with(".jpeg") || 
                              item.ends_with(".gif") || item.ends_with(".bmp") || item.ends_with(".webp") || 
                              item.ends_with(".svg") {
@@ -801,25 +3641,45 @@ impl App {
                         // Other files
                         Style::default().fg(Color::Gray)
                     };
-                    ListItem::new(item.as_str()).style(style)
+                    ListItem::new(tree_item_line(item, changed.get(i).copied().unwrap_or(false))).style(style)
                 })
                 .collect();
 
+            let files_title = match &self.active_tag {
+                Some(tag) => format!("Files - #{}", tag),
+                None => "Files".to_string(),
+            };
             let list = List::new(items)
-                .block(Block::default().title("Files").borders(Borders::ALL))
+                .block(Block::default().title(files_title).borders(Borders::ALL))
                 .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
-                .highlight_symbol("> ");
+                .highlight_symbol("> ")
+                .scroll_padding(TREE_SCROLL_PADDING);
 
             f.render_stateful_widget(list, chunks[0], self.file_tree.get_state_mut());
             
             // Render content
+            let title_width = chunks[1].width.saturating_sub(2) as usize;
             let title = if let Some(file_path) = &self.current_file {
-                format!("Content - {}", file_path.file_name().unwrap().to_string_lossy())
+                match &self.current_file_info {
+                    Some(info) => {
+                        // The size and date go first when even the file name would not fit beside them
+                        let info = format!(" ({})", info);
+                        let width = title_width.saturating_sub("Content - ".len());
+                        let name_width = file_path.file_name().map_or(0, |name| name.to_string_lossy().chars().count());
+                        if width >= name_width + info.chars().count() {
+                            format!("Content - {}{}", self.breadcrumb(file_path, width - info.chars().count()), info)
+                        } else {
+                            format!("Content - {}", self.breadcrumb(file_path, width))
+                        }
+                    }
+                    None => format!("Content - {}", self.breadcrumb(file_path, title_width.saturating_sub("Content - ".len()))),
+                }
             } else {
                 "Content".to_string()
             };
 
             // Check what type of content to render
+            let is_markdown = self.current_is_markdown();
             if let Some(file_path) = &self.current_file {
                 if FileTree::is_image_file(file_path) && self.current_image.is_some() && self.image_state.is_some() {
                     // Render image
@@ -830,26 +3690,58 @@ impl App {
                         f.render_widget(block, chunks[1]);
                         
                         // Then render the image inside
+                        self.image_area = Some(inner);
                         let image_widget = StatefulImage::new(None);
                         f.render_stateful_widget(image_widget, inner, state);
                     }
-                } else if file_path.extension().and_then(|s| s.to_str()) == Some("md") && !self.current_content.is_empty() {
-                    // Parse and render markdown
-                    match self.markdown_renderer.parse_markdown(&self.current_content) {
-                        Ok(elements) => {
-                            let rendered_text = self.markdown_renderer.render_to_text(&elements);
-                            let paragraph = Paragraph::new(rendered_text)
-                                .block(Block::default().title(title.as_str()).borders(Borders::ALL))
-                                .wrap(Wrap { trim: true })
-                                .scroll((0, 0));
-                            f.render_widget(paragraph, chunks[1]);
+                } else if is_markdown && self.rendered_conflicts {
+                    let paragraph = Paragraph::new(Text::from(self.rendered_lines.clone()))
+                        .block(Block::default().title(format!("{} - merge conflict", title)).borders(Borders::ALL))
+                        .wrap(Wrap { trim: false });
+                    f.render_widget(paragraph, chunks[1]);
+                } else if is_markdown && !self.current_content.is_empty() {
+                    // Markdown was parsed and rendered when the note was loaded
+                    let block = Block::default().title(title.as_str()).borders(Borders::ALL);
+                    let inner = block.inner(chunks[1]);
+                    let lines: Vec<Line> = if gutter_width > 0 {
+                        self.rendered_lines
+                            .iter()
+                            .enumerate()
+                            .map(|(i, line)| {
+                                let mut spans = vec![self.line_number_gutter(i, gutter_width as usize)];
+                                spans.extend(line.spans.iter().cloned());
+                                Line::from(spans).style(line.style)
+                            })
+                            .collect()
+                    } else {
+                        self.rendered_lines.clone()
+                    };
+                    // Keep leading spaces so code blocks stay indented; lines are already wrapped
+                    let paragraph = Paragraph::new(Text::from(lines))
+                        .block(block)
+                        .wrap(Wrap { trim: false })
+                        .scroll((0, 0));
+                    f.render_widget(paragraph, chunks[1]);
+
+                    // Draw images over the room the renderer left below their placeholders
+                    for (line, path) in self.rendered_images.iter().filter(|_| self.inline_images) {
+                        let top = *line as u16;
+                        if top >= inner.height {
+                            break;
                         }
-                        Err(_) => {
-                            // Fallback to plain text if markdown parsing fails
-                            let paragraph = Paragraph::new(self.current_content.as_str())
-                                .block(Block::default().title(title.as_str()).borders(Borders::ALL))
-                                .wrap(Wrap { trim: true });
-                            f.render_widget(paragraph, chunks[1]);
+                        let picker = &mut self.image_picker;
+                        let state = self.inline_image_states.entry(path.clone()).or_insert_with(|| {
+                            let image = image::open(path).ok()?;
+                            picker.as_mut().map(|picker| picker.new_resize_protocol(image))
+                        });
+                        if let Some(state) = state {
+                            let area = Rect {
+                                x: inner.x + gutter_width,
+                                y: inner.y + top,
+                                width: inner.width.saturating_sub(gutter_width),
+                                height: (INLINE_IMAGE_ROWS as u16).min(inner.height - top),
+                            };
+                            f.render_stateful_widget(StatefulImage::new(None), area, state);
                         }
                     }
                 } else {
@@ -859,6 +3751,17 @@ impl App {
                         .wrap(Wrap { trim: true });
                     f.render_widget(paragraph, chunks[1]);
                 }
+            } else if file_items.is_empty() && !self.file_tree.is_filtered() {
+                // Empty notes directory: explain how to get started
+                let hint = format!(
+                    "No notes yet in {}\n\nPress 'n' to create your first note\nPress 'd' to create a folder\nPress 'c' to choose a different notes directory",
+                    self.config.root_directory.display()
+                );
+                let paragraph = Paragraph::new(hint)
+                    .block(Block::default().title("Welcome to RNotes").borders(Borders::ALL))
+                    .style(Style::default().fg(Color::Yellow))
+                    .wrap(Wrap { trim: true });
+                f.render_widget(paragraph, chunks[1]);
             } else {
                 // No file selected
                 let paragraph = Paragraph::new("No file selected")
@@ -870,6 +3773,55 @@ impl App {
 
         // Render footer
         self.render_footer(f, main_chunks[2]);
+
+        if self.show_help {
+            self.render_help_overlay(f, main_chunks[1]);
+        } else if self.show_git_error {
+            self.render_git_error_overlay(f, main_chunks[1]);
+        }
+    }
+
+    fn render_git_error_overlay(&self, f: &mut Frame, area: Rect) {
+        let detail = self.git_error.as_deref().unwrap_or_default();
+        let width = area.width.saturating_sub(4).min(90);
+        // Room for the wrapped text and the borders
+        let inner_width = width.saturating_sub(2).max(1) as usize;
+        let lines: usize = detail.lines().map(|line| line.chars().count().max(1).div_ceil(inner_width)).sum();
+        let height = (lines as u16 + 2).min(area.height);
+        let overlay = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let error = Paragraph::new(detail)
+            .block(Block::default().title("Git error - Esc:Close, ! shows it again").borders(Borders::ALL))
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: false });
+        f.render_widget(ratatui::widgets::Clear, overlay);
+        f.render_widget(error, overlay);
+    }
+
+    fn render_help_overlay(&mut self, f: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(4).min(80);
+        let height = area.height.saturating_sub(2);
+        let overlay = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let lines = help::help_lines(self.config.command_palette_key());
+        let max_scroll = (lines.len() as u16).saturating_sub(height.saturating_sub(2));
+        self.help_scroll = self.help_scroll.min(max_scroll);
+
+        let help = Paragraph::new(Text::from(lines))
+            .block(Block::default().title("Key Bindings - j/k:Scroll | ?/Esc:Close").borders(Borders::ALL))
+            .scroll((self.help_scroll, 0));
+        f.render_widget(ratatui::widgets::Clear, overlay);
+        f.render_widget(help, overlay);
     }
 
 
@@ -877,15 +3829,11 @@ impl App {
     fn render_config_screen(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
+            .margin(1)
             .constraints([
                 Constraint::Length(3), // Title
-                Constraint::Length(3), // Root directory
-                Constraint::Length(3), // Editor
-                Constraint::Length(3), // Git enabled
-                Constraint::Length(3), // Git repository
-                Constraint::Length(3), // Git username
-                Constraint::Length(3), // Git email
-                Constraint::Min(1),    // Help
+                Constraint::Min(1),    // Settings
+                Constraint::Length(3), // Help
             ])
             .split(area);
 
@@ -895,107 +3843,48 @@ impl App {
             .style(Style::default().fg(Color::Yellow));
         f.render_widget(title, chunks[0]);
 
-        // Root directory field
-        let root_dir_style = if self.config_field == 0 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let root_dir_content = if self.config_field == 0 {
-            self.config_input.as_str()
-        } else {
-            &self.config.root_directory.to_string_lossy()
-        };
-        let root_dir = Paragraph::new(root_dir_content)
-            .block(Block::default().title("Root Directory").borders(Borders::ALL))
-            .style(root_dir_style);
-        f.render_widget(root_dir, chunks[1]);
-
-        // Editor field
-        let editor_style = if self.config_field == 1 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let editor_content = if self.config_field == 1 {
-            self.config_input.as_str()
-        } else {
-            self.config.editor.as_str()
-        };
-        let editor = Paragraph::new(editor_content)
-            .block(Block::default().title("Editor").borders(Borders::ALL))
-            .style(editor_style);
-        f.render_widget(editor, chunks[2]);
-
-        // Git enabled field
-        let git_enabled_style = if self.config_field == 2 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let git_enabled_content = if self.config_field == 2 {
-            self.config_input.as_str()
-        } else {
-            if self.config.git_enabled { "true" } else { "false" }
-        };
-        let git_enabled = Paragraph::new(git_enabled_content)
-            .block(Block::default().title("Git Enabled (any key to toggle)").borders(Borders::ALL))
-            .style(git_enabled_style);
-        f.render_widget(git_enabled, chunks[3]);
-
-        // Git repository field
-        let git_repo_style = if self.config_field == 3 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let git_repo_content = if self.config_field == 3 {
-            self.config_input.as_str()
-        } else {
-            self.config.git_repository.as_deref().unwrap_or("")
-        };
-        let git_repo = Paragraph::new(git_repo_content)
-            .block(Block::default().title("Git Repository URL").borders(Borders::ALL))
-            .style(git_repo_style);
-        f.render_widget(git_repo, chunks[4]);
-
-        // Git username field
-        let git_username_style = if self.config_field == 4 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let git_username_content = if self.config_field == 4 {
-            self.config_input.as_str()
-        } else {
-            self.config.git_username.as_deref().unwrap_or("")
-        };
-        let git_username = Paragraph::new(git_username_content)
-            .block(Block::default().title("Git Username").borders(Borders::ALL))
-            .style(git_username_style);
-        f.render_widget(git_username, chunks[5]);
-
-        // Git email field
-        let git_email_style = if self.config_field == 5 {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let git_email_content = if self.config_field == 5 {
-            self.config_input.as_str()
-        } else {
-            self.config.git_email.as_deref().unwrap_or("")
-        };
-        let git_email = Paragraph::new(git_email_content)
-            .block(Block::default().title("Git Email").borders(Borders::ALL))
-            .style(git_email_style);
-        f.render_widget(git_email, chunks[6]);
+        let label_width = self.config_fields.iter().map(|field| field.label.chars().count()).max().unwrap_or(0);
+        let items: Vec<ListItem> = self.config_fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let value = settings::get(&self.config_draft, &field.key);
+                let content = match &field.kind {
+                    FieldKind::Bool => {
+                        if value.and_then(|value| value.as_bool()).unwrap_or(false) { "[x]" } else { "[ ]" }.to_string()
+                    }
+                    FieldKind::Choice(_) => format!("◀ {} ▶", value.map(settings::display).unwrap_or_default()),
+                    FieldKind::Text if i == self.config_field && field.secret => self.config_input.masked_with_cursor('*', '▏'),
+                    FieldKind::Text if i == self.config_field => self.config_input.with_cursor('▏'),
+                    FieldKind::Text => {
+                        let text = value.map(settings::display).unwrap_or_default();
+                        if field.secret && !text.is_empty() { "********".to_string() } else { text }
+                    }
+                };
+                let style = if i == self.config_field {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<width$}  ", field.label, width = label_width), Style::default().fg(Color::Cyan)),
+                    Span::raw(content),
+                ]))
+                .style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().title("Settings").borders(Borders::ALL))
+            .highlight_symbol("> ");
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.config_field));
+        f.render_stateful_widget(list, chunks[1], &mut state);
 
         // Help text
-        let help = Paragraph::new("Tab: Next field | Enter: Save & Exit | Esc: Cancel")
+        let help = Paragraph::new("↑/↓ Tab: Next field | ←/→ Home/End: Move cursor | Space: Toggle/Choose | Enter: Save & Exit | Esc: Cancel")
             .block(Block::default().borders(Borders::ALL))
             .style(Style::default().fg(Color::Gray));
-        f.render_widget(help, chunks[7]);
+        f.render_widget(help, chunks[2]);
     }
 
     fn render_top_bar(&self, f: &mut Frame, area: Rect) {
@@ -1024,14 +3913,25 @@ impl App {
         let root_dir = self.config.root_directory.to_string_lossy();
         
         // Add Git status if enabled
-        let git_status = if self.config.git_enabled {
+        let git_status = if self.git_manager.is_enabled() {
             match self.git_manager.get_status() {
                 Ok(status) => {
-                    if status.has_changes() {
+                    let mut text = if status.has_changes() {
                         format!(" | Git: {} changes", status.modified + status.untracked)
                     } else {
                         " | Git: ✓".to_string()
+                    };
+                    if status.ahead > 0 {
+                        text.push_str(&format!(" ↑{}", status.ahead));
+                    }
+                    if status.behind > 0 {
+                        text.push_str(&format!(" ↓{}", status.behind));
                     }
+                    if let Ok(Some(history)) = self.git_manager.history() {
+                        let elapsed = chrono::Utc::now().timestamp() - history.last_commit_time;
+                        text.push_str(&format!(" | {} commits, last {}", history.commits, format_time_ago(elapsed)));
+                    }
+                    text
                 }
                 Err(_) => " | Git: ⚠".to_string(),
             }
@@ -1039,8 +3939,37 @@ impl App {
             String::new()
         };
         
-        let status_line = format!(" RNotes - {} | Current: {} | Root: {}{} ", 
-                                current_file_name, current_context, root_dir, git_status);
+        // Warn when the note grows beyond the configured word limit
+        let word_limit = match self.config.max_words {
+            Some(max_words) if self.current_word_count > max_words => {
+                format!(" | ⚠ {}/{} words", self.current_word_count, max_words)
+            }
+            _ => String::new(),
+        };
+        
+        let autocommit = match self.last_autocommit {
+            Some(time) => format!(" | Autocommit: {}", time.format("%H:%M")),
+            None => String::new(),
+        };
+
+        let indexing = match &self.search_index_build {
+            Some(IndexBuild { indexed, total: Some(total), .. }) => format!(" | Indexing {}/{}", indexed, total),
+            Some(_) => " | Indexing".to_string(),
+            None => String::new(),
+        };
+        
+        let fetching = match self.git_task.as_ref().and_then(|task| task.progress) {
+            Some(progress) => format!(
+                " | Fetching {}/{} objects, {}",
+                progress.received_objects,
+                progress.total_objects,
+                format_file_size(progress.received_bytes as u64)
+            ),
+            None => String::new(),
+        };
+        
+        let status_line = format!(" RNotes - {} | Current: {} | Root: {}{}{}{}{}{} ", 
+                                current_file_name, current_context, root_dir, git_status, fetching, autocommit, indexing, word_limit);
         
         let paragraph = Paragraph::new(status_line.as_str())
             .style(Style::default().bg(Color::Blue).fg(Color::White));
@@ -1048,141 +3977,700 @@ impl App {
         f.render_widget(paragraph, area);
     }
 
-    fn render_footer(&self, f: &mut Frame, area: Rect) {
-        let footer_text = match self.mode {
-            AppMode::Normal => {
-                // Check if current selection is an image to show appropriate help
-                let is_image = self.file_tree.get_selected_path()
-                    .map(|p| FileTree::is_image_file(&p))
-                    .unwrap_or(false);
-                
-                if self.config.git_enabled {
-                    if is_image {
-                        " j/k:Navigate | y:Copy to clipboard | i:Edit | n:New | r:Rename | x:Delete | d:Folder | c:Config | g:Push | p:Pull | q:Quit "
-                    } else {
-                        " j/k:Navigate | Space/→:Expand/Lines | i:Edit | n:New | r:Rename | x:Delete | d:Folder | c:Config | g:Push | p:Pull | q:Quit "
-                    }
+    fn render_footer(&self, f: &mut Frame, area: Rect) {
+        let footer_text = match self.mode {
+            AppMode::Normal => {
+                // Check if current selection is an image to show appropriate help
+                let is_image = self.file_tree.get_selected_path()
+                    .map(|p| FileTree::is_image_file(&p))
+                    .unwrap_or(false);
+                
+                if self.read_only {
+                    if is_image {
+                        " READ-ONLY | j/k:Navigate | ?:Help | y/Y:Copy path | o/O:File manager | c:Config | p:Pull | q:Quit "
+                    } else {
+                        " READ-ONLY | j/k:Navigate | ?:Help | Space/→:Expand/Lines | /:Search | t:Tags | b:Backlinks | B:Broken links | v:Split view | </>:Tree width | R:Relative paths | #:Note counts | L:Line numbers | y/Y:Copy path | o/O:File manager | c:Config | p:Pull | q:Quit "
+                    }
+                } else if self.git_manager.is_enabled() {
+                    if is_image {
+                        " j/k:Navigate | ?:Help | y/Y:Copy path | o/O:File manager | i:Edit | n:New | r:Rename | x:Delete | d:Folder | C:Duplicate | [/]:Reorder | T:Journal | c:Config | g:Push | p:Pull | q:Quit "
+                    } else {
+                        " j/k:Navigate | ?:Help | Space/→:Expand/Lines | i:Edit | n:New | r:Rename | x:Delete | d:Folder | C:Duplicate | [/]:Reorder | T:Journal | e/E:HTML/PDF | /:Search | t:Tags | b:Backlinks | B:Broken links | v:Split view | </>:Tree width | R:Relative paths | #:Note counts | L:Line numbers | y/Y:Copy path | o/O:File manager | K:Encrypt | c:Config | I:.gitignore | u:Discard changes | M:Conflicts | S:Stage | g:Push | p:Pull | q:Quit "
+                    }
+                } else {
+                    if is_image {
+                        " j/k:Navigate | ?:Help | y/Y:Copy path | o/O:File manager | i:Edit | n:New | r:Rename | x:Delete | d:Folder | C:Duplicate | [/]:Reorder | T:Journal | c:Config | q:Quit "
+                    } else {
+                        " j/k:Navigate | ?:Help | Space/→:Expand/Lines | i:Edit | n:New | r:Rename | x:Delete | d:Folder | C:Duplicate | [/]:Reorder | T:Journal | e/E:HTML/PDF | /:Search | t:Tags | b:Backlinks | B:Broken links | v:Split view | </>:Tree width | R:Relative paths | #:Note counts | L:Line numbers | y/Y:Copy path | o/O:File manager | c:Config | q:Quit "
+                    }
+                }
+            }
+            AppMode::Config => " Tab:Next field | Enter:Save | Esc:Cancel ",
+            AppMode::CommandPalette => " Type a command | Tab:Complete | ↑/↓:Choose | Enter:Run | Esc:Cancel ",
+            AppMode::ExportVault => " Type a path | ←/→ Home/End:Move cursor | Enter:Export | Esc:Cancel ",
+            AppMode::Rename => " Type new name | ←/→ Home/End:Move cursor | Enter:Confirm | Esc:Cancel ",
+            AppMode::DeleteConfirm => " y:Yes, delete | n:No, cancel | Esc:Cancel ",
+            AppMode::DiscardConfirm => " y:Yes, discard changes | n:No, cancel | Esc:Cancel ",
+            AppMode::LineNavigation if self.read_only && self.git_manager.is_enabled() => " READ-ONLY | j/k:Navigate lines | Enter:Follow link | y/Y:Copy line/note | r:Copy as text | z:Fold | B:Blame | ←/Esc:Back ",
+            AppMode::LineNavigation if self.read_only => " READ-ONLY | j/k:Navigate lines | Enter:Follow link | y/Y:Copy line/note | r:Copy as text | z:Fold | ←/Esc:Back ",
+            AppMode::LineNavigation if self.git_manager.is_enabled() => " j/k:Navigate lines | Enter:Follow link | y/Y:Copy line/note | r:Copy as text | z:Fold | B:Blame | i:Edit | ←/Esc:Back ",
+            AppMode::LineNavigation => " j/k:Navigate lines | Enter:Follow link | y/Y:Copy line/note | r:Copy as text | z:Fold | i:Edit | ←/Esc:Back ",
+            AppMode::QuitConfirm => " c:Commit & push, then quit | q:Quit anyway | n/Esc:Cancel ",
+            AppMode::Tags => " j/k:Navigate tags | Enter:Filter notes by tag | Esc:Back ",
+            AppMode::Backlinks => " j/k:Navigate | Enter:Open note | Esc:Back ",
+            AppMode::BrokenLinks => " j/k:Navigate | Enter:Open note at the link | Esc:Back ",
+            AppMode::Conflicts => " j/k:Navigate | Enter:Edit & mark resolved | Esc:Back ",
+            AppMode::Staging => " j/k:Navigate | Space:Stage/unstage | c:Commit staged & push | Esc:Back ",
+            AppMode::TemplatePicker => " j/k:Navigate | Enter:Create note | Esc:Cancel ",
+            AppMode::VaultPicker => " j/k:Navigate | Enter:Switch vault | Esc:Cancel ",
+            AppMode::IdentityPrompt => " y:Use global git identity | n/Esc:Skip ",
+            AppMode::SplitPreview if self.read_only => " READ-ONLY | j/k:Scroll | PgUp/PgDn:Page | Home/End:Top/Bottom | v/Esc:Back ",
+            AppMode::PassphrasePrompt => " Type passphrase | Enter:Unlock | Esc:Cancel ",
+            AppMode::SplitPreview => " j/k:Scroll | PgUp/PgDn:Page | Home/End:Top/Bottom | i:Edit | v/Esc:Back ",
+            AppMode::Search => " Type to search | ↑/↓:Select | Enter:Open at the line | Esc:Back ",
+            AppMode::Editor => " Type to edit | Arrows/Home/End/PgUp/PgDn:Move | Ctrl+S:Save | Esc:Close ",
+        };
+
+        // Put the position first so it stays visible on narrow terminals
+        let footer_text = if self.mode == AppMode::LineNavigation {
+            let more = if self.lazy_render.as_ref().is_some_and(|lazy| !lazy.is_complete()) { "+" } else { "" };
+            format!(" Line {}/{}{} |{}", self.line_selection + 1, self.rendered_lines.len(), more, footer_text)
+        } else if let Some(editor) = self.text_editor.as_ref().filter(|_| self.mode == AppMode::Editor) {
+            let (row, col) = editor.cursor();
+            format!(" Ln {}, Col {} |{}", row + 1, col + 1, footer_text)
+        } else {
+            footer_text.to_string()
+        };
+        
+        // Progress and status messages take precedence over the help text
+        let paragraph = if let Some(task) = &self.git_task {
+            Paragraph::new(format!(" {} {}... ", spinner_frame(task.started), task.operation.label()))
+                .style(Style::default().bg(Color::Yellow).fg(Color::Black))
+        } else if let Some(message) = &self.status_message {
+            Paragraph::new(format!(" {} ", message))
+                .style(Style::default().bg(Color::Yellow).fg(Color::Black))
+        } else {
+            Paragraph::new(footer_text)
+                .style(Style::default().bg(Color::Gray).fg(Color::Black))
+        };
+        
+        f.render_widget(paragraph, area);
+    }
+
+    fn render_rename_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        // Title
+        let (current_name, item_type) = if let Some(path) = self.file_tree.get_selected_path() {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let type_str = if path.is_dir() { "Folder" } else { "File" };
+            (name, type_str)
+        } else {
+            ("No item selected".to_string(), "Item")
+        };
+        
+        let title = Paragraph::new(format!("Rename {}: {}", item_type, current_name))
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(title, chunks[0]);
+
+        // Input field
+        let input_title = if item_type == "File" {
+            "New Name (add an extension to change it)"
+        } else {
+            "New Name"
+        };
+        render_line_input(f, chunks[1], input_title, &self.rename_input);
+    }
+
+    fn render_command_palette_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .split(area);
+
+        render_line_input(f, chunks[0], "Command", &self.command_input);
+
+        let (name, _) = commands::parse(self.command_input.as_str());
+        let matches = commands::matching(name);
+        let name_width = commands::COMMANDS.iter().map(|command| command.name.len()).max().unwrap_or(0);
+        let items: Vec<ListItem> = matches
+            .iter()
+            .map(|command| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:<width$}", command.name, width = name_width), Style::default().fg(Color::Green)),
+                    Span::styled(format!("  {}  ", command.key), Style::default().fg(Color::Cyan)),
+                    Span::raw(command.description),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(format!("Commands ({})", matches.len())).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(if matches.is_empty() { None } else { Some(self.command_selection) });
+        f.render_stateful_widget(list, chunks[1], &mut state);
+    }
+
+    fn render_export_vault_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        let title = Paragraph::new(format!("Export Vault: {}", self.config.root_directory.display()))
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(title, chunks[0]);
+
+        render_line_input(f, chunks[1], "Save As", &self.export_vault_input);
+
+        let help = Paragraph::new("Every note, in file tree order, goes into one document with a table of contents.\nA path ending in .html writes HTML; anything else writes markdown.\nThe path must be absolute (~ is your home folder) and outside the vault.")
+            .style(Style::default().fg(Color::Gray));
+        f.render_widget(help, chunks[2]);
+    }
+
+    fn render_delete_confirm_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(if self.delete_counts.is_some() { 11 } else { 9 }),
+                Constraint::Length(if self.delete_preview.is_empty() { 0 } else { DELETE_PREVIEW_LINES as u16 + 2 }),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        // Confirmation message
+        let (target_name, item_type) = if let Some(path) = &self.delete_target {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let type_str = if path.is_dir() { "folder" } else { "file" };
+            (name, type_str)
+        } else {
+            ("Unknown".to_string(), "item")
+        };
+        
+        let contents = match self.delete_counts {
+            Some((files, folders)) => format!(
+                "\n\nThis will delete {} file{} and {} folder{}",
+                files,
+                if files == 1 { "" } else { "s" },
+                folders,
+                if folders == 1 { "" } else { "s" }
+            ),
+            None => String::new(),
+        };
+        let warning_text = format!(
+            "⚠️  DELETE CONFIRMATION  ⚠️\n\nAre you sure you want to delete this {}?\n\n📁 {}{}\n\nThis action cannot be undone!",
+            item_type, target_name, contents
+        );
+        
+        let warning = Paragraph::new(warning_text.as_str())
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true });
+        f.render_widget(warning, chunks[0]);
+
+        if !self.delete_preview.is_empty() {
+            let preview_lines: Vec<Line> = self.delete_preview
+                .iter()
+                .map(|line| Line::from(line.clone()))
+                .collect();
+            let preview = Paragraph::new(preview_lines)
+                .block(Block::default().borders(Borders::ALL).title("Preview"))
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(preview, chunks[1]);
+        }
+
+        // Instructions
+        let instructions = Paragraph::new("Press 'y' to DELETE or 'n' to CANCEL")
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(instructions, chunks[2]);
+    }
+
+    fn render_discard_confirm_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(7),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        let target_name = self.discard_target
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        let warning_text = format!(
+            "⚠️  DISCARD CHANGES  ⚠️\n\nRestore this file to its last committed version?\n\n📄 {}\n\nAll uncommitted edits to it will be lost!",
+            target_name
+        );
+
+        let warning = Paragraph::new(warning_text.as_str())
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true });
+        f.render_widget(warning, chunks[0]);
+
+        // Instructions
+        let instructions = Paragraph::new("Press 'y' to DISCARD or 'n' to CANCEL")
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(instructions, chunks[1]);
+    }
+
+    fn render_quit_confirm_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(7),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        let status = self.git_manager.get_status().unwrap_or_default();
+
+        let warning_text = format!(
+            "⚠️  UNCOMMITTED CHANGES  ⚠️\n\nYour notes have changes that are not synced:\n{} modified, {} untracked, {} staged\n\nQuitting now will leave them only on this machine.",
+            status.modified, status.untracked, status.staged
+        );
+
+        let warning = Paragraph::new(warning_text.as_str())
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow))
+            .wrap(Wrap { trim: true });
+        f.render_widget(warning, chunks[0]);
+
+        // Instructions
+        let instructions = Paragraph::new("Press 'c' to COMMIT & PUSH, 'q' to QUIT ANYWAY or 'n' to CANCEL")
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(instructions, chunks[1]);
+    }
+
+    fn render_tags_screen(&self, f: &mut Frame, area: Rect) {
+        if self.tag_entries.is_empty() {
+            let paragraph = Paragraph::new("No #tags found in your notes")
+                .block(Block::default().title("Tags").borders(Borders::ALL))
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(paragraph, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self.tag_entries
+            .iter()
+            .map(|(tag, count)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("#{}", tag), Style::default().fg(Color::Cyan)),
+                    Span::styled(format!(" ({})", count), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(format!("Tags ({})", self.tag_entries.len())).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.tag_selection));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_search_screen(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .split(area);
+
+        render_line_input(f, chunks[0], "Search notes", &self.search_input);
+
+        let mut title = format!("Results ({})", self.search_results.len());
+        if self.search_index_build.is_some() {
+            title.push_str(" - still indexing, more may appear");
+        }
+        let items: Vec<ListItem> = self.search_results
+            .iter()
+            .map(|hit| {
+                let relative = hit.path.strip_prefix(&self.config.root_directory).unwrap_or(&hit.path);
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}:{}", relative.to_string_lossy(), hit.line + 1), Style::default().fg(Color::Green)),
+                    Span::raw(format!("  {}", hit.text.trim())),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(if self.search_results.is_empty() { None } else { Some(self.search_selection) });
+        f.render_stateful_widget(list, chunks[1], &mut state);
+    }
+
+    fn render_backlinks_screen(&self, f: &mut Frame, area: Rect) {
+        let current_name = self.current_file
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = format!("Backlinks - {} ({})", current_name, self.backlink_entries.len());
+
+        if self.backlink_entries.is_empty() {
+            let paragraph = Paragraph::new("No notes link to this note")
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(paragraph, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self.backlink_entries
+            .iter()
+            .map(|path| {
+                let relative = path.strip_prefix(&self.config.root_directory).unwrap_or(path);
+                ListItem::new(relative.to_string_lossy().to_string()).style(Style::default().fg(Color::Green))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.backlink_selection));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_broken_links_screen(&self, f: &mut Frame, area: Rect) {
+        let title = format!("Broken links ({})", self.broken_link_entries.len());
+
+        if self.broken_link_entries.is_empty() {
+            let paragraph = Paragraph::new("Every link points to a note in the vault")
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(paragraph, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self.broken_link_entries
+            .iter()
+            .map(|link| {
+                let relative = link.source.strip_prefix(&self.config.root_directory).unwrap_or(&link.source);
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}:{}", relative.to_string_lossy(), link.line + 1), Style::default().fg(Color::Green)),
+                    Span::styled(format!("  {}", link.text), Style::default().fg(Color::Red)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.broken_link_selection));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_conflicts_screen(&self, f: &mut Frame, area: Rect) {
+        let title = format!("Merge conflicts ({})", self.conflict_entries.len());
+        let items: Vec<ListItem> = self.conflict_entries
+            .iter()
+            .map(|path| {
+                let relative = path.strip_prefix(&self.config.root_directory).unwrap_or(path);
+                ListItem::new(relative.to_string_lossy().to_string()).style(Style::default().fg(Color::Red))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.conflict_selection));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_staging_screen(&self, f: &mut Frame, area: Rect) {
+        let staged = self.staging_entries.iter().filter(|(_, status)| status.staged).count();
+        let title = format!("Changes ({} staged of {})", staged, self.staging_entries.len());
+        let items: Vec<ListItem> = self.staging_entries
+            .iter()
+            .map(|(path, status)| {
+                // [~] has further edits since it was staged
+                let (checkbox, color) = match (status.staged, status.modified) {
+                    (true, true) => ("[~]", Color::Yellow),
+                    (true, false) => ("[x]", Color::Green),
+                    (false, _) => ("[ ]", Color::Red),
+                };
+                let kind = if status.new {
+                    "new"
+                } else if status.deleted {
+                    "deleted"
                 } else {
-                    if is_image {
-                        " j/k:Navigate | y:Copy to clipboard | i:Edit | n:New | r:Rename | x:Delete | d:Folder | c:Config | q:Quit "
-                    } else {
-                        " j/k:Navigate | Space/→:Expand/Lines | i:Edit | n:New | r:Rename | x:Delete | d:Folder | c:Config | q:Quit "
-                    }
+                    "modified"
+                };
+                let relative = path.strip_prefix(&self.config.root_directory).unwrap_or(path);
+                ListItem::new(format!("{} {:<8} {}", checkbox, kind, relative.to_string_lossy()))
+                    .style(Style::default().fg(color))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.staging_selection));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_template_picker_screen(&self, f: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self.template_entries
+            .iter()
+            .map(|entry| match entry {
+                Some(path) => ListItem::new(path.file_stem().unwrap_or_default().to_string_lossy().to_string())
+                    .style(Style::default().fg(Color::Green)),
+                None => ListItem::new("(default)").style(Style::default().fg(Color::Gray)),
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title("New Note - Choose a Template").borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.template_selection));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_vault_picker_screen(&self, f: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self.config.vaults
+            .iter()
+            .map(|vault| {
+                let text = format!("{} - {}", vault.name, vault.path.display());
+                if vault.path == self.config.root_directory {
+                    ListItem::new(format!("{} (current)", text)).style(Style::default().fg(Color::Yellow))
+                } else {
+                    ListItem::new(text).style(Style::default().fg(Color::Green))
                 }
-            }
-            AppMode::Config => " Tab:Next field | Enter:Save | Esc:Cancel ",
-            AppMode::Rename => " Type new name | Enter:Confirm | Esc:Cancel ",
-            AppMode::DeleteConfirm => " y:Yes, delete | n:No, cancel | Esc:Cancel ",
-            AppMode::LineNavigation => " j/k:Navigate lines | y:Copy line | i:Edit | ←/Esc:Back ",
-        };
-        
-        let paragraph = Paragraph::new(footer_text)
-            .style(Style::default().bg(Color::Gray).fg(Color::Black));
-        
-        f.render_widget(paragraph, area);
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().title("Switch Vault").borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        let mut state = ratatui::widgets::ListState::default();
+        state.select(Some(self.vault_selection));
+        f.render_stateful_widget(list, area, &mut state);
     }
 
-    fn render_rename_screen(&self, f: &mut Frame, area: Rect) {
+    fn render_identity_prompt_screen(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3),
+                Constraint::Length(8),
                 Constraint::Min(1),
             ])
             .split(area);
 
-        // Title
-        let (current_name, item_type) = if let Some(path) = self.file_tree.get_selected_path() {
-            let name = path.file_name().unwrap().to_string_lossy().to_string();
-            let type_str = if path.is_dir() { "Folder" } else { "File" };
-            (name, type_str)
-        } else {
-            ("No item selected".to_string(), "Item")
-        };
-        
-        let title = Paragraph::new(format!("Rename {}: {}", item_type, current_name))
+        let (global_name, global_email) = &self.global_identity;
+        let name = self.config.git_username.as_ref().or(global_name.as_ref()).cloned().unwrap_or_default();
+        let email = self.config.git_email.as_ref().or(global_email.as_ref()).cloned().unwrap_or_default();
+
+        let prompt_text = format!(
+            "Git author not configured\n\nRNotes has no git username/email set. Your global git config has:\n\n{} <{}>\n\nUse this identity for RNotes commits?",
+            name, email
+        );
+
+        let prompt = Paragraph::new(prompt_text.as_str())
             .block(Block::default().borders(Borders::ALL))
-            .style(Style::default().fg(Color::Yellow));
-        f.render_widget(title, chunks[0]);
+            .style(Style::default().fg(Color::Yellow))
+            .wrap(Wrap { trim: true });
+        f.render_widget(prompt, chunks[0]);
 
-        // Input field
-        let input = Paragraph::new(self.rename_input.as_str())
-            .block(Block::default().title("New Name").borders(Borders::ALL))
-            .style(Style::default().fg(Color::White));
-        f.render_widget(input, chunks[1]);
+        let instructions = Paragraph::new("Press 'y' to SAVE it to the config or 'n' to SKIP")
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(instructions, chunks[1]);
     }
 
-    fn render_delete_confirm_screen(&self, f: &mut Frame, area: Rect) {
+    fn render_passphrase_prompt_screen(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(5),
+                Constraint::Length(3),
                 Constraint::Min(1),
             ])
             .split(area);
 
-        // Confirmation message
-        let (target_name, item_type) = if let Some(path) = &self.delete_target {
-            let name = path.file_name().unwrap().to_string_lossy().to_string();
-            let type_str = if path.is_dir() { "folder" } else { "file" };
-            (name, type_str)
-        } else {
-            ("Unknown".to_string(), "item")
+        let title = match &self.passphrase_action {
+            Some(PassphraseAction::Encrypt(path)) => format!(
+                "Encrypt {} (the plain file will be removed)",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            _ => "Unlock encrypted notes".to_string(),
         };
-        
-        let warning_text = format!(
-            "⚠️  DELETE CONFIRMATION  ⚠️\n\nAre you sure you want to delete this {}?\n\n📁 {}\n\nThis action cannot be undone!",
-            item_type, target_name
-        );
-        
-        let warning = Paragraph::new(warning_text.as_str())
-            .block(Block::default().borders(Borders::ALL))
-            .style(Style::default().fg(Color::Red))
-            .wrap(Wrap { trim: true });
-        f.render_widget(warning, chunks[0]);
-
-        // Instructions
-        let instructions = Paragraph::new("Press 'y' to DELETE or 'n' to CANCEL")
+        let title = Paragraph::new(title)
             .block(Block::default().borders(Borders::ALL))
             .style(Style::default().fg(Color::Yellow));
-        f.render_widget(instructions, chunks[1]);
+        f.render_widget(title, chunks[0]);
+
+        // Never echo the passphrase itself
+        render_secret_line_input(f, chunks[1], "Passphrase", &self.passphrase_input);
+    }
+
+    fn render_editor_screen(&mut self, f: &mut Frame, area: Rect) {
+        let editor = match &mut self.text_editor {
+            Some(editor) => editor,
+            None => return,
+        };
+        let file_name = self.current_file
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = if editor.is_modified() { format!("Edit - {} [+]", file_name) } else { format!("Edit - {}", file_name) };
+
+        let width = area.width.saturating_sub(2) as usize;
+        let height = area.height.saturating_sub(2) as usize;
+        let (top, left) = editor.scroll_to_cursor(width, height);
+        let lines: Vec<Line> = editor
+            .lines()
+            .iter()
+            .skip(top)
+            .take(height)
+            // One cell per character keeps the cursor where the text is
+            .map(|line| Line::from(line.chars().skip(left).take(width).map(|c| if c == '\t' { ' ' } else { c }).collect::<String>()))
+            .collect();
+
+        let (row, col) = editor.cursor();
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL)),
+            area,
+        );
+        f.set_cursor(area.x + 1 + (col - left) as u16, area.y + 1 + (row - top) as u16);
+    }
+
+    /// Path of `path` relative to the notes root, shortened from the front to fit `width` characters
+    ///
+    /// Leading folders are dropped first, leaving `…/` in their place, so the
+    /// file name stays readable as long as possible.
+    fn breadcrumb(&self, path: &Path, width: usize) -> String {
+        let relative = path.strip_prefix(&self.config.root_directory).unwrap_or(path);
+        let parts: Vec<String> = relative.iter().map(|part| part.to_string_lossy().to_string()).collect();
+        let full = parts.join("/");
+        if full.chars().count() <= width {
+            return full;
+        }
+        for start in 1..parts.len() {
+            let shortened = format!("…/{}", parts[start..].join("/"));
+            if shortened.chars().count() <= width {
+                return shortened;
+            }
+        }
+        // Not even the file name fits; keep its end, where the extension is
+        let name = parts.last().cloned().unwrap_or_default();
+        let keep = width.saturating_sub(1);
+        let tail: String = name.chars().skip(name.chars().count().saturating_sub(keep)).collect();
+        format!("…{}", tail)
+    }
+
+    fn render_split_preview_screen(&mut self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        // Preview wraps to its own pane; the width is restored on leaving the mode
+        self.content_width = chunks[1].width.saturating_sub(2);
+
+        let width = (chunks[0].width.saturating_sub(2) as usize).saturating_sub("Source - ".len());
+        let file_name = self.current_file
+            .as_ref()
+            .map(|path| self.breadcrumb(path, width))
+            .unwrap_or_default();
+
+        // Keep the preview at the same relative position as the source
+        let source_len = self.content_lines.len().max(1);
+        let preview_scroll = self.split_scroll * self.rendered_lines.len() / source_len;
+
+        let source = Paragraph::new(self.current_content.as_str())
+            .block(Block::default().title(format!("Source - {}", file_name)).borders(Borders::ALL))
+            .scroll((self.split_scroll as u16, 0));
+        f.render_widget(source, chunks[0]);
+
+        let preview = Paragraph::new(Text::from(self.rendered_lines.clone()))
+            .block(Block::default().title("Preview").borders(Borders::ALL))
+            .scroll((preview_scroll as u16, 0));
+        f.render_widget(preview, chunks[1]);
     }
 
     fn render_line_navigation_screen(&mut self, f: &mut Frame, area: Rect) {
+        let tree_width = self.config.tree_width();
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .constraints([Constraint::Percentage(tree_width), Constraint::Percentage(100 - tree_width)])
             .split(area);
+        self.content_width = chunks[1].width.saturating_sub(2);
+        self.tree_height = chunks[0].height.saturating_sub(2);
 
         // Create the items vector for file tree
         let file_items = self.file_tree.get_items();
+        let changed = self.tree_change_markers();
         let items: Vec<ListItem> = file_items
             .iter()
-            .map(|item| {
+            .enumerate()
+            .map(|(i, item)| {
                 let style = if item.contains("▶") || item.contains("▼") {
                     // Directory
                     Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
-                } else if item.ends_with(".md") {
+                } else if file_tree::is_markdown_file(Path::new(item)) {
                     // Markdown file
                     Style::default().fg(Color::Green)
                 } else {
                     // Other files
                     Style::default().fg(Color::Gray)
                 };
-                ListItem::new(item.as_str()).style(style)
+                ListItem::new(tree_item_line(item, changed.get(i).copied().unwrap_or(false))).style(style)
             })
             .collect();
 
         let list = List::new(items)
             .block(Block::default().title("Files").borders(Borders::ALL))
             .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
-            .highlight_symbol("> ");
+            .highlight_symbol("> ")
+            .scroll_padding(TREE_SCROLL_PADDING);
 
         f.render_stateful_widget(list, chunks[0], self.file_tree.get_state_mut());
         
         // Render content with line navigation using formatted lines
         let title = if let Some(file_path) = &self.current_file {
-            format!("Line Navigation - {}", file_path.file_name().unwrap().to_string_lossy())
+            let width = (chunks[1].width.saturating_sub(2) as usize).saturating_sub("Line Navigation - ".len());
+            format!("Line Navigation - {}", self.breadcrumb(file_path, width))
         } else {
             "Line Navigation".to_string()
         };
@@ -1198,12 +4686,20 @@ impl App {
                     Style::default()
                 };
                 
-                // Create a line with line number and preserve the formatting
-                let line_number = format!("{:3}: ", i + 1);
+                // Create a line with line number and preserve the formatting;
+                // without colors the selection is also marked in the gutter
+                let line_number = if self.monochrome && i == self.line_selection {
+                    format!("{:3}> ", i + 1)
+                } else {
+                    format!("{:3}: ", i + 1)
+                };
                 let mut spans = vec![Span::styled(
                     line_number,
                     Style::default().fg(Color::DarkGray),
                 )];
+                if let Some(blame) = &self.blame {
+                    spans.push(self.blame_gutter(blame, i));
+                }
                 
                 // Add the formatted line spans
                 spans.extend(line.spans.iter().cloned());
@@ -1231,24 +4727,321 @@ impl App {
 
         f.render_stateful_widget(line_list, chunks[1], &mut line_state);
     }
+
+    /// Columns taken by line numbers in the content pane, 0 when they are hidden
+    fn line_number_gutter_width(&self) -> u16 {
+        if !self.config.show_line_numbers || !self.current_is_markdown() || self.rendered_conflicts {
+            return 0;
+        }
+        let digits = self.current_content.lines().count().max(1).to_string().len().max(3);
+        digits as u16 + 1
+    }
+
+    /// Number of the source line behind rendered line `i`, on its first rendered line only
+    fn line_number_gutter(&self, i: usize, width: usize) -> Span<'static> {
+        let source_line = |i: usize| self.rendered_source_lines.get(i).copied().flatten();
+        let text = match source_line(i) {
+            Some(line) if i == 0 || source_line(i - 1) != Some(line) => {
+                format!("{:>digits$} ", line + 1, digits = width - 1)
+            }
+            _ => " ".repeat(width),
+        };
+        Span::styled(text, Style::default().fg(Color::DarkGray))
+    }
+
+    /// Commit and author of the source line behind rendered line `i`, on its first rendered line only
+    fn blame_gutter(&self, blame: &[Option<BlameLine>], i: usize) -> Span<'static> {
+        let source_line = |i: usize| match self.rendered_source_lines.get(i) {
+            Some(source_line) => *source_line,
+            None => Some(i),
+        };
+        let text = match source_line(i) {
+            Some(line) if i == 0 || source_line(i - 1) != Some(line) => match blame.get(line) {
+                Some(Some(entry)) => {
+                    format!("{} {:<12} ", entry.commit, entry.author.chars().take(12).collect::<String>())
+                }
+                Some(None) => format!("{:<width$}", "not committed", width = BLAME_GUTTER_WIDTH),
+                None => " ".repeat(BLAME_GUTTER_WIDTH),
+            },
+            _ => " ".repeat(BLAME_GUTTER_WIDTH),
+        };
+        Span::styled(text, Style::default().fg(Color::Yellow))
+    }
+}
+
+/// A file name for a note whose first line is `line`, without the extension
+///
+/// Heading markers are dropped, other characters that are awkward in file names
+/// separate words, and the words are joined with dashes; the result may be empty.
+fn note_name_from_line(line: &str) -> String {
+    let title: String = line
+        .trim()
+        .trim_start_matches('#')
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_') { c } else { ' ' })
+        .collect();
+    let name = title.split_whitespace().collect::<Vec<_>>().join("-");
+    name.chars().take(NOTE_NAME_MAX_CHARS).collect::<String>().trim_end_matches('-').to_string()
+}
+
+/// A file tree entry, marked with a dot when it has uncommitted changes
+fn tree_item_line(item: &str, changed: bool) -> Line<'static> {
+    let mut spans = vec![Span::raw(item.to_string())];
+    if changed {
+        spans.push(Span::styled(" ●", Style::default().fg(Color::Yellow)));
+    }
+    Line::from(spans)
+}
+
+/// Drop every color from a drawn frame, keeping bold, italic and underline
+///
+/// Cells that had a background (status bars, code, highlights, the selected
+/// line) are shown in reverse video instead so they still stand out.
+fn strip_colors(buffer: &mut Buffer, image_area: Option<Rect>) {
+    let area = buffer.area;
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            // Images drawn with colored half blocks would turn into noise
+            if image_area.is_some_and(|image| {
+                (image.left()..image.right()).contains(&x) && (image.top()..image.bottom()).contains(&y)
+            }) {
+                continue;
+            }
+            let cell = buffer.get_mut(x, y);
+            if cell.bg != Color::Reset {
+                cell.modifier.insert(Modifier::REVERSED);
+            }
+            cell.fg = Color::Reset;
+            cell.bg = Color::Reset;
+        }
+    }
+}
+
+/// Draw a prompt's input in a bordered field with the terminal cursor in it
+fn render_line_input(f: &mut Frame, area: Rect, title: &str, input: &LineInput) {
+    render_input_text(f, area, title, input.as_str(), input.cursor());
+}
+
+/// Render a prompt's input with every character shown as `*`
+fn render_secret_line_input(f: &mut Frame, area: Rect, title: &str, input: &LineInput) {
+    render_input_text(f, area, title, &"*".repeat(input.as_str().chars().count()), input.cursor());
+}
+
+fn render_input_text(f: &mut Frame, area: Rect, title: &str, text: &str, cursor: usize) {
+    // Keep the cursor in view when the text is wider than the field
+    let width = area.width.saturating_sub(2) as usize;
+    let left = (cursor + 1).saturating_sub(width);
+    let paragraph = Paragraph::new(text)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::White))
+        .scroll((0, left as u16));
+    f.render_widget(paragraph, area);
+    f.set_cursor(area.x + 1 + (cursor - left) as u16, area.y + 1);
+}
+
+/// Apply a cursor movement or edit key to a prompt's input
+fn edit_line_input(input: &mut LineInput, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Char(c) => input.insert_char(c),
+        KeyCode::Backspace => input.backspace(),
+        KeyCode::Delete => input.delete(),
+        KeyCode::Left => input.move_left(),
+        KeyCode::Right => input.move_right(),
+        KeyCode::Home => input.move_home(),
+        KeyCode::End => input.move_end(),
+        _ => {}
+    }
+}
+
+/// Where `path` is after renaming `old_path` to `new_path`, if it is that item or inside it
+fn moved_path(path: &Path, old_path: &Path, new_path: &Path) -> Option<PathBuf> {
+    path.strip_prefix(old_path).ok().map(|relative| new_path.join(relative))
+}
+
+/// Rename each `(from, to)` in turn, undoing the ones done if any of them fails
+fn rename_all(steps: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    for (done, (from, to)) in steps.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            for (from, to) in steps[..done].iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// The remembered recent notes of the config's notes directory that still exist
+fn remembered_recent_notes(config: &Config) -> VecDeque<PathBuf> {
+    if !config.remember_recent_notes {
+        return VecDeque::new();
+    }
+    config
+        .recent_notes()
+        .iter()
+        .map(|path| config.root_directory.join(path))
+        .filter(|path| path.is_file())
+        .take(RECENT_NOTES_LIMIT)
+        .collect()
+}
+
+fn invalid_patterns_message(patterns: &[String]) -> String {
+    format!("⚠ Invalid ignore_patterns in config: {}", patterns.join(", "))
+}
+
+/// A note's text, with undecodable bytes shown as `�` rather than nothing at all, and whether any were
+fn decode_note(bytes: Vec<u8>) -> (String, bool) {
+    match String::from_utf8(bytes) {
+        Ok(content) => (content, false),
+        Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), true),
+    }
+}
+
+/// Convert CRLF and lone CR line endings to LF
+fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Text with `\n` line endings turned back into the note's own, CRLF if `crlf`
+fn restore_line_endings(text: &str, crlf: bool) -> String {
+    if crlf {
+        text.replace('\n', "\r\n")
+    } else {
+        text.to_string()
+    }
+}
+
+/// A file's name without its extension, and the extension including its dot
+///
+/// The extension of an encrypted note is the whole `.md.age`, so a renamed or
+/// copied note stays encrypted.
+fn split_extension(path: &Path) -> (String, String) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let plain = if crypto::is_encrypted_note(path) {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    };
+    let stem = plain.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = name[stem.len()..].to_string();
+    (stem, extension)
+}
+
+/// Whether a rename input ends in a file extension such as `.md` or `.txt`
+///
+/// Only letters and digits with at least one letter count, so numbers or spaces
+/// after the last dot, as in `v1.2` or `Meeting 10.30`, are part of the name and
+/// those names still get the original extension.
+fn has_explicit_extension(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ext.chars().all(|c| c.is_ascii_alphanumeric()) && ext.chars().any(|c| c.is_ascii_alphabetic())
+        })
+}
+
+/// Number of files and folders under `dir`, counting `dir` itself
+///
+/// Symlinks are counted as files and not followed, as deleting the folder
+/// removes only the links.
+fn count_folder_contents(dir: &Path) -> (usize, usize) {
+    let (mut files, mut folders) = (0, 0);
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        folders += 1;
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => pending.push(entry.path()),
+                _ => files += 1,
+            }
+        }
+    }
+    (files, folders)
+}
+
+/// Whether `path` ends up inside `root` once symlinks and `..` are resolved, even if it does not exist yet
+fn is_inside_root(root: &Path, path: &Path) -> bool {
+    let root = match fs::canonicalize(root) {
+        Ok(root) => root,
+        Err(_) => return false,
+    };
+    // Resolve symlinks as far as the path exists and apply the rest by hand
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+        if let Ok(canonical) = fs::canonicalize(&resolved) {
+            resolved = canonical;
+        }
+    }
+    resolved.starts_with(&root)
+}
+
+/// Format a number of seconds in the past for display, e.g. "2h ago"
+fn format_time_ago(seconds: i64) -> String {
+    match seconds.max(0) {
+        seconds if seconds < 60 => "just now".to_string(),
+        seconds if seconds < 60 * 60 => format!("{}m ago", seconds / 60),
+        seconds if seconds < 24 * 60 * 60 => format!("{}h ago", seconds / (60 * 60)),
+        seconds => format!("{}d ago", seconds / (24 * 60 * 60)),
+    }
+}
+
+/// Format a byte count for display, e.g. "12.3 KB"
+fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
 }
 
 fn main() -> Result<()> {
+    let args = match CliArgs::parse() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    // Pasted text arrives in one piece instead of as keys, so a newline in it does not submit a prompt
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app and run it
-    let mut app = App::new()?;
-    let res = app.run(&mut terminal);
+    let res = App::new(&args).and_then(|mut app| app.run(&mut terminal));
 
     // Restore terminal
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
+        DisableBracketedPaste,
         LeaveAlternateScreen
     )?;
     terminal.show_cursor()?;
@@ -1259,3 +5052,203 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_utf8_is_decoded_lossily() {
+        assert_eq!(decode_note(b"# Title\nplain".to_vec()), ("# Title\nplain".to_string(), false));
+
+        let (content, lossy) = decode_note(b"# Caf\xe9\r\nnext \xff line\n".to_vec());
+        assert!(lossy);
+        let lines: Vec<_> = normalize_line_endings(&content).lines().map(str::to_string).collect();
+        assert_eq!(lines, ["# Caf\u{fffd}", "next \u{fffd} line"]);
+    }
+
+    #[test]
+    fn renaming_a_parent_folder_moves_the_open_note() {
+        let (old, new) = (Path::new("/notes/projects"), Path::new("/notes/work"));
+        assert_eq!(
+            moved_path(Path::new("/notes/projects/2024/plan.md"), old, new),
+            Some(PathBuf::from("/notes/work/2024/plan.md"))
+        );
+        assert_eq!(moved_path(old, old, new), Some(new.to_path_buf()));
+        // Only whole components match, so a folder sharing the name's start stays put
+        assert_eq!(moved_path(Path::new("/notes/projects-old/plan.md"), old, new), None);
+        assert_eq!(moved_path(Path::new("/notes/plan.md"), old, new), None);
+    }
+
+    #[test]
+    fn paths_escaping_the_notes_directory_are_outside_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("notes");
+        fs::create_dir_all(root.join("sub")).unwrap();
+
+        assert!(is_inside_root(&root, &root.join("note.md")));
+        assert!(is_inside_root(&root, &root.join("new/folder/note.md")));
+        assert!(is_inside_root(&root, &root.join("sub/../note.md")));
+
+        assert!(!is_inside_root(&root, &root.join("../escape")));
+        assert!(!is_inside_root(&root, &root.join("sub/../../escape.md")));
+        assert!(!is_inside_root(&root, &root.join("missing/../../escape.md")));
+        assert!(!is_inside_root(&root, &dir.path().join("escape.md")));
+
+        // A symlink inside the notes directory does not make its target part of it
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path(), root.join("link")).unwrap();
+            assert!(!is_inside_root(&root, &root.join("link/escape.md")));
+        }
+    }
+
+    #[test]
+    fn encrypted_notes_keep_their_whole_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("todo.md.age"), "not really encrypted").unwrap();
+        let mut app = app(dir.path());
+        app.config.encryption_enabled = true;
+        app.file_tree.apply_config(&app.config).unwrap();
+
+        rename(&mut app, &dir.path().join("todo.md.age"), "plan");
+        assert!(dir.path().join("plan.md.age").is_file());
+        rename(&mut app, &dir.path().join("plan.md.age"), "ideas.md");
+        assert!(dir.path().join("ideas.md.age").is_file());
+        rename(&mut app, &dir.path().join("ideas.md.age"), "todo.md.age");
+        assert!(dir.path().join("todo.md.age").is_file());
+        assert_eq!(app.status_message, None);
+
+        app.file_tree.reveal_path(&dir.path().join("todo.md.age")).unwrap();
+        app.duplicate_selected_file().unwrap();
+        assert!(dir.path().join("todo copy.md.age").is_file());
+        assert_eq!(app.file_tree.get_selected_path(), Some(&dir.path().join("todo copy.md.age")));
+
+        assert_eq!(split_extension(Path::new("notes/plan.md")), ("plan".to_string(), ".md".to_string()));
+        assert_eq!(split_extension(Path::new("Makefile")), ("Makefile".to_string(), String::new()));
+    }
+
+    #[test]
+    fn failed_renames_are_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, missing) = (dir.path().join("01-a.md"), dir.path().join("02-a.md"), dir.path().join("missing.md"));
+        fs::write(&a, "a").unwrap();
+
+        let steps = vec![(a.clone(), b.clone()), (missing, dir.path().join("elsewhere.md"))];
+        assert!(rename_all(&steps).is_err());
+        assert!(a.exists());
+        assert!(!b.exists());
+
+        assert!(rename_all(&steps[..1]).is_ok());
+        assert!(b.exists());
+    }
+
+    /// An app on `root` with every optional feature off
+    fn app(root: &Path) -> App {
+        let mut config = Config::default();
+        config.root_directory = root.to_path_buf();
+        config.create_welcome_file = false;
+        App::with_config(config, &CliArgs::default()).unwrap()
+    }
+
+    /// Rename `path` the way typing `input` at the rename prompt does
+    fn rename(app: &mut App, path: &Path, input: &str) {
+        app.file_tree.reveal_path(path).unwrap();
+        app.rename_input.set(input.to_string());
+        app.perform_rename().unwrap();
+    }
+
+    #[test]
+    fn typed_extensions_are_explicit() {
+        for name in ["plan.md", "plan.MARKDOWN", "photo.png", "notes.txt", "archive.tar", "data.mp3"] {
+            assert!(has_explicit_extension(name), "{} has an extension", name);
+        }
+        for name in ["plan", "v1.2", "Meeting 10.30", "Dr. Smith", "plan.", ".md"] {
+            assert!(!has_explicit_extension(name), "{} has no extension", name);
+        }
+    }
+
+    #[test]
+    fn renames_use_a_typed_extension_and_keep_the_original_otherwise() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("note.md"), "# Note").unwrap();
+        let mut app = app(dir.path());
+
+        rename(&mut app, &dir.path().join("note.md"), "v1.2");
+        assert!(dir.path().join("v1.2.md").is_file());
+        assert_eq!(app.status_message, None);
+
+        rename(&mut app, &dir.path().join("v1.2.md"), "note.txt");
+        assert!(dir.path().join("note.txt").is_file());
+        assert!(!dir.path().join("note.txt.md").exists());
+        assert!(app.status_message.as_deref().is_some_and(|message| message.contains("hidden from the tree")));
+    }
+
+    #[test]
+    fn renaming_the_folder_of_the_open_note_keeps_it_open() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("projects/2024")).unwrap();
+        fs::write(dir.path().join("projects/2024/plan.md"), "# Plan").unwrap();
+        let mut app = app(dir.path());
+        app.open_note(&dir.path().join("projects/2024/plan.md")).unwrap();
+
+        rename(&mut app, &dir.path().join("projects"), "work");
+        let moved = dir.path().join("work/2024/plan.md");
+        assert!(moved.is_file());
+        assert_eq!(app.current_file, Some(moved.clone()));
+        assert_eq!(app.file_tree.get_selected_path(), Some(&moved));
+        assert_eq!(app.recent_notes.front(), Some(&moved));
+        assert_eq!(app.current_content, "# Plan");
+    }
+
+    #[test]
+    fn read_only_mode_follows_the_saved_config() {
+        let dir = tempfile::tempdir().unwrap();
+        // Saving writes the config file, so keep it out of the user's own
+        std::env::set_var("RNOTES_CONFIG", dir.path().join("config.json"));
+        let mut app = app(dir.path());
+        assert!(!app.read_only);
+
+        app.open_config().unwrap();
+        app.config_field = app.config_fields.iter().position(|field| field.key == "read_only").unwrap();
+        app.handle_config_input(KeyCode::Char(' ')).unwrap();
+        app.handle_config_input(KeyCode::Enter).unwrap();
+        assert!(app.config.read_only);
+        assert!(app.read_only);
+
+        // The setting cannot switch read-only mode off from inside it
+        app.open_config().unwrap();
+        app.config_field = app.config_fields.iter().position(|field| field.key == "read_only").unwrap();
+        app.handle_config_input(KeyCode::Char(' ')).unwrap();
+        app.handle_config_input(KeyCode::Enter).unwrap();
+        assert!(app.config.read_only);
+        assert!(app.read_only);
+    }
+
+    #[test]
+    fn crlf_notes_are_saved_with_crlf() {
+        let original = "# Title\r\n\r\nText\r\n";
+        let edited = normalize_line_endings(original);
+        assert_eq!(edited, "# Title\n\nText\n");
+        assert_eq!(restore_line_endings(&edited, true), original);
+        assert_eq!(restore_line_endings(&edited, false), edited);
+    }
+
+    #[test]
+    fn crlf_and_unterminated_notes_render_without_carriage_returns() {
+        let renderer = MarkdownRenderer::new();
+        for original in ["# Title\r\n\r\nLast line", "# Title\r\rLast line", "# Title\n\nLast line"] {
+            let content = normalize_line_endings(original);
+            assert_eq!(content, "# Title\n\nLast line");
+            assert_eq!(content.lines().last(), Some("Last line"));
+
+            let elements = renderer.parse_markdown(&content).unwrap();
+            let text = renderer.render_to_plain_text(&elements);
+            assert!(!text.contains('\r'), "{:?}", text);
+            assert!(text.contains("Last line"), "{:?}", text);
+        }
+
+        // Saving keeps a missing final newline missing
+        assert_eq!(restore_line_endings("# Title\n\nLast line", true), "# Title\r\n\r\nLast line");
+    }
+}
@@ -5,21 +5,207 @@ use ratatui::{
     text::{Line, Span, Text},
 };
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::frontmatter::Frontmatter;
 
 #[derive(Debug, Clone)]
 pub enum MarkdownElement {
-    Heading { level: u8, text: String },
-    Paragraph { text: String },
+    Heading { level: u8, spans: Vec<InlineRun> },
+    Paragraph { spans: Vec<InlineRun> },
     CodeBlock { language: Option<String>, code: String },
-    InlineCode { text: String },
-    Link { text: String, url: String },
-    Bold { text: String },
-    Italic { text: String },
-    List { items: Vec<String>, ordered: bool },
-    BlockQuote { text: String },
+    List { items: Vec<Vec<InlineRun>>, ordered: bool },
+    BlockQuote { depth: usize, spans: Vec<InlineRun> },
     Rule,
     Text { text: String },
     Table { headers: Vec<String>, rows: Vec<Vec<String>>, alignments: Vec<TableAlignment> },
+    Frontmatter(Frontmatter),
+    Image { alt: String, url: String },
+}
+
+/// Text of a paragraph, heading, list item or quote shown in one inline style
+pub type InlineRun = (String, InlineStyle);
+
+/// Whether the `==` starting `words[0]` opens a highlight closed later on the line
+///
+/// A lone `==`, as in `a == b`, stays plain text.
+fn opens_highlight(words: &[&str]) -> bool {
+    let rest = match words.first().and_then(|word| word.strip_prefix("==")) {
+        Some(rest) if !rest.is_empty() => rest,
+        _ => return false,
+    };
+    let closes = |word: &str| word.len() > 2 && word.ends_with("==");
+    closes(rest) || words[1..].iter().any(|word| closes(word))
+}
+
+/// Inline styles open at some point of a block's text
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InlineStyle {
+    code: bool,
+    bold: bool,
+    italic: bool,
+    strikethrough: bool,
+    link: bool,
+    highlight: bool,
+}
+
+impl InlineStyle {
+    fn style(&self) -> Style {
+        let mut style = Style::default();
+        if self.code {
+            style = style.fg(Color::Green).bg(Color::Black);
+        }
+        if self.link {
+            style = style.fg(Color::Blue).add_modifier(Modifier::UNDERLINED);
+        }
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if self.italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if self.strikethrough {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        if self.highlight {
+            style = style.fg(Color::Black).bg(Color::Yellow);
+        }
+        style
+    }
+}
+
+/// Block text collected as runs of one inline style each
+#[derive(Default)]
+struct RunCollector {
+    runs: Vec<InlineRun>,
+    style: InlineStyle,
+}
+
+impl RunCollector {
+    /// End the run collected in `text`
+    fn end_run(&mut self, text: &mut String) {
+        if !text.is_empty() {
+            self.runs.push((std::mem::take(text), self.style));
+        }
+    }
+
+    /// End the run in `text` and continue in the style `change` gives
+    fn restyle(&mut self, text: &mut String, change: impl FnOnce(&mut InlineStyle)) {
+        self.end_run(text);
+        change(&mut self.style);
+    }
+
+    /// Every run so far with the surrounding whitespace trimmed, or `None` if they hold no text
+    fn take(&mut self, text: &mut String) -> Option<Vec<InlineRun>> {
+        self.end_run(text);
+        self.style = InlineStyle::default();
+        let mut runs = std::mem::take(&mut self.runs);
+        while runs.first().is_some_and(|(text, _)| text.trim().is_empty()) {
+            runs.remove(0);
+        }
+        while runs.last().is_some_and(|(text, _)| text.trim().is_empty()) {
+            runs.pop();
+        }
+        let first = runs.first_mut()?;
+        first.0 = first.0.trim_start().to_string();
+        let last = runs.last_mut()?;
+        last.0 = last.0.trim_end().to_string();
+        Some(runs)
+    }
+}
+
+/// A word of styled text, and the style of the space before it
+struct StyledWord {
+    pieces: Vec<InlineRun>,
+    gap: InlineStyle,
+}
+
+/// Split runs into lines at newlines
+fn split_lines(runs: &[InlineRun]) -> Vec<Vec<InlineRun>> {
+    let mut lines = vec![Vec::new()];
+    for (text, style) in runs {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+            if !part.is_empty() {
+                lines.last_mut().unwrap().push((part.to_string(), *style));
+            }
+        }
+    }
+    lines
+}
+
+/// Spans showing `runs` in `base` with each run's inline style on top
+fn styled_spans(runs: &[InlineRun], base: Style) -> Vec<Span<'static>> {
+    runs.iter().map(|(text, style)| Span::styled(text.clone(), base.patch(style.style()))).collect()
+}
+
+/// Split runs into lines at newlines and those into words
+fn split_words(runs: &[InlineRun]) -> Vec<Vec<StyledWord>> {
+    let mut segments = vec![Vec::new()];
+    let mut pieces: Vec<InlineRun> = Vec::new();
+    let mut gap = InlineStyle::default();
+    for (text, style) in runs {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !pieces.is_empty() {
+                    let word = StyledWord { pieces: std::mem::take(&mut pieces), gap };
+                    segments.last_mut().unwrap().push(word);
+                }
+                gap = *style;
+                if c == '\n' {
+                    segments.push(Vec::new());
+                }
+                continue;
+            }
+            match pieces.last_mut() {
+                Some((piece, piece_style)) if piece_style == style => piece.push(c),
+                _ => pieces.push((c.to_string(), *style)),
+            }
+        }
+    }
+    if !pieces.is_empty() {
+        segments.last_mut().unwrap().push(StyledWord { pieces, gap });
+    }
+    segments
+}
+
+/// Markdown extensions to parse beyond CommonMark, tables and strikethrough
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkdownFlavor {
+    // `- [ ]` and `- [x]` list items
+    pub tasklists: bool,
+    // `[^1]` references and their definitions
+    pub footnotes: bool,
+    // Curly quotes, en and em dashes and ellipses
+    pub smart_punctuation: bool,
+    // `{#id .class}` after a heading
+    pub heading_attributes: bool,
+}
+
+impl MarkdownFlavor {
+    /// Parser options for this flavor
+    pub fn options(&self) -> Options {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_SMART_PUNCTUATION, self.smart_punctuation);
+        options.set(Options::ENABLE_HEADING_ATTRIBUTES, self.heading_attributes);
+        options
+    }
+}
+
+/// Source lines a parsed element came from (0-based, inclusive)
+#[derive(Debug, Clone, Default)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+    // First line of each list item or table row, in order
+    pub rows: Vec<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +218,246 @@ pub enum TableAlignment {
 
 pub struct MarkdownRenderer {
     code_block_regex: Regex,
+    wrap_width: usize,
+    // Lines left for each image to be drawn over; 0 shows just a placeholder
+    image_rows: usize,
+    // Columns between tab stops in code and other preformatted text
+    tab_width: usize,
+    flavor: MarkdownFlavor,
+}
+
+/// Rendered lines, the source line of each, and the first line and URL of each image
+pub type RenderedMarkdown = (Vec<Line<'static>>, Vec<Option<usize>>, Vec<(usize, String)>);
+
+/// Content width of each table column, shrunk so the table fits in `max_width`
+fn table_column_widths(headers: &[String], rows: &[Vec<String>], max_width: usize) -> Vec<usize> {
+    let natural: Vec<usize> = (0..headers.len())
+        .map(|i| {
+            std::iter::once(&headers[i])
+                .chain(rows.iter().filter_map(|row| row.get(i)))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(1)
+        })
+        .collect();
+
+    // Every column costs a border and a space on each side
+    let overhead = 3 * natural.len() + 1;
+    let mut remaining = max_width.saturating_sub(overhead);
+    if natural.iter().sum::<usize>() <= remaining {
+        return natural;
+    }
+
+    // Narrow columns keep their width; the rest share what is left evenly
+    let mut order: Vec<usize> = (0..natural.len()).collect();
+    order.sort_by_key(|&i| natural[i]);
+    let mut widths = natural.clone();
+    for (placed, &i) in order.iter().enumerate() {
+        let share = remaining / (order.len() - placed);
+        widths[i] = natural[i].min(share).max(MIN_TABLE_COLUMN_WIDTH);
+        remaining = remaining.saturating_sub(widths[i]);
+    }
+    widths
+}
+
+/// Narrowest a table column is shrunk to, even if the table then overflows
+const MIN_TABLE_COLUMN_WIDTH: usize = 3;
+
+/// Word-wrap a table cell to `width` characters, splitting words that don't fit
+fn wrap_cell(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while !word.is_empty() {
+            let used = current.chars().count();
+            let space = usize::from(used > 0);
+            if used + space + word.len() <= width {
+                if space == 1 {
+                    current.push(' ');
+                }
+                current.extend(word.drain(..));
+            } else if used > 0 {
+                lines.push(std::mem::take(&mut current));
+            } else {
+                lines.push(word.drain(..width).collect());
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Pad cell text to `width` according to the column alignment, with a space on each side
+fn pad_cell(text: &str, width: usize, alignment: &TableAlignment) -> String {
+    match alignment {
+        TableAlignment::Right => format!(" {:>width$} ", text, width = width),
+        TableAlignment::Center => format!(" {:^width$} ", text, width = width),
+        TableAlignment::Left | TableAlignment::None => format!(" {:<width$} ", text, width = width),
+    }
+}
+
+/// Replace tabs with spaces up to the next multiple of `tab_width` columns
+pub fn expand_tabs(line: &str, tab_width: usize) -> String {
+    if !line.contains('\t') {
+        return line.to_string();
+    }
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_width - column % tab_width;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+/// Show a note with merge conflicts as raw text, coloring each side of every conflict
+pub fn render_conflicts(text: &str, tab_width: usize) -> Vec<Line<'static>> {
+    let marker = |color: Color| Style::default().fg(Color::White).bg(color).add_modifier(Modifier::BOLD);
+    let mut side = None;
+
+    text.lines()
+        .map(|line| {
+            let (style, next_side) = if line.starts_with("<<<<<<<") {
+                (marker(Color::Red), Some(Color::Green))
+            } else if line.starts_with("|||||||") && side.is_some() {
+                (marker(Color::DarkGray), Some(Color::DarkGray))
+            } else if line.starts_with("=======") && side.is_some() {
+                (marker(Color::DarkGray), Some(Color::Cyan))
+            } else if line.starts_with(">>>>>>>") && side.is_some() {
+                (marker(Color::Blue), None)
+            } else {
+                let style = side.map_or(Style::default(), |color| Style::default().fg(color));
+                return Line::from(Span::styled(expand_tabs(line, tab_width), style));
+            };
+            side = next_side;
+            Line::from(Span::styled(line.to_string(), style))
+        })
+        .collect()
+}
+
+/// Whether each line is fenced code or one of its fences, and the opening line of a block still open at the end
+fn fenced_lines(lines: &[String]) -> (Vec<bool>, Option<usize>) {
+    let mut fenced = Vec::with_capacity(lines.len());
+    let mut fence: Option<(&str, usize)> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let in_fence = match fence {
+            Some((marker, _)) if trimmed.starts_with(marker) => {
+                fence = None;
+                true
+            }
+            Some(_) => true,
+            None if trimmed.starts_with("```") => {
+                fence = Some(("```", i));
+                true
+            }
+            None if trimmed.starts_with("~~~") => {
+                fence = Some(("~~~", i));
+                true
+            }
+            None => false,
+        };
+        fenced.push(in_fence);
+    }
+    (fenced, fence.map(|(_, line)| line))
+}
+
+/// Split a note into ranges of about `chunk_lines` source lines that parse independently
+///
+/// Chunks end on a blank line outside fenced code, so no block is cut in half.
+pub fn split_into_chunks(lines: &[String], chunk_lines: usize) -> Vec<std::ops::Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let (fenced, _) = fenced_lines(lines);
+
+    for (i, line) in lines.iter().enumerate() {
+        if !fenced[i] && line.trim().is_empty() && i + 1 - start >= chunk_lines {
+            chunks.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+    if start < lines.len() {
+        chunks.push(start..lines.len());
+    }
+    chunks
+}
+
+/// Describe the first structural problem in a note, such as a code block that is never closed
+///
+/// The note still renders; this only tells the user why it may look wrong.
+pub fn find_problem(lines: &[String]) -> Option<String> {
+    let (_, open_fence) = fenced_lines(lines);
+    open_fence.map(|line| format!("Line {}: code block is never closed, the rest of the note is shown as code", line + 1))
+}
+
+/// Source line and level of every ATX heading (`## Title`) outside fenced code
+pub fn heading_lines(lines: &[String]) -> Vec<(usize, usize)> {
+    let (fenced, _) = fenced_lines(lines);
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !fenced[*i])
+        .filter_map(|(i, line)| {
+            let trimmed = line.trim_start();
+            let level = trimmed.chars().take_while(|&c| c == '#').count();
+            let rest = &trimmed[level..];
+            ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t']))).then_some((i, level))
+        })
+        .collect()
+}
+
+/// The dimmed `## ` drawn before a heading's text
+fn heading_prefix(level: usize) -> Span<'static> {
+    Span::styled(format!("{} ", "#".repeat(level)), Style::default().fg(Color::DarkGray))
+}
+
+/// Work out which source line each rendered line of one element shows
+fn source_lines_for(element: &MarkdownElement, span: &SourceSpan, rendered: &[Line]) -> Vec<Option<usize>> {
+    let content_count = rendered.iter().filter(|line| line.width() > 0).count();
+    let source_count = span.end - span.start + 1;
+    let mut content_index = 0;
+    let mut row = None;
+    let mut continuation = 0;
+
+    rendered
+        .iter()
+        .map(|line| {
+            if line.width() == 0 {
+                return None;
+            }
+            let j = content_index;
+            content_index += 1;
+
+            match element {
+                MarkdownElement::CodeBlock { .. } => {
+                    // Opening fence, one line per code line, closing fence
+                    Some(if j + 1 == content_count { span.end } else { (span.start + j).min(span.end) })
+                }
+                MarkdownElement::List { .. } => {
+                    // Items start with a bullet; lines after a hard break start with spaces
+                    if line.spans.first().is_some_and(|span| !span.content.trim().is_empty()) {
+                        row = Some(row.map_or(0, |k| k + 1));
+                        continuation = 0;
+                    } else {
+                        continuation += 1;
+                    }
+                    row.and_then(|k| span.rows.get(k))
+                        .map(|&start| (start + continuation).min(span.end))
+                }
+                _ => Some((span.start + j * source_count / content_count.max(1)).min(span.end)),
+            }
+        })
+        .collect()
 }
 
 impl Default for MarkdownRenderer {
@@ -44,27 +470,61 @@ impl MarkdownRenderer {
     pub fn new() -> Self {
         Self {
             code_block_regex: Regex::new(r"```(\w+)?\n((?s:.)*?)```").unwrap(),
+            wrap_width: 80,
+            image_rows: 0,
+            tab_width: 4,
+            flavor: MarkdownFlavor::default(),
         }
     }
 
+    pub fn wrap_width(&self) -> usize {
+        self.wrap_width
+    }
+
+    /// Set the content width: paragraphs wrap at it, and rules and tables fit within it
+    pub fn set_wrap_width(&mut self, width: usize) {
+        self.wrap_width = width.max(10);
+    }
+
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Set the distance between tab stops used to expand tabs
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width.max(1);
+    }
+
+    /// Set the markdown extensions notes are parsed with
+    pub fn set_flavor(&mut self, flavor: MarkdownFlavor) {
+        self.flavor = flavor;
+    }
+
+    /// Set how many lines are kept free for each image drawn inline
+    pub fn set_image_rows(&mut self, rows: usize) {
+        self.image_rows = rows;
+    }
+
     pub fn parse_markdown(&self, markdown: &str) -> Result<Vec<MarkdownElement>> {
-        // Use pulldown-cmark with table support enabled
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_TABLES);
-        options.insert(Options::ENABLE_STRIKETHROUGH);
+        self.parse_markdown_with_spans(markdown).map(|(elements, _)| elements)
+    }
+
+    /// Parse markdown, also returning the source lines of each element
+    pub fn parse_markdown_with_spans(&self, markdown: &str) -> Result<(Vec<MarkdownElement>, Vec<SourceSpan>)> {
+        // Use pulldown-cmark with the configured extensions, plus frontmatter
+        let mut options = self.flavor.options();
+        options.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
         
         let parser = Parser::new_ext(markdown, options);
         let mut elements = Vec::new();
         let mut current_text = String::new();
         let mut in_heading = None;
         let mut in_paragraph = false;
-        let mut in_code_block = false;
+        let mut inline = RunCollector::default();
         let mut code_lang = None;
-        let mut in_bold = false;
-        let mut in_italic = false;
-        let mut in_link = false;
-        let mut link_url = String::new();
-        let mut in_blockquote = false;
+        // Where the alt text of the current image starts in `current_text`, and its URL
+        let mut image: Option<(usize, String)> = None;
+        let mut blockquote_depth = 0;
         let mut list_items = Vec::new();
         let mut in_list = false;
         let mut is_ordered_list = false;
@@ -76,29 +536,31 @@ impl MarkdownRenderer {
         let mut current_row = Vec::new();
         let mut table_alignments = Vec::new();
 
-        for event in parser {
+        // Byte offset where each source line starts, to turn event ranges into lines
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(markdown.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset).saturating_sub(1);
+        let mut spans: Vec<SourceSpan> = Vec::new();
+        let mut row_lines: Vec<usize> = Vec::new();
+        let mut last_range = 0..0;
+
+        for (event, range) in parser.into_offset_iter() {
+            // Text outside tables, code and frontmatter keeps its inline styles
+            let styled = (in_paragraph || in_list || in_heading.is_some() || blockquote_depth > 0) && !in_table;
             match event {
                 Event::Start(tag) => match tag {
                     Tag::Heading { level, .. } => {
                         if in_paragraph {
-                            elements.push(MarkdownElement::Paragraph {
-                                text: current_text.trim().to_string(),
-                            });
-                            current_text.clear();
+                            if let Some(spans) = inline.take(&mut current_text) {
+                                elements.push(MarkdownElement::Paragraph { spans });
+                            }
                             in_paragraph = false;
                         }
                         in_heading = Some(level as u8);
                     }
-                    Tag::Paragraph => {
-                        if !in_list && !in_blockquote {
-                            // Check if this paragraph contains a table marker
-                            if !current_text.contains("__TABLE__") {
-                                in_paragraph = true;
-                            }
-                        }
-                    }
+                    Tag::Paragraph if !in_list && blockquote_depth == 0 => in_paragraph = true,
                     Tag::CodeBlock(kind) => {
-                        in_code_block = true;
                         code_lang = match kind {
                             pulldown_cmark::CodeBlockKind::Fenced(lang) => {
                                 if lang.is_empty() { None } else { Some(lang.to_string()) }
@@ -106,19 +568,36 @@ impl MarkdownRenderer {
                             _ => None,
                         };
                     }
-                    Tag::Strong => in_bold = true,
-                    Tag::Emphasis => in_italic = true,
-                    Tag::Link { dest_url, .. } => {
-                        in_link = true;
-                        link_url = dest_url.to_string();
+                    // Inline spans become styled runs
+                    Tag::Strong if styled => inline.restyle(&mut current_text, |style| style.bold = true),
+                    Tag::Emphasis if styled => inline.restyle(&mut current_text, |style| style.italic = true),
+                    Tag::Strikethrough if styled => {
+                        inline.restyle(&mut current_text, |style| style.strikethrough = true)
+                    }
+                    Tag::Strikethrough => current_text.push_str("~~"),
+                    Tag::Link { .. } if styled => inline.restyle(&mut current_text, |style| style.link = true),
+                    // Images get their own element only in plain paragraphs
+                    Tag::Image { dest_url, .. } if in_paragraph || (!in_list && !in_table && blockquote_depth == 0) => {
+                        inline.end_run(&mut current_text);
+                        image = Some((current_text.len(), dest_url.to_string()));
+                    }
+                    Tag::BlockQuote(_) => {
+                        // Flush the outer quote's text before the nested one starts
+                        if blockquote_depth > 0 {
+                            if let Some(spans) = inline.take(&mut current_text) {
+                                elements.push(MarkdownElement::BlockQuote { depth: blockquote_depth, spans });
+                            }
+                        }
+                        blockquote_depth += 1;
                     }
-                    Tag::BlockQuote(_) => in_blockquote = true,
                     Tag::List(start) => {
+                        row_lines.clear();
                         in_list = true;
                         is_ordered_list = start.is_some();
                         list_items.clear();
                     }
                     Tag::Item => {
+                        row_lines.push(line_of(range.start));
                         // Start of list item
                     }
                     Tag::Table(alignments) => {
@@ -127,20 +606,26 @@ impl MarkdownRenderer {
                             pulldown_cmark::Alignment::Left => TableAlignment::Left,
                             pulldown_cmark::Alignment::Center => TableAlignment::Center,
                             pulldown_cmark::Alignment::Right => TableAlignment::Right,
-                            pulldown_cmark::Alignment::None => TableAlignment::Left,
+                            pulldown_cmark::Alignment::None => TableAlignment::None,
                         }).collect();
                         table_headers.clear();
                         table_rows.clear();
                     }
                     Tag::TableHead => {
+                        row_lines.clear();
+                        row_lines.push(line_of(range.start));
                         // Start of table header
                     }
                     Tag::TableRow => {
+                        row_lines.push(line_of(range.start));
                         current_row.clear();
                     }
                     Tag::TableCell => {
                         // Start of table cell
                     }
+                    Tag::MetadataBlock(_) => current_text.clear(),
+                    // The definition's paragraph starts with its label
+                    Tag::FootnoteDefinition(label) => current_text.push_str(&format!("[^{}]: ", label)),
                     _ => {}
                 },
                 Event::End(tag_end) => match tag_end {
@@ -148,27 +633,24 @@ impl MarkdownRenderer {
                         if let Some(h_level) = in_heading {
                             elements.push(MarkdownElement::Heading {
                                 level: h_level,
-                                text: current_text.trim().to_string(),
+                                spans: inline.take(&mut current_text).unwrap_or_default(),
                             });
-                            current_text.clear();
                             in_heading = None;
                         }
                     }
                     TagEnd::Paragraph => {
                         if in_paragraph {
-                            elements.push(MarkdownElement::Paragraph {
-                                text: current_text.trim().to_string(),
-                            });
-                            current_text.clear();
+                            // Nothing is left when the paragraph was only an image
+                            if let Some(spans) = inline.take(&mut current_text) {
+                                elements.push(MarkdownElement::Paragraph { spans });
+                            }
                             in_paragraph = false;
-                        } else if in_list && !current_text.trim().is_empty() {
-                            list_items.push(current_text.trim().to_string());
-                            current_text.clear();
-                        } else if in_blockquote {
-                            elements.push(MarkdownElement::BlockQuote {
-                                text: current_text.trim().to_string(),
-                            });
-                            current_text.clear();
+                        } else if in_list {
+                            list_items.extend(inline.take(&mut current_text));
+                        } else if blockquote_depth > 0 {
+                            if let Some(spans) = inline.take(&mut current_text) {
+                                elements.push(MarkdownElement::BlockQuote { depth: blockquote_depth, spans });
+                            }
                         }
                     }
                     TagEnd::CodeBlock => {
@@ -177,21 +659,28 @@ impl MarkdownRenderer {
                             code: current_text.trim_end().to_string(),
                         });
                         current_text.clear();
-                        in_code_block = false;
                         code_lang = None;
                     }
-                    TagEnd::Strong => in_bold = false,
-                    TagEnd::Emphasis => in_italic = false,
-                    TagEnd::Link => {
-                        elements.push(MarkdownElement::Link {
-                            text: current_text.clone(),
-                            url: link_url.clone(),
-                        });
-                        current_text.clear();
-                        in_link = false;
-                        link_url.clear();
+                    TagEnd::Strong if styled => inline.restyle(&mut current_text, |style| style.bold = false),
+                    TagEnd::Emphasis if styled => inline.restyle(&mut current_text, |style| style.italic = false),
+                    TagEnd::Strikethrough if styled => {
+                        inline.restyle(&mut current_text, |style| style.strikethrough = false)
+                    }
+                    TagEnd::Strikethrough => current_text.push_str("~~"),
+                    TagEnd::Link if styled => inline.restyle(&mut current_text, |style| style.link = false),
+                    TagEnd::Image => {
+                        if let Some((start, url)) = image.take() {
+                            let alt = current_text.split_off(start);
+                            let style = inline.style;
+                            if let Some(spans) = inline.take(&mut current_text) {
+                                elements.push(MarkdownElement::Paragraph { spans });
+                            }
+                            // Text after the image is still inside the same inline spans
+                            inline.style = style;
+                            elements.push(MarkdownElement::Image { alt: alt.trim().to_string(), url });
+                        }
                     }
-                    TagEnd::BlockQuote(_) => in_blockquote = false,
+                    TagEnd::BlockQuote(_) => blockquote_depth = blockquote_depth.saturating_sub(1),
                     TagEnd::List(_) => {
                         if !list_items.is_empty() {
                             elements.push(MarkdownElement::List {
@@ -202,12 +691,7 @@ impl MarkdownRenderer {
                         }
                         in_list = false;
                     }
-                    TagEnd::Item => {
-                        if !current_text.trim().is_empty() {
-                            list_items.push(current_text.trim().to_string());
-                            current_text.clear();
-                        }
-                    }
+                    TagEnd::Item => list_items.extend(inline.take(&mut current_text)),
                     TagEnd::Table => {
                         if in_table {
                             elements.push(MarkdownElement::Table {
@@ -218,19 +702,24 @@ impl MarkdownRenderer {
                             in_table = false;
                         }
                     }
-                    TagEnd::TableHead => {
-                        // End of table header
+                    TagEnd::TableHead if in_table => {
+                        // The header cells are not wrapped in a row
+                        table_headers = current_row.clone();
+                        current_row.clear();
                     }
-                    TagEnd::TableRow => {
-                        if in_table {
-                            if table_headers.is_empty() {
-                                // This is the header row
-                                table_headers = current_row.clone();
-                            } else {
-                                // This is a data row
-                                table_rows.push(current_row.clone());
-                            }
-                        }
+                    TagEnd::TableRow if in_table => {
+                        table_rows.push(current_row.clone());
+                    }
+                    TagEnd::MetadataBlock(_) => {
+                        // Malformed frontmatter stays visible as ordinary text
+                        let element = match Frontmatter::parse(&current_text) {
+                            Some(frontmatter) => MarkdownElement::Frontmatter(frontmatter),
+                            None => MarkdownElement::Paragraph {
+                                spans: vec![(format!("---\n{}\n---", current_text.trim_end()), InlineStyle::default())],
+                            },
+                        };
+                        elements.push(element);
+                        current_text.clear();
                     }
                     TagEnd::TableCell => {
                         if in_table {
@@ -244,225 +733,106 @@ impl MarkdownRenderer {
                     current_text.push_str(&text);
                 }
                 Event::Code(code) => {
-                    if !in_code_block {
-                        elements.push(MarkdownElement::InlineCode {
-                            text: code.to_string(),
-                        });
+                    if styled {
+                        inline.restyle(&mut current_text, |style| style.code = true);
+                        current_text.push_str(&code);
+                        inline.restyle(&mut current_text, |style| style.code = false);
                     } else {
+                        // Other blocks are shown as plain text, so keep the backticks
+                        current_text.push('`');
                         current_text.push_str(&code);
+                        current_text.push('`');
                     }
                 }
                 Event::Rule => {
                     elements.push(MarkdownElement::Rule);
                 }
+                Event::TaskListMarker(checked) => current_text.push_str(if checked { "☑ " } else { "☐ " }),
+                Event::FootnoteReference(label) => current_text.push_str(&format!("[^{}]", label)),
+                Event::SoftBreak => current_text.push(' '),
+                Event::HardBreak => current_text.push('\n'),
                 _ => {}
             }
-        }
 
-        // Handle any remaining text
-        if !current_text.trim().is_empty() {
-            if in_paragraph {
-                elements.push(MarkdownElement::Paragraph {
-                    text: current_text.trim().to_string(),
-                });
-            } else if !in_list {
-                elements.push(MarkdownElement::Text {
-                    text: current_text.trim().to_string(),
+            // Elements are pushed when their block ends, and the end event covers the whole block
+            while spans.len() < elements.len() {
+                let is_container = matches!(
+                    elements[spans.len()],
+                    MarkdownElement::List { .. } | MarkdownElement::Table { .. }
+                );
+                spans.push(SourceSpan {
+                    start: line_of(range.start),
+                    end: line_of(range.end.saturating_sub(1).max(range.start)),
+                    rows: if is_container { row_lines.clone() } else { Vec::new() },
                 });
             }
+            last_range = range;
         }
 
-        Ok(elements)
-    }
-
-    fn parse_tables_manually(&self, markdown: &str) -> String {
-        let lines: Vec<&str> = markdown.lines().collect();
-        let mut result = Vec::new();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let line = lines[i].trim();
-            
-            // Check if this line looks like a table header
-            if line.contains('|') && i + 1 < lines.len() {
-                let next_line = lines[i + 1].trim();
-                // Check if next line is a separator (contains | and -)
-                if next_line.contains('|') && next_line.contains('-') {
-                    // Found a table!
-                    let (table_element, consumed_lines) = self.parse_single_table(&lines[i..]);
-                    
-                    if let Some(table) = table_element {
-                        // Add the table to our elements
-                        let table_text = self.render_table_as_text(&table);
-                        result.push(table_text);
-                    }
-                    
-                    i += consumed_lines;
-                } else {
-                    result.push(lines[i].to_string());
-                    i += 1;
-                }
-            } else {
-                result.push(lines[i].to_string());
-                i += 1;
+        // Handle any remaining text
+        if in_paragraph {
+            if let Some(spans) = inline.take(&mut current_text) {
+                elements.push(MarkdownElement::Paragraph { spans });
             }
+        } else if !in_list && !current_text.trim().is_empty() {
+            elements.push(MarkdownElement::Text {
+                text: current_text.trim().to_string(),
+            });
         }
-
-        result.join("\n")
-    }
-
-    fn parse_single_table(&self, lines: &[&str]) -> (Option<MarkdownElement>, usize) {
-        if lines.len() < 2 {
-            return (None, 0);
+        while spans.len() < elements.len() {
+            spans.push(SourceSpan {
+                start: line_of(last_range.start),
+                end: line_of(last_range.end.saturating_sub(1).max(last_range.start)),
+                rows: Vec::new(),
+            });
         }
 
-        let header_line = lines[0].trim();
-        let separator_line = lines[1].trim();
-
-        // Parse headers
-        let headers: Vec<String> = header_line
-            .split('|')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+        Ok((elements, spans))
+    }
 
-        if headers.is_empty() {
-            return (None, 0);
-        }
+    pub fn render_to_text(&self, elements: &[MarkdownElement]) -> Text<'static> {
+        Text::from(self.render_lines(elements, None).0)
+    }
 
-        // Parse alignment from separator
-        let alignments: Vec<TableAlignment> = separator_line
-            .split('|')
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| {
-                let trimmed = s.trim();
-                if trimmed.starts_with(':') && trimmed.ends_with(':') {
-                    TableAlignment::Center
-                } else if trimmed.ends_with(':') {
-                    TableAlignment::Right
-                } else {
-                    TableAlignment::Left
-                }
+    /// Render elements as unstyled text, with headings shown without their `#` markers
+    pub fn render_to_plain_text(&self, elements: &[MarkdownElement]) -> String {
+        let text = self.render_to_text(elements);
+        let lines: Vec<String> = text
+            .lines
+            .iter()
+            .map(|line| {
+                let spans = match line.spans.split_first() {
+                    Some((first, rest))
+                        if !rest.is_empty()
+                            && *first == heading_prefix(first.content.len().saturating_sub(1)) =>
+                    {
+                        rest
+                    }
+                    _ => &line.spans[..],
+                };
+                spans.iter().map(|span| span.content.as_ref()).collect::<String>().trim_end().to_string()
             })
             .collect();
-
-        // Parse rows
-        let mut rows = Vec::new();
-        let mut consumed = 2; // header + separator
-
-        for &line in &lines[2..] {
-            let trimmed = line.trim();
-            
-            // Stop if we hit an empty line or a line without |
-            if trimmed.is_empty() || !trimmed.contains('|') {
-                break;
-            }
-
-            let row: Vec<String> = trimmed
-                .split('|')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-
-            if !row.is_empty() {
-                rows.push(row);
-                consumed += 1;
-            } else {
-                break;
-            }
-        }
-
-        let table = MarkdownElement::Table {
-            headers,
-            rows,
-            alignments,
-        };
-
-        (Some(table), consumed)
+        lines.join("\n").trim_matches('\n').to_string()
     }
 
-    fn render_table_as_text(&self, table: &MarkdownElement) -> String {
-        if let MarkdownElement::Table { headers, rows, alignments: _alignments } = table {
-            let mut result = Vec::new();
-            
-            // Calculate column widths
-            let mut col_widths = Vec::new();
-            for (i, header) in headers.iter().enumerate() {
-                let mut max_width = header.len();
-                for row in rows {
-                    if let Some(cell) = row.get(i) {
-                        max_width = max_width.max(cell.len());
-                    }
-                }
-                col_widths.push(max_width + 2); // Add padding
-            }
-
-            // Top border
-            let mut top_line = "┌".to_string();
-            for (i, &width) in col_widths.iter().enumerate() {
-                top_line.push_str(&"─".repeat(width));
-                if i < col_widths.len() - 1 {
-                    top_line.push_str("┬");
-                }
-            }
-            top_line.push_str("┐");
-            result.push(top_line);
-
-            // Header row
-            let mut header_line = "│".to_string();
-            for (i, header) in headers.iter().enumerate() {
-                let width = col_widths[i];
-                header_line.push_str(&format!(" {:<width$}", header, width = width - 1));
-                header_line.push_str("│");
-            }
-            result.push(header_line);
-
-            // Separator
-            let mut sep_line = "├".to_string();
-            for (i, &width) in col_widths.iter().enumerate() {
-                sep_line.push_str(&"─".repeat(width));
-                if i < col_widths.len() - 1 {
-                    sep_line.push_str("┼");
-                }
-            }
-            sep_line.push_str("┤");
-            result.push(sep_line);
-
-            // Data rows
-            for row in rows {
-                let mut row_line = "│".to_string();
-                for (i, _) in headers.iter().enumerate() {
-                    let width = col_widths[i];
-                    let cell_content = row.get(i).cloned().unwrap_or_default();
-                    row_line.push_str(&format!(" {:<width$}", cell_content, width = width - 1));
-                    row_line.push_str("│");
-                }
-                result.push(row_line);
-            }
-
-            // Bottom border
-            let mut bottom_line = "└".to_string();
-            for (i, &width) in col_widths.iter().enumerate() {
-                bottom_line.push_str(&"─".repeat(width));
-                if i < col_widths.len() - 1 {
-                    bottom_line.push_str("┴");
-                }
-            }
-            bottom_line.push_str("┘");
-            result.push(bottom_line);
-
-            result.join("\n")
-        } else {
-            String::new()
-        }
+    /// Render elements, mapping each output line to the source line it shows
+    ///
+    /// Spacers and table borders have no source line and map to `None`.
+    pub fn render_with_source_map(&self, elements: &[MarkdownElement], spans: &[SourceSpan]) -> RenderedMarkdown {
+        self.render_lines(elements, Some(spans))
     }
 
-    pub fn render_to_text(&self, elements: &[MarkdownElement]) -> Text<'static> {
+    fn render_lines(&self, elements: &[MarkdownElement], spans: Option<&[SourceSpan]>) -> RenderedMarkdown {
         let mut lines = Vec::new();
+        let mut sources = Vec::new();
+        let mut images = Vec::new();
 
-        for element in elements {
+        for (index, element) in elements.iter().enumerate() {
+            let first_line = lines.len();
+            let mut table_rows: Option<Vec<Option<usize>>> = None;
             match element {
-                MarkdownElement::Heading { level, text } => {
+                MarkdownElement::Heading { level, spans } => {
                     // Add spacing before headings (except for the first element)
                     if !lines.is_empty() {
                         lines.push(Line::from(""));
@@ -489,15 +859,13 @@ impl MarkdownRenderer {
                             .add_modifier(Modifier::BOLD),
                     };
 
-                    let prefix = "#".repeat(*level as usize);
-                    lines.push(Line::from(vec![
-                        Span::styled(format!("{} ", prefix), Style::default().fg(Color::DarkGray)),
-                        Span::styled(text.clone(), style),
-                    ]));
+                    let mut heading = vec![heading_prefix(*level as usize)];
+                    heading.extend(styled_spans(spans, style));
+                    lines.push(Line::from(heading));
                     lines.push(Line::from(""));
                 }
-                MarkdownElement::Paragraph { text } => {
-                    lines.extend(self.wrap_text_with_inline_formatting(text, 80));
+                MarkdownElement::Paragraph { spans } => {
+                    lines.extend(self.wrap_text_with_inline_formatting(spans, self.wrap_width));
                     lines.push(Line::from(""));
                 }
                 MarkdownElement::CodeBlock { language, code } => {
@@ -519,7 +887,7 @@ impl MarkdownRenderer {
                     // Code content
                     for line in code.lines() {
                         lines.push(Line::from(Span::styled(
-                            format!("  {}", line),
+                            format!("  {}", expand_tabs(line, self.tab_width)),
                             Style::default().fg(Color::Green).bg(Color::Black),
                         )));
                     }
@@ -527,18 +895,6 @@ impl MarkdownRenderer {
                     lines.push(Line::from(Span::styled("```".to_string(), Style::default().fg(Color::DarkGray))));
                     lines.push(Line::from(""));
                 }
-                MarkdownElement::InlineCode { text } => {
-                    lines.push(Line::from(Span::styled(
-                        format!("`{}`", text),
-                        Style::default().fg(Color::Green).bg(Color::Black),
-                    )));
-                }
-                MarkdownElement::Link { text, url: _url } => {
-                    lines.push(Line::from(Span::styled(
-                        format!("[{}]", text),
-                        Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
-                    )));
-                }
                 MarkdownElement::List { items, ordered } => {
                     for (i, item) in items.iter().enumerate() {
                         let prefix = if *ordered {
@@ -547,164 +903,231 @@ impl MarkdownRenderer {
                             "• ".to_string()
                         };
 
-                        lines.push(Line::from(vec![
-                            Span::styled(prefix, Style::default().fg(Color::Yellow)),
-                            Span::raw(item.clone()),
-                        ]));
+                        let indent = " ".repeat(prefix.chars().count());
+                        let mut bullet = Some(Span::styled(prefix, Style::default().fg(Color::Yellow)));
+                        for line in split_lines(item) {
+                            // Continuation lines after a hard break line up with the item text
+                            let start = bullet.take().unwrap_or_else(|| Span::raw(indent.clone()));
+                            let mut spans = vec![start];
+                            spans.extend(styled_spans(&line, Style::default()));
+                            lines.push(Line::from(spans));
+                        }
                     }
                     lines.push(Line::from(""));
                 }
-                MarkdownElement::BlockQuote { text } => {
-                    for line in text.lines() {
-                        lines.push(Line::from(vec![
-                            Span::styled("▎ ".to_string(), Style::default().fg(Color::Blue)),
-                            Span::styled(line.to_string(), Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC)),
-                        ]));
+                MarkdownElement::BlockQuote { depth, spans } => {
+                    let quoted = Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC);
+                    for line in split_lines(spans) {
+                        let mut spans = vec![Span::styled("▎ ".repeat(*depth), Style::default().fg(Color::Blue))];
+                        spans.extend(styled_spans(&line, quoted));
+                        lines.push(Line::from(spans));
                     }
                     lines.push(Line::from(""));
                 }
                 MarkdownElement::Rule => {
                     lines.push(Line::from(Span::styled(
-                        "─".repeat(60),
+                        "─".repeat(self.wrap_width),
                         Style::default().fg(Color::DarkGray),
                     )));
                     lines.push(Line::from(""));
                 }
                 MarkdownElement::Text { text } => {
-                    lines.extend(self.wrap_text_with_inline_formatting(text, 80));
+                    let spans = [(text.clone(), InlineStyle::default())];
+                    lines.extend(self.wrap_text_with_inline_formatting(&spans, self.wrap_width));
                 }
-                MarkdownElement::Table { headers, rows, alignments: _alignments } => {
-                    // Add spacing before table
-                    if !lines.is_empty() {
-                        lines.push(Line::from(""));
-                    }
-
-                    // Calculate column widths
-                    let mut col_widths = Vec::new();
-                    for (i, header) in headers.iter().enumerate() {
-                        let mut max_width = header.len();
-                        for row in rows {
-                            if let Some(cell) = row.get(i) {
-                                max_width = max_width.max(cell.len());
-                            }
-                        }
-                        col_widths.push(max_width + 2); // Add padding
+                MarkdownElement::Frontmatter(frontmatter) => {
+                    if let Some(title) = &frontmatter.title {
+                        lines.push(Line::from(Span::styled(
+                            title.clone(),
+                            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                        )));
                     }
 
-                    // Render table top border
-                    let mut top_spans = vec![Span::styled("┌".to_string(), Style::default().fg(Color::Cyan))];
-                    for (i, _) in headers.iter().enumerate() {
-                        let width = col_widths.get(i).unwrap_or(&10);
-                        top_spans.push(Span::styled("─".repeat(*width), Style::default().fg(Color::Cyan)));
-                        if i < headers.len() - 1 {
-                            top_spans.push(Span::styled("┬".to_string(), Style::default().fg(Color::Cyan)));
-                        }
+                    let mut details = Vec::new();
+                    if let Some(date) = &frontmatter.date {
+                        details.push(Span::styled(date.clone(), Style::default().fg(Color::DarkGray)));
                     }
-                    top_spans.push(Span::styled("┐".to_string(), Style::default().fg(Color::Cyan)));
-                    lines.push(Line::from(top_spans));
-
-                    // Render table header
-                    let mut header_spans = vec![Span::styled("│".to_string(), Style::default().fg(Color::Cyan))];
-                    for (i, header) in headers.iter().enumerate() {
-                        let width = col_widths.get(i).unwrap_or(&10);
-                        let padded_header = format!(" {:<width$}", header, width = width - 1);
-                        header_spans.push(Span::styled(padded_header, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
-                        header_spans.push(Span::styled("│".to_string(), Style::default().fg(Color::Cyan)));
-                    }
-                    lines.push(Line::from(header_spans));
-
-                    // Render table separator
-                    let mut separator_spans = vec![Span::styled("├".to_string(), Style::default().fg(Color::Cyan))];
-                    for (i, _) in headers.iter().enumerate() {
-                        let width = col_widths.get(i).unwrap_or(&10);
-                        separator_spans.push(Span::styled("─".repeat(*width), Style::default().fg(Color::Cyan)));
-                        if i < headers.len() - 1 {
-                            separator_spans.push(Span::styled("┼".to_string(), Style::default().fg(Color::Cyan)));
+                    for tag in &frontmatter.tags {
+                        if !details.is_empty() {
+                            details.push(Span::raw(" "));
                         }
+                        details.push(Span::styled(format!("#{}", tag), Style::default().fg(Color::Magenta)));
                     }
-                    separator_spans.push(Span::styled("┤".to_string(), Style::default().fg(Color::Cyan)));
-                    lines.push(Line::from(separator_spans));
-
-                    // Render table rows
-                    for row in rows {
-                        let mut row_spans = vec![Span::styled("│".to_string(), Style::default().fg(Color::Cyan))];
-                        for (i, _) in headers.iter().enumerate() {
-                            let width = col_widths.get(i).unwrap_or(&10);
-                            let cell_content = row.get(i).cloned().unwrap_or_default();
-                            let padded_cell = format!(" {:<width$}", cell_content, width = width - 1);
-                            row_spans.push(Span::styled(padded_cell, Style::default().fg(Color::White)));
-                            row_spans.push(Span::styled("│".to_string(), Style::default().fg(Color::Cyan)));
-                        }
-                        lines.push(Line::from(row_spans));
+                    if !details.is_empty() {
+                        lines.push(Line::from(details));
                     }
 
-                    // Render table bottom border
-                    let mut bottom_spans = vec![Span::styled("└".to_string(), Style::default().fg(Color::Cyan))];
-                    for (i, _) in headers.iter().enumerate() {
-                        let width = col_widths.get(i).unwrap_or(&10);
-                        bottom_spans.push(Span::styled("─".repeat(*width), Style::default().fg(Color::Cyan)));
-                        if i < headers.len() - 1 {
-                            bottom_spans.push(Span::styled("┴".to_string(), Style::default().fg(Color::Cyan)));
-                        }
+                    if !frontmatter.is_empty() {
+                        lines.push(Line::from(Span::styled(
+                            "─".repeat(self.wrap_width),
+                            Style::default().fg(Color::DarkGray),
+                        )));
+                        lines.push(Line::from(""));
+                    }
+                }
+                MarkdownElement::Table { headers, rows, alignments } => {
+                    // Add spacing before table
+                    if !lines.is_empty() {
+                        lines.push(Line::from(""));
                     }
-                    bottom_spans.push(Span::styled("┘".to_string(), Style::default().fg(Color::Cyan)));
-                    lines.push(Line::from(bottom_spans));
+
+                    let (table_lines, rows_of_lines) = self.render_table(headers, rows, alignments);
+                    let mut line_rows = vec![None; lines.len() - first_line];
+                    line_rows.extend(rows_of_lines);
+                    lines.extend(table_lines);
+                    table_rows = Some(line_rows);
+                    lines.push(Line::from(""));
+                }
+                MarkdownElement::Image { alt, url } => {
+                    images.push((lines.len(), url.clone()));
+                    let label = if alt.is_empty() { url } else { alt };
+                    lines.push(Line::from(Span::styled(
+                        format!("🖼 {}", label),
+                        Style::default().fg(Color::Magenta).add_modifier(Modifier::ITALIC),
+                    )));
+                    // Room for the image to be drawn over the placeholder
+                    lines.extend((1..self.image_rows).map(|_| Line::from("")));
                     lines.push(Line::from(""));
                 }
-                _ => {}
+            }
+
+            match (spans.and_then(|spans| spans.get(index)), table_rows) {
+                (Some(span), Some(table_rows)) => {
+                    // Table lines know their row; wrapped cells span several lines
+                    sources.extend(table_rows.iter().map(|row| row.and_then(|k| span.rows.get(k).copied())));
+                    sources.resize(lines.len(), None);
+                }
+                (Some(span), None) => sources.extend(source_lines_for(element, span, &lines[first_line..])),
+                (None, _) => sources.resize(lines.len(), None),
             }
         }
 
-        Text::from(lines)
+        (lines, sources, images)
     }
 
-    fn wrap_text_with_inline_formatting(&self, text: &str, width: usize) -> Vec<Line<'static>> {
+    /// Draw a table within the wrap width, also returning the row each line shows
+    ///
+    /// Row 0 is the header. Columns shrink to fit and long cells wrap onto
+    /// extra lines of their row.
+    fn render_table(
+        &self,
+        headers: &[String],
+        rows: &[Vec<String>],
+        alignments: &[TableAlignment],
+    ) -> (Vec<Line<'static>>, Vec<Option<usize>>) {
+        let border = Style::default().fg(Color::Cyan);
+        let header_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        let cell_style = Style::default().fg(Color::White);
+
+        let widths = table_column_widths(headers, rows, self.wrap_width);
+        let rule = |left: &str, middle: &str, right: &str| {
+            let mut spans = vec![Span::styled(left.to_string(), border)];
+            for (i, width) in widths.iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::styled(middle.to_string(), border));
+                }
+                spans.push(Span::styled("─".repeat(width + 2), border));
+            }
+            spans.push(Span::styled(right.to_string(), border));
+            Line::from(spans)
+        };
+
+        let mut lines = vec![rule("┌", "┬", "┐")];
+        let mut line_rows = vec![None];
+        let all_rows = std::iter::once(headers).chain(rows.iter().map(|row| row.as_slice()));
+        for (k, row) in all_rows.enumerate() {
+            let style = if k == 0 { header_style } else { cell_style };
+            let cells: Vec<Vec<String>> = widths
+                .iter()
+                .enumerate()
+                .map(|(i, &width)| wrap_cell(row.get(i).map(String::as_str).unwrap_or(""), width))
+                .collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+
+            for line_index in 0..height {
+                let mut spans = vec![Span::styled("│".to_string(), border)];
+                for (i, cell) in cells.iter().enumerate() {
+                    let text = cell.get(line_index).map(String::as_str).unwrap_or("");
+                    let alignment = alignments.get(i).unwrap_or(&TableAlignment::Left);
+                    spans.push(Span::styled(pad_cell(text, widths[i], alignment), style));
+                    spans.push(Span::styled("│".to_string(), border));
+                }
+                lines.push(Line::from(spans));
+                line_rows.push(Some(k));
+            }
+
+            if k == 0 {
+                lines.push(rule("├", "┼", "┤"));
+                line_rows.push(None);
+            }
+        }
+        lines.push(rule("└", "┴", "┘"));
+        line_rows.push(None);
+
+        (lines, line_rows)
+    }
+
+    fn wrap_text_with_inline_formatting(&self, runs: &[InlineRun], width: usize) -> Vec<Line<'static>> {
         let mut lines = Vec::new();
         let mut current_line = Vec::new();
         let mut current_length = 0;
+        // Highlight (==text==) is plain text to the parser, so it is found here and may wrap
+        let mut highlight = false;
 
-        // Simple word wrapping with inline markdown support
-        for word in text.split_whitespace() {
-            let word_len = word.len();
-            
-            if current_length + word_len + 1 > width && !current_line.is_empty() {
-                lines.push(Line::from(current_line.clone()));
-                current_line.clear();
+        // Simple word wrapping of the styled runs; newlines are hard breaks
+        for (i, segment) in split_words(runs).into_iter().enumerate() {
+            if i > 0 {
+                lines.push(Line::from(std::mem::take(&mut current_line)));
                 current_length = 0;
             }
 
-            if !current_line.is_empty() {
-                current_line.push(Span::raw(" ".to_string()));
-                current_length += 1;
-            }
+            let plain: Vec<String> = segment
+                .iter()
+                .map(|word| word.pieces.iter().map(|(text, _)| text.as_str()).collect())
+                .collect();
+            let plain: Vec<&str> = plain.iter().map(String::as_str).collect();
+            for (index, word) in segment.into_iter().enumerate() {
+                let mut pieces = word.pieces;
+                let opens_highlight = !highlight
+                    && pieces[0].0.starts_with("==")
+                    && opens_highlight(&plain[index..]);
+                if opens_highlight {
+                    pieces[0].0.drain(..2);
+                }
+                let closes_highlight = (highlight || opens_highlight)
+                    && pieces.last().is_some_and(|(text, _)| text.ends_with("=="));
+                if closes_highlight {
+                    let last = pieces.last_mut().unwrap();
+                    last.0.truncate(last.0.len() - 2);
+                }
+                pieces.retain(|(text, _)| !text.is_empty());
+                let word_len: usize = pieces.iter().map(|(text, _)| text.chars().count()).sum();
 
-            // Check for inline formatting
-            if word.starts_with("**") && word.ends_with("**") && word.len() > 4 {
-                // Bold text
-                let content = &word[2..word.len()-2];
-                current_line.push(Span::styled(
-                    content.to_string(),
-                    Style::default().add_modifier(Modifier::BOLD),
-                ));
-            } else if word.starts_with('*') && word.ends_with('*') && word.len() > 2 {
-                // Italic text
-                let content = &word[1..word.len()-1];
-                current_line.push(Span::styled(
-                    content.to_string(),
-                    Style::default().add_modifier(Modifier::ITALIC),
-                ));
-            } else if word.starts_with('`') && word.ends_with('`') && word.len() > 2 {
-                // Inline code
-                let content = &word[1..word.len()-1];
-                current_line.push(Span::styled(
-                    content.to_string(),
-                    Style::default().fg(Color::Green).bg(Color::Black),
-                ));
-            } else {
-                current_line.push(Span::raw(word.to_string()));
-            }
+                if current_length + word_len + 1 > width && !current_line.is_empty() {
+                    lines.push(Line::from(current_line.clone()));
+                    current_line.clear();
+                    current_length = 0;
+                }
 
-            current_length += word_len;
+                if !current_line.is_empty() {
+                    // The space inside a span (e.g. code or a link) keeps its style
+                    let gap = InlineStyle { highlight, ..word.gap };
+                    current_line.push(Span::styled(" ".to_string(), gap.style()));
+                    current_length += 1;
+                }
+                if opens_highlight {
+                    highlight = true;
+                }
+
+                for (text, style) in pieces {
+                    current_line.push(Span::styled(text, InlineStyle { highlight, ..style }.style()));
+                }
+
+                if closes_highlight {
+                    highlight = false;
+                }
+                current_length += word_len;
+            }
         }
 
         if !current_line.is_empty() {
@@ -718,3 +1141,204 @@ impl MarkdownRenderer {
         lines
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each line's text, with highlighted runs wrapped in `[` and `]`
+    fn highlighted(renderer: &MarkdownRenderer, text: &str) -> Vec<String> {
+        renderer
+            .wrap_text_with_inline_formatting(&[(text.to_string(), InlineStyle::default())], 80)
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| {
+                        if span.style.bg == Some(Color::Yellow) {
+                            format!("[{}]", span.content)
+                        } else {
+                            span.content.to_string()
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn highlights_need_a_closing_marker_on_the_line() {
+        let renderer = MarkdownRenderer::new();
+        assert_eq!(highlighted(&renderer, "==word=="), ["[word]"]);
+        assert_eq!(highlighted(&renderer, "a ==two words== b"), ["a [two][ ][words] b"]);
+        assert_eq!(highlighted(&renderer, "if a == b then"), ["if a == b then"]);
+        assert_eq!(highlighted(&renderer, "==open without close"), ["==open without close"]);
+        assert_eq!(highlighted(&renderer, "==open\nclose=="), ["==open", "close=="]);
+    }
+
+    #[test]
+    fn nested_blockquotes_keep_their_depth() {
+        let renderer = MarkdownRenderer::new();
+        let elements = renderer.parse_markdown("> outer\n>\n> > inner\n").unwrap();
+        let quotes: Vec<_> = elements
+            .iter()
+            .filter_map(|element| match element {
+                MarkdownElement::BlockQuote { depth, spans } => Some((*depth, spans[0].0.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(quotes, [(1, "outer"), (2, "inner")]);
+
+        let text = renderer.render_to_plain_text(&elements);
+        assert!(text.contains("▎ ▎ inner"), "{}", text);
+    }
+
+    #[test]
+    fn fenced_code_hides_headings_and_chunk_breaks() {
+        let lines: Vec<String> = ["# One", "", "```", "# not a heading", "", "```", "", "~~~", "## still code"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(heading_lines(&lines), [(0, 1)]);
+        assert_eq!(split_into_chunks(&lines, 1), [0..2, 2..7, 7..9]);
+        assert_eq!(
+            find_problem(&lines).as_deref(),
+            Some("Line 8: code block is never closed, the rest of the note is shown as code")
+        );
+        assert_eq!(find_problem(&lines[..7]), None);
+    }
+
+    #[test]
+    fn rendered_lines_map_back_to_their_source_line() {
+        let renderer = MarkdownRenderer::new();
+        let source = "| Name | Count |\n|------|-------|\n| a | 1 |\n| b | 2 |\n\nFirst paragraph.\n\nSecond paragraph.\n";
+        let (elements, spans) = renderer.parse_markdown_with_spans(source).unwrap();
+        let (lines, sources, _) = renderer.render_with_source_map(&elements, &spans);
+        let source_lines: Vec<&str> = source.lines().collect();
+        let copied: Vec<(String, Option<&str>)> = lines
+            .iter()
+            .zip(&sources)
+            .map(|(line, source)| {
+                let text = line.spans.iter().map(|span| span.content.as_ref()).collect();
+                (text, source.map(|row| source_lines[row]))
+            })
+            .collect();
+
+        for (text, copied) in &copied {
+            match text.trim() {
+                "First paragraph." | "Second paragraph." => assert_eq!(Some(text.as_str()), *copied),
+                row if row.starts_with("│ a") => assert_eq!(*copied, Some("| a | 1 |")),
+                row if row.starts_with('├') || row.starts_with('└') => assert_eq!(*copied, None, "{}", row),
+                _ => {}
+            }
+        }
+        assert_eq!(copied.iter().filter(|(_, copied)| *copied == Some("Second paragraph.")).count(), 1);
+    }
+
+    #[test]
+    fn table_columns_follow_their_alignment() {
+        let renderer = MarkdownRenderer::new();
+        let elements = renderer
+            .parse_markdown("| Left | Middle | Right | Plain |\n|:-----|:------:|------:|-------|\n| a | b | c | d |\n")
+            .unwrap();
+        let text = renderer.render_to_plain_text(&elements);
+        assert!(text.contains("│ a    │   b    │     c │ d     │"), "{}", text);
+
+        // Headers are aligned the same way
+        let elements = renderer.parse_markdown("| A | Wide column |\n|--:|:---:|\n| 100 | x |\n").unwrap();
+        let text = renderer.render_to_plain_text(&elements);
+        assert!(text.contains("│   A │ Wide column │"), "{}", text);
+        assert!(text.contains("│ 100 │      x      │"), "{}", text);
+    }
+
+    #[test]
+    fn tables_are_parsed_from_the_table_events() {
+        let renderer = MarkdownRenderer::new();
+        let tables = |markdown: &str| -> Vec<(Vec<String>, Vec<Vec<String>>)> {
+            renderer
+                .parse_markdown(markdown)
+                .unwrap()
+                .into_iter()
+                .filter_map(|element| match element {
+                    MarkdownElement::Table { headers, rows, .. } => Some((headers, rows)),
+                    _ => None,
+                })
+                .collect()
+        };
+        let expected = vec![(
+            vec!["Name".to_string(), "Count".to_string()],
+            vec![vec!["a|b".to_string(), "1".to_string()]],
+        )];
+
+        // With and without the outer pipes, and an escaped pipe inside a cell
+        assert_eq!(tables("| Name | Count |\n|---|---|\n| a\\|b | 1 |\n"), expected);
+        assert_eq!(tables("Name | Count\n--- | ---\na\\|b | 1\n"), expected);
+        assert!(tables("| Not | a table |\n\nJust text\n").is_empty());
+    }
+
+    #[test]
+    fn inline_code_and_bold_stay_within_their_sentence() {
+        let renderer = MarkdownRenderer::new();
+        let elements = renderer.parse_markdown("Run `cargo build` and **then** check the [log](log.md).\n").unwrap();
+        assert_eq!(elements.len(), 1, "{:?}", elements);
+        assert!(matches!(elements[0], MarkdownElement::Paragraph { .. }));
+
+        let text = renderer.render_to_text(&elements);
+        let line = &text.lines[0];
+        let content: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(content, "Run cargo build and then check the log.");
+
+        let style_of = |word: &str| line.spans.iter().find(|span| span.content.contains(word)).unwrap().style;
+        assert_ne!(style_of("cargo"), style_of("Run"));
+        assert!(style_of("then").add_modifier.contains(Modifier::BOLD));
+        assert!(!style_of("check").add_modifier.contains(Modifier::BOLD));
+        assert_ne!(style_of("log"), style_of("check"));
+    }
+
+    #[test]
+    fn private_use_characters_are_plain_text() {
+        let renderer = MarkdownRenderer::new();
+        // Nerd Font icons live in the private use area
+        let source = "Tomato \u{E000} timer \u{E001}**done**\u{E004}\n";
+        let elements = renderer.parse_markdown(source).unwrap();
+        let spans = match &elements[0] {
+            MarkdownElement::Paragraph { spans } => spans.clone(),
+            other => panic!("{:?}", other),
+        };
+        let bold = InlineStyle { bold: true, ..InlineStyle::default() };
+        assert_eq!(
+            spans,
+            [
+                ("Tomato \u{E000} timer \u{E001}".to_string(), InlineStyle::default()),
+                ("done".to_string(), bold),
+                ("\u{E004}".to_string(), InlineStyle::default()),
+            ]
+        );
+
+        let line = &renderer.render_to_text(&elements).lines[0];
+        let content: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(content, "Tomato \u{E000} timer \u{E001}done\u{E004}");
+        assert!(!line.spans[0].style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn strikethrough_is_crossed_out_in_every_block() {
+        let renderer = MarkdownRenderer::new();
+        let source = "# Plan ~~old~~\n\nThis is ~~gone~~ now.\n\n- item ~~done~~\n- next\n\n> quote ~~struck~~\n";
+        let elements = renderer.parse_markdown(source).unwrap();
+        let text = renderer.render_to_text(&elements);
+
+        let mut struck = Vec::new();
+        for line in &text.lines {
+            let content: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+            assert!(!content.contains('~'), "{}", content);
+            struck.extend(
+                line.spans
+                    .iter()
+                    .filter(|span| span.style.add_modifier.contains(Modifier::CROSSED_OUT))
+                    .map(|span| span.content.to_string()),
+            );
+        }
+        assert_eq!(struck, ["old", "gone", "done", "struck"]);
+    }
+}
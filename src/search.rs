@@ -0,0 +1,251 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::SystemTime,
+};
+
+use crate::config::Config;
+use crate::file_tree::FileTree;
+
+// Notes read by the background indexer before handing them over
+const INDEX_BATCH_SIZE: usize = 50;
+
+/// A line of a note that matches a search
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub path: PathBuf,
+    // 0-based source line
+    pub line: usize,
+    pub text: String,
+}
+
+/// What the background indexer has read so far
+pub enum IndexUpdate {
+    // Number of notes that will be indexed
+    Total(usize),
+    // Path, content and modification time of each note read
+    Notes(Vec<(PathBuf, String, Option<SystemTime>)>),
+    // Notes that were indexed but are gone
+    Removed(Vec<PathBuf>),
+}
+
+struct IndexedNote {
+    lines: Vec<String>,
+    modified: Option<SystemTime>,
+}
+
+/// Vault-wide full-text index mapping words to the note lines they appear on
+///
+/// Notes are referred to by a number so each word's entries don't repeat the path.
+#[derive(Default)]
+pub struct SearchIndex {
+    notes: HashMap<usize, IndexedNote>,
+    // Path of each note number, and the number of each path; a path keeps its number once given one
+    paths: Vec<PathBuf>,
+    ids: HashMap<PathBuf, usize>,
+    terms: BTreeMap<String, BTreeSet<(usize, usize)>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index `content` as the text of `path`, replacing whatever was indexed for it before
+    pub fn insert(&mut self, path: PathBuf, content: &str, modified: Option<SystemTime>) {
+        self.remove(&path);
+        let id = match self.ids.get(&path) {
+            Some(id) => *id,
+            None => {
+                self.paths.push(path.clone());
+                self.ids.insert(path, self.paths.len() - 1);
+                self.paths.len() - 1
+            }
+        };
+        let lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+        for (number, line) in lines.iter().enumerate() {
+            for word in words(line) {
+                self.terms.entry(word).or_default().insert((id, number));
+            }
+        }
+        self.notes.insert(id, IndexedNote { lines, modified });
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        let id = match self.ids.get(path) {
+            Some(id) => *id,
+            None => return,
+        };
+        let note = match self.notes.remove(&id) {
+            Some(note) => note,
+            None => return,
+        };
+        for (number, line) in note.lines.iter().enumerate() {
+            for word in words(line) {
+                if let Some(lines) = self.terms.get_mut(&word) {
+                    lines.remove(&(id, number));
+                    if lines.is_empty() {
+                        self.terms.remove(&word);
+                    }
+                }
+            }
+        }
+    }
+
+    /// When each indexed note was last modified, for `index_in_background` to skip unchanged ones
+    pub fn modification_times(&self) -> HashMap<PathBuf, Option<SystemTime>> {
+        self.notes.iter().map(|(id, note)| (self.paths[*id].clone(), note.modified)).collect()
+    }
+
+    /// Lines containing every word of `query`, the last of which may be typed only partly
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_words: Vec<String> = words(query).collect();
+        let (last, complete) = match query_words.split_last() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+
+        let mut matches: BTreeSet<(usize, usize)> = self.terms
+            .range(last.clone()..)
+            .take_while(|(term, _)| term.starts_with(last.as_str()))
+            .flat_map(|(_, lines)| lines.iter().copied())
+            .collect();
+        for word in complete {
+            match self.terms.get(word) {
+                Some(lines) => matches.retain(|entry| lines.contains(entry)),
+                None => return Vec::new(),
+            }
+        }
+
+        // Listed by path, not by the order notes happened to be indexed in
+        let mut matches: Vec<(&PathBuf, usize, usize)> = matches
+            .into_iter()
+            .map(|(id, line)| (&self.paths[id], id, line))
+            .collect();
+        matches.sort();
+        matches
+            .into_iter()
+            .take(limit)
+            .filter_map(|(path, id, line)| {
+                let text = self.notes.get(&id)?.lines.get(line)?.clone();
+                Some(SearchHit { path: path.clone(), line, text })
+            })
+            .collect()
+    }
+}
+
+/// Read the vault's notes on a background thread, sending them over in batches
+///
+/// Notes listed in `known` with their current modification time are skipped,
+/// and those no longer in the vault are reported as removed, so an empty
+/// `known` indexes every note and the times of an existing index refresh it.
+pub fn index_in_background(config: Config, known: HashMap<PathBuf, Option<SystemTime>>) -> Receiver<IndexUpdate> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let files = FileTree::collect_markdown_files(&config);
+        let current: HashSet<&PathBuf> = files.iter().collect();
+        let removed: Vec<PathBuf> = known.keys().filter(|path| !current.contains(path)).cloned().collect();
+        if !removed.is_empty() && sender.send(IndexUpdate::Removed(removed)).is_err() {
+            return;
+        }
+
+        let changed: Vec<(&PathBuf, Option<SystemTime>)> = files
+            .iter()
+            .map(|path| (path, fs::metadata(path).and_then(|metadata| metadata.modified()).ok()))
+            .filter(|(path, modified)| known.get(*path) != Some(modified))
+            .collect();
+        if sender.send(IndexUpdate::Total(changed.len())).is_err() {
+            return;
+        }
+        for chunk in changed.chunks(INDEX_BATCH_SIZE) {
+            let (mut notes, mut unreadable) = (Vec::new(), Vec::new());
+            for (path, modified) in chunk {
+                match fs::read_to_string(path) {
+                    Ok(content) => notes.push(((*path).clone(), content, *modified)),
+                    Err(_) => unreadable.push((*path).clone()),
+                }
+            }
+            // Stop once the app quit or switched vaults
+            if sender.send(IndexUpdate::Notes(notes)).is_err() {
+                return;
+            }
+            if !unreadable.is_empty() && sender.send(IndexUpdate::Removed(unreadable)).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+/// Lowercase words of `text`, split on anything that is not a letter or digit
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply every update the background indexer sends to `index`
+    fn run(index: &mut SearchIndex, config: &Config) {
+        for update in index_in_background(config.clone(), index.modification_times()) {
+            match update {
+                IndexUpdate::Total(_) => {}
+                IndexUpdate::Notes(notes) => {
+                    for (path, content, modified) in notes {
+                        index.insert(path, &content, modified);
+                    }
+                }
+                IndexUpdate::Removed(paths) => paths.iter().for_each(|path| index.remove(path)),
+            }
+        }
+    }
+
+    fn found(index: &SearchIndex, query: &str) -> Vec<(String, usize)> {
+        index
+            .search(query, 10)
+            .into_iter()
+            .map(|hit| (hit.path.file_name().unwrap().to_string_lossy().to_string(), hit.line))
+            .collect()
+    }
+
+    #[test]
+    fn lines_match_every_word_and_the_last_as_a_prefix() {
+        let mut index = SearchIndex::new();
+        index.insert(PathBuf::from("/v/b.md"), "Meeting notes\nother meeting", None);
+        index.insert(PathBuf::from("/v/a.md"), "notes from the meeting", None);
+        assert_eq!(found(&index, "meeting no"), [("a.md".to_string(), 0), ("b.md".to_string(), 0)]);
+        assert_eq!(found(&index, "MEET"), [("a.md".to_string(), 0), ("b.md".to_string(), 0), ("b.md".to_string(), 1)]);
+
+        index.insert(PathBuf::from("/v/a.md"), "rewritten", None);
+        assert_eq!(found(&index, "notes"), [("b.md".to_string(), 0)]);
+        index.remove(Path::new("/v/b.md"));
+        assert!(found(&index, "notes").is_empty());
+        assert_eq!(found(&index, "rewritten"), [("a.md".to_string(), 0)]);
+    }
+
+    #[test]
+    fn background_refresh_drops_deleted_and_ignored_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.root_directory = dir.path().to_path_buf();
+        fs::create_dir(dir.path().join("archive")).unwrap();
+        fs::write(dir.path().join("archive/old.md"), "apple").unwrap();
+        fs::write(dir.path().join("keep.md"), "apple").unwrap();
+        fs::write(dir.path().join("gone.md"), "apple").unwrap();
+
+        let mut index = SearchIndex::new();
+        run(&mut index, &config);
+        assert_eq!(found(&index, "apple").len(), 3);
+
+        fs::remove_file(dir.path().join("gone.md")).unwrap();
+        config.ignore_patterns = vec!["archive/**".to_string()];
+        run(&mut index, &config);
+        assert_eq!(found(&index, "apple"), [("keep.md".to_string(), 0)]);
+    }
+}
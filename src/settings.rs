@@ -0,0 +1,259 @@
+use serde_json::{Map, Value};
+
+/// How a setting is edited on the config screen
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    Text,
+    Bool,
+    Choice(&'static [&'static str]),
+}
+
+/// One row of the config screen
+#[derive(Debug, Clone)]
+pub struct ConfigField {
+    // Key in the config file; nested settings are joined with a dot
+    pub key: String,
+    pub label: String,
+    pub kind: FieldKind,
+    // Shown masked, e.g. passphrases
+    pub secret: bool,
+}
+
+/// Labels for the settings, in the order the config screen lists them
+///
+/// Settings missing here are still listed after these, labelled after their key.
+const FIELD_LABELS: &[(&str, &str)] = &[
+    ("root_directory", "Root Directory"),
+    ("editor", "Editor"),
+    ("git_enabled", "Git Enabled"),
+    ("git_repository", "Git Repository URL"),
+    ("git_username", "Git Username"),
+    ("git_email", "Git Email"),
+    ("git_remote_name", "Git Remote Name"),
+    ("git_branch", "Git Branch"),
+    ("git_ssh_key", "Git SSH Key"),
+    ("git_ssh_passphrase", "Git SSH Key Passphrase"),
+    ("pull_on_startup", "Pull On Startup"),
+    ("pull_before_push", "Pull Before Push"),
+    ("autocommit_interval_secs", "Autocommit Interval (seconds)"),
+    ("autocommit_push", "Push Automatic Commits"),
+    ("commit_message_template", "Commit Message Template"),
+    ("read_only", "Read-only"),
+    ("date_format", "Date Format"),
+    ("journal_path_pattern", "Journal Path Pattern"),
+    ("journal_template", "Journal Template"),
+    ("templates_directory", "Templates Directory"),
+    ("export_directory", "Export Directory"),
+    ("pdf_converter", "PDF Converter"),
+    ("max_words", "Word Limit"),
+    ("tab_width", "Tab Width"),
+    ("tree_width_percent", "Tree Width (%)"),
+    ("command_palette_key", "Command Palette Key"),
+    ("natural_sort", "Natural Sort"),
+    ("order_prefixes", "Order By Numeric Prefixes"),
+    ("show_note_counts", "Show Note Counts"),
+    ("show_line_numbers", "Show Line Numbers"),
+    ("follow_symlinks", "Follow Symlinks"),
+    ("ignore_patterns", "Ignore Patterns"),
+    ("monochrome", "Monochrome"),
+    ("inline_images", "Inline Images"),
+    ("encryption_enabled", "Encryption"),
+    ("clipboard_fallback", "Clipboard Fallback"),
+    ("large_note_threshold_kb", "Large Note Threshold (KB)"),
+    ("remember_recent_notes", "Remember Recent Notes"),
+    ("create_welcome_file", "Create Welcome File"),
+];
+
+/// Settings RNotes keeps track of itself, and `vaults`, which is edited in the config file
+const HIDDEN_FIELDS: &[&str] = &["vault_recent_notes", "welcome_file_deleted_in", "vaults"];
+
+const SECRET_FIELDS: &[&str] = &["git_ssh_passphrase"];
+
+/// Settings that take one of a fixed set of values
+const CHOICE_FIELDS: &[(&str, &[&str])] = &[("clipboard_fallback", &["osc52", "temp_file", "none"])];
+
+/// The rows of the config screen for `config`, a serialized `Config`
+///
+/// Nested settings get a row each; lists of anything but text are left to the
+/// config file.
+pub fn fields(config: &Value) -> Vec<ConfigField> {
+    let object = match config.as_object() {
+        Some(object) => object,
+        None => return Vec::new(),
+    };
+
+    let mut keys: Vec<&String> = object.keys().filter(|key| !HIDDEN_FIELDS.contains(&key.as_str())).collect();
+    let order = |key: &str| FIELD_LABELS.iter().position(|(known, _)| *known == key).unwrap_or(FIELD_LABELS.len());
+    keys.sort_by_key(|key| order(key));
+
+    let mut fields = Vec::new();
+    for key in keys {
+        match &object[key] {
+            Value::Object(nested) => {
+                for (nested_key, value) in nested {
+                    if let Some(kind) = field_kind(&format!("{}.{}", key, nested_key), value) {
+                        fields.push(field(format!("{}.{}", key, nested_key), kind));
+                    }
+                }
+            }
+            value => {
+                if let Some(kind) = field_kind(key, value) {
+                    fields.push(field(key.clone(), kind));
+                }
+            }
+        }
+    }
+    fields
+}
+
+fn field(key: String, kind: FieldKind) -> ConfigField {
+    let label = match FIELD_LABELS.iter().find(|(known, _)| *known == key) {
+        Some((_, label)) => label.to_string(),
+        None => label_from_key(&key),
+    };
+    ConfigField {
+        secret: SECRET_FIELDS.contains(&key.as_str()),
+        key,
+        label,
+        kind,
+    }
+}
+
+fn field_kind(key: &str, value: &Value) -> Option<FieldKind> {
+    if let Some((_, choices)) = CHOICE_FIELDS.iter().find(|(known, _)| *known == key) {
+        return Some(FieldKind::Choice(choices));
+    }
+    match value {
+        Value::Bool(_) => Some(FieldKind::Bool),
+        Value::Array(items) if items.iter().all(Value::is_string) => Some(FieldKind::Text),
+        Value::Array(_) | Value::Object(_) => None,
+        _ => Some(FieldKind::Text),
+    }
+}
+
+/// `markdown_flavor.smart_punctuation` becomes "Markdown flavor: Smart punctuation"
+fn label_from_key(key: &str) -> String {
+    key.split('.')
+        .map(|part| {
+            let words = part.replace('_', " ");
+            let mut chars = words.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(": ")
+}
+
+/// The value of the setting `key` in `config`
+pub fn get<'a>(config: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(config, |value, part| value.get(part))
+}
+
+/// Replace the value of the setting `key` in `config`
+pub fn set(config: &mut Value, key: &str, new_value: Value) {
+    let mut value = config;
+    for part in key.split('.') {
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        value = value.as_object_mut().unwrap().entry(part).or_insert(Value::Null);
+    }
+    *value = new_value;
+}
+
+/// How a setting is shown, and edited as text
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<String>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// Values typed text could stand for, most specific first
+///
+/// Which one fits is up to the type of the setting, so the caller tries each in turn.
+pub fn candidates(text: &str) -> Vec<Value> {
+    let text = text.trim();
+    if text.is_empty() {
+        return vec![Value::Null, Value::String(String::new()), Value::Array(Vec::new())];
+    }
+    let mut candidates = Vec::new();
+    if let Ok(number) = text.parse::<serde_json::Number>() {
+        candidates.push(Value::Number(number));
+    }
+    candidates.push(Value::String(text.to_string()));
+    candidates.push(Value::Array(
+        text.split(',')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| Value::String(item.to_string()))
+            .collect(),
+    ));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn draft() -> Value {
+        serde_json::to_value(Config::default()).unwrap()
+    }
+
+    // The first candidate the setting accepts, the way the config screen picks it
+    fn accepted(draft: &Value, key: &str, text: &str) -> Option<Value> {
+        candidates(text).into_iter().find(|value| {
+            let mut draft = draft.clone();
+            set(&mut draft, key, value.clone());
+            serde_json::from_value::<Config>(draft).is_ok()
+        })
+    }
+
+    #[test]
+    fn nested_settings_get_a_row_each() {
+        let fields = fields(&draft());
+        let flavor: Vec<&ConfigField> = fields.iter().filter(|field| field.key.starts_with("markdown_flavor.")).collect();
+        assert_eq!(flavor.len(), 4);
+        let punctuation = flavor.iter().find(|field| field.key == "markdown_flavor.smart_punctuation").unwrap();
+        assert_eq!(punctuation.label, "Markdown flavor: Smart punctuation");
+        assert_eq!(punctuation.kind, FieldKind::Bool);
+
+        // Known settings come first, in the order of FIELD_LABELS
+        assert_eq!(fields[0].key, "root_directory");
+        assert_eq!(fields[1].key, "editor");
+        let position = |key: &str| fields.iter().position(|field| field.key == key).unwrap();
+        assert!(position("create_welcome_file") < position("markdown_flavor.tasklists"));
+        assert!(!fields.iter().any(|field| HIDDEN_FIELDS.contains(&field.key.as_str())));
+    }
+
+    #[test]
+    fn nested_settings_are_set_in_place() {
+        let mut draft = draft();
+        set(&mut draft, "markdown_flavor.footnotes", Value::Bool(true));
+        assert_eq!(get(&draft, "markdown_flavor.footnotes"), Some(&Value::Bool(true)));
+        assert_eq!(get(&draft, "markdown_flavor.tasklists"), Some(&Value::Bool(false)));
+
+        let config: Config = serde_json::from_value(draft).unwrap();
+        assert!(config.markdown_flavor.footnotes);
+        assert!(!config.markdown_flavor.tasklists);
+    }
+
+    #[test]
+    fn typed_text_takes_the_type_of_the_setting() {
+        let draft = draft();
+        // A number typed into a text setting stays text
+        assert_eq!(accepted(&draft, "git_branch", "2024"), Some(Value::String("2024".to_string())));
+        assert_eq!(accepted(&draft, "tab_width", "2"), Some(Value::from(2)));
+        assert_eq!(accepted(&draft, "tab_width", "two"), None);
+        assert_eq!(accepted(&draft, "git_branch", " "), Some(Value::Null));
+        assert_eq!(
+            accepted(&draft, "ignore_patterns", "*.tmp, drafts/"),
+            Some(Value::Array(vec![Value::from("*.tmp"), Value::from("drafts/")]))
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+
+use crate::config::Config;
+use crate::file_tree::FileTree;
+use crate::frontmatter::{self, Frontmatter};
+
+/// Vault-wide index of `#tags` and the notes that use them
+pub struct TagIndex {
+    tags: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+impl TagIndex {
+    /// Scan every note in the vault and index its tags
+    pub fn build(config: &Config) -> Self {
+        let tag_regex = tag_regex();
+        let mut tags: BTreeMap<String, BTreeSet<PathBuf>> = BTreeMap::new();
+
+        for path in FileTree::collect_markdown_files(config) {
+            if let Ok(content) = fs::read_to_string(&path) {
+                for tag in extract_tags_with(&tag_regex, &content) {
+                    tags.entry(tag).or_default().insert(path.clone());
+                }
+            }
+        }
+
+        Self { tags }
+    }
+
+    /// All tags with the number of notes using each, sorted by name
+    pub fn tags_with_counts(&self) -> Vec<(String, usize)> {
+        self.tags
+            .iter()
+            .map(|(tag, files)| (tag.clone(), files.len()))
+            .collect()
+    }
+
+    pub fn files_for(&self, tag: &str) -> Vec<PathBuf> {
+        self.tags
+            .get(tag)
+            .map(|files| files.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+fn tag_regex() -> Regex {
+    // A tag starts with a letter so issue numbers like #12 are not picked up.
+    // Headings never match because they need whitespace after the hashes.
+    Regex::new(r"(?:^|[\s(\[,;])#([\p{L}_][\p{L}\p{N}_/-]*)").unwrap()
+}
+
+/// Extract the distinct `#tags` and frontmatter tags of a note, ignoring code blocks and inline code
+fn extract_tags_with(tag_regex: &Regex, content: &str) -> Vec<String> {
+    let mut tags = BTreeSet::new();
+    let mut in_code_block = false;
+
+    // Frontmatter `tags:` count as tags too
+    let body = match frontmatter::split(content) {
+        Some((yaml, body)) => match Frontmatter::parse(yaml) {
+            Some(frontmatter) => {
+                tags.extend(frontmatter.tags);
+                body
+            }
+            None => content,
+        },
+        None => content,
+    };
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        // Drop inline code spans before matching
+        let without_code: String = line
+            .split('`')
+            .enumerate()
+            .filter(|(i, _)| i % 2 == 0)
+            .map(|(_, part)| part)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        for captures in tag_regex.captures_iter(&without_code) {
+            tags.insert(captures[1].trim_end_matches(['/', '-']).to_string());
+        }
+    }
+
+    tags.into_iter().collect()
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Local};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::file_tree;
+
+/// Content used for new notes when no template is chosen
+pub const DEFAULT_TEMPLATE: &str = "# New Note\n\nWrite your notes here...\n";
+
+/// Content used for journal notes when no journal template is configured
+pub const DEFAULT_JOURNAL_TEMPLATE: &str = "# {{date}}\n\n";
+
+/// List the markdown templates available in `dir`, sorted by name
+pub fn list_templates(dir: &Path) -> Vec<PathBuf> {
+    let mut templates: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file() && file_tree::is_markdown_file(path)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    templates.sort();
+    templates
+}
+
+/// Substitute `{{title}}`, `{{date}}`, `{{time}}`, `{{year}}`, `{{month}}` and `{{day}}`
+///
+/// `{{date}}` uses `date_format` when given, `YYYY-MM-DD` otherwise.
+pub fn fill_placeholders(text: &str, title: &str, now: &DateTime<Local>, date_format: Option<&str>) -> String {
+    text.replace("{{title}}", title)
+        .replace("{{date}}", &now.format(date_format.unwrap_or("%Y-%m-%d")).to_string())
+        .replace("{{time}}", &now.format("%H:%M").to_string())
+        .replace("{{year}}", &now.format("%Y").to_string())
+        .replace("{{month}}", &now.format("%m").to_string())
+        .replace("{{day}}", &now.format("%d").to_string())
+}